@@ -1,3 +1,4 @@
+use crate::DEFAULT_CONFIG_FILE;
 use anyhow::Result;
 use autometrics_am::config::AmConfig;
 use clap::{Parser, Subcommand};
@@ -6,13 +7,21 @@ use std::path::PathBuf;
 use tracing::info;
 
 mod explore;
+mod functions;
 mod init;
 mod instrument;
 mod list;
 mod proxy;
+mod query;
+mod rules;
+mod snapshot;
 pub mod start;
 pub mod system;
 pub mod update;
+mod workspace;
+
+/// The URL of the web server of `am start` with the default listen address.
+const DEFAULT_AM_URL: &str = "http://127.0.0.1:6789";
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None, bin_name = "am")]
@@ -39,7 +48,7 @@ pub struct Application {
 pub enum SubCommands {
     /// Start scraping the specified endpoint(s), while also providing a web
     /// interface to inspect the autometrics data.
-    Start(start::CliArguments),
+    Start(Box<start::CliArguments>),
 
     /// Manage am related system settings. Such as cleaning up downloaded
     /// Prometheus, Pushgateway installs.
@@ -65,6 +74,23 @@ pub enum SubCommands {
     /// List the functions in a project
     List(list::Arguments),
 
+    /// Generate Prometheus rules for the objectives declared in a project
+    Rules(rules::Arguments),
+
+    /// Show the request rate, error ratio and latency of the instrumented
+    /// functions in a project
+    Functions(functions::Arguments),
+
+    /// Evaluate a PromQL query and print the result
+    Query(query::Arguments),
+
+    /// Create a snapshot of the data of the running `am start` instance,
+    /// together with its Prometheus config and rules
+    Snapshot(snapshot::Arguments),
+
+    /// Manage the workspaces that keep the data of `am start --workspace`
+    Workspace(workspace::Arguments),
+
     /// Instrument a project entirely.
     ///
     /// IMPORTANT: This will add code in your files! If you want to easily
@@ -79,8 +105,13 @@ pub enum SubCommands {
 
 pub async fn handle_command(app: Application, config: AmConfig, mp: MultiProgress) -> Result<()> {
     match app.command {
-        SubCommands::Start(args) => start::handle_command(args, config, mp).await,
-        SubCommands::System(args) => system::handle_command(args, mp).await,
+        SubCommands::Start(args) => {
+            let config_file = app
+                .config_file
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_FILE));
+            start::handle_command(*args, config, config_file, mp).await
+        }
+        SubCommands::System(args) => system::handle_command(args, config, mp).await,
         SubCommands::Explore(args) => explore::handle_command(args).await,
         SubCommands::Proxy(args) => proxy::handle_command(args).await,
         SubCommands::Init(args) => init::handle_command(args).await,
@@ -95,6 +126,11 @@ pub async fn handle_command(app: Application, config: AmConfig, mp: MultiProgres
         }
         SubCommands::Update(args) => update::handle_command(args, mp).await,
         SubCommands::List(args) => list::handle_command(args),
+        SubCommands::Rules(args) => rules::handle_command(args),
+        SubCommands::Functions(args) => functions::handle_command(args).await,
+        SubCommands::Query(args) => query::handle_command(args).await,
+        SubCommands::Snapshot(args) => snapshot::handle_command(args, mp).await,
+        SubCommands::Workspace(args) => workspace::handle_command(args),
         SubCommands::Instrument(args) => instrument::handle_command(args),
         SubCommands::MarkdownHelp => {
             let disable_toc = true;
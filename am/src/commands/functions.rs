@@ -0,0 +1,420 @@
+use super::query::{format_value, instant_query, resolve_prometheus_url};
+use am_list::{FunctionId, FunctionInfo};
+use anyhow::{bail, Result};
+use autometrics_am::prometheus::QueryData;
+use clap::{Parser, ValueEnum};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use url::Url;
+
+/// Matches the call counters of all autometrics libraries, which use
+/// slightly different names depending on the version and exporter.
+const CALLS_SELECTOR: &str = r#"__name__=~"function_calls(_count)?(_total)?""#;
+
+/// Matches the latency histogram buckets of all autometrics libraries.
+const DURATION_BUCKETS_SELECTOR: &str = r#"__name__=~"function_calls_duration(_seconds)?_bucket""#;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Arguments {
+    /// The directory containing the project(s) to list the functions of.
+    #[clap(default_value = ".")]
+    root: PathBuf,
+
+    /// The column to sort by. Metrics are sorted from high to low, names
+    /// alphabetically.
+    #[clap(short, long, value_enum, default_value_t = SortBy::Rate)]
+    sort: SortBy,
+
+    /// Only show functions whose module or name contains this text.
+    #[clap(short, long)]
+    filter: Option<String>,
+
+    /// Only show functions that have never been called.
+    #[clap(long)]
+    never_called: bool,
+
+    /// The window over which the rates and latencies are calculated.
+    #[clap(long, default_value = "5m", value_parser = humantime::parse_duration)]
+    window: Duration,
+
+    /// The URL of the web server of the running `am start` instance, whose
+    /// Prometheus is queried.
+    #[clap(long, env, default_value = super::DEFAULT_AM_URL)]
+    am_url: Url,
+
+    /// Query this Prometheus instead of the one of `am start`.
+    #[clap(long, env, alias = "prometheus-address")]
+    prometheus_url: Option<Url>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum SortBy {
+    Module,
+    Function,
+    #[default]
+    Rate,
+    Errors,
+    P95,
+    P99,
+}
+
+pub async fn handle_command(args: Arguments) -> Result<()> {
+    let functions: Vec<FunctionInfo> = am_list::list_all_project_functions(&args.root)?
+        .into_values()
+        .flat_map(|(_, functions)| functions)
+        .collect();
+
+    if functions.is_empty() {
+        bail!("No instrumented functions found in {}", args.root.display());
+    }
+
+    let prometheus_url = resolve_prometheus_url(&args.am_url, args.prometheus_url)?;
+    let metrics = FunctionMetrics::fetch(&prometheus_url, args.window).await?;
+
+    let mut reports = join_metrics(functions, &metrics);
+    let total = reports.len();
+    let never_called = reports.iter().filter(|r| r.stats.is_none()).count();
+
+    let filter = args.filter.as_deref().map(str::to_lowercase);
+    reports.retain(|report| {
+        let matches_filter = match &filter {
+            Some(filter) => {
+                report.id.module.to_lowercase().contains(filter)
+                    || report.id.function.to_lowercase().contains(filter)
+            }
+            None => true,
+        };
+
+        matches_filter && (!args.never_called || report.stats.is_none())
+    });
+    sort_reports(&mut reports, args.sort);
+
+    let mut stdout = io::stdout().lock();
+    write_table(&mut stdout, &reports)?;
+    writeln!(
+        stdout,
+        "\n{never_called} of {total} instrumented functions have never been called"
+    )?;
+
+    Ok(())
+}
+
+/// The metrics of all functions that Prometheus has recorded calls for.
+#[derive(Debug, Default)]
+pub(crate) struct FunctionMetrics {
+    /// The total amount of calls.
+    calls: HashMap<FunctionId, f64>,
+    /// The calls per second.
+    rate: HashMap<FunctionId, f64>,
+    /// The failed calls per second.
+    error_rate: HashMap<FunctionId, f64>,
+    /// The 95th and 99th percentile latency, in seconds.
+    p95: HashMap<FunctionId, f64>,
+    p99: HashMap<FunctionId, f64>,
+}
+
+impl FunctionMetrics {
+    pub(crate) async fn fetch(prometheus_url: &Url, window: Duration) -> Result<Self> {
+        let window = format!("{}s", window.as_secs().max(1));
+        let rate =
+            |selector: &str| format!("sum by (function, module) (rate({{{selector}}}[{window}]))");
+        let quantile = |quantile: &str| {
+            format!(
+                "histogram_quantile({quantile}, sum by (le, function, module) (rate({{{DURATION_BUCKETS_SELECTOR}}}[{window}])))"
+            )
+        };
+
+        let calls_query = format!("sum by (function, module) ({{{CALLS_SELECTOR}}})");
+        let rate_query = rate(CALLS_SELECTOR);
+        let error_rate_query = rate(&format!(r#"{CALLS_SELECTOR},result="error""#));
+        let p95_query = quantile("0.95");
+        let p99_query = quantile("0.99");
+
+        let (calls, rate, error_rate, p95, p99) = tokio::try_join!(
+            instant_query(prometheus_url, &calls_query),
+            instant_query(prometheus_url, &rate_query),
+            instant_query(prometheus_url, &error_rate_query),
+            instant_query(prometheus_url, &p95_query),
+            instant_query(prometheus_url, &p99_query),
+        )?;
+
+        Ok(Self {
+            calls: by_function(calls),
+            rate: by_function(rate),
+            error_rate: by_function(error_rate),
+            p95: by_function(p95),
+            p99: by_function(p99),
+        })
+    }
+
+    /// The reports of all functions that have been called, without their
+    /// location.
+    pub(crate) fn reports(&self) -> Vec<FunctionReport> {
+        self.calls
+            .iter()
+            .filter(|(_, calls)| **calls > 0.0)
+            .map(|(id, _)| FunctionReport {
+                id: id.clone(),
+                location: None,
+                stats: self.stats(id),
+            })
+            .collect()
+    }
+
+    /// The stats of function `id`, or `None` if it has never been called.
+    fn stats(&self, id: &FunctionId) -> Option<FunctionStats> {
+        if !self.calls.get(id).is_some_and(|calls| *calls > 0.0) {
+            return None;
+        }
+
+        let get = |values: &HashMap<FunctionId, f64>| values.get(id).copied().unwrap_or(f64::NAN);
+        let rate = get(&self.rate);
+        let error_rate = self.error_rate.get(id).copied().unwrap_or(0.0);
+
+        Some(FunctionStats {
+            rate,
+            error_ratio: if rate > 0.0 {
+                error_rate / rate
+            } else {
+                f64::NAN
+            },
+            p95: get(&self.p95),
+            p99: get(&self.p99),
+        })
+    }
+}
+
+/// Index the values of an instant vector by the `module` and `function`
+/// labels.
+fn by_function(data: QueryData) -> HashMap<FunctionId, f64> {
+    let QueryData::Vector(series) = data else {
+        return HashMap::new();
+    };
+
+    series
+        .into_iter()
+        .map(|series| {
+            let label = |name: &str| series.metric.get(name).cloned().unwrap_or_default();
+            let id = FunctionId {
+                module: label("module"),
+                function: label("function"),
+            };
+            (id, series.value.value())
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FunctionReport {
+    pub(crate) id: FunctionId,
+    /// The location of the definition, as `file:line`.
+    pub(crate) location: Option<String>,
+    /// `None` if Prometheus has not recorded any calls of the function.
+    pub(crate) stats: Option<FunctionStats>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FunctionStats {
+    pub(crate) rate: f64,
+    pub(crate) error_ratio: f64,
+    pub(crate) p95: f64,
+    pub(crate) p99: f64,
+}
+
+/// Combine the functions found in the code with their metrics. Functions
+/// that are found more than once are only included once.
+fn join_metrics(functions: Vec<FunctionInfo>, metrics: &FunctionMetrics) -> Vec<FunctionReport> {
+    let functions: BTreeMap<FunctionId, FunctionInfo> = functions
+        .into_iter()
+        .map(|function| (function.id.clone(), function))
+        .collect();
+
+    functions
+        .into_values()
+        .map(|function| FunctionReport {
+            location: function.definition.map(|definition| {
+                format!("{}:{}", definition.file, definition.range.start.line + 1)
+            }),
+            stats: metrics.stats(&function.id),
+            id: function.id,
+        })
+        .collect()
+}
+
+/// Sort the metrics from high to low, with the functions that have no value
+/// last, and the names alphabetically.
+pub(crate) fn sort_reports(reports: &mut [FunctionReport], sort: SortBy) {
+    let metric = |report: &FunctionReport| {
+        let stats = report.stats.as_ref()?;
+        let value = match sort {
+            SortBy::Rate => stats.rate,
+            SortBy::Errors => stats.error_ratio,
+            SortBy::P95 => stats.p95,
+            SortBy::P99 => stats.p99,
+            SortBy::Module | SortBy::Function => return None,
+        };
+        (!value.is_nan()).then_some(value)
+    };
+
+    reports.sort_by(|a, b| match sort {
+        SortBy::Module => a.id.cmp(&b.id),
+        SortBy::Function => (&a.id.function, &a.id.module).cmp(&(&b.id.function, &b.id.module)),
+        _ => match (metric(a), metric(b)) {
+            (Some(a), Some(b)) => b.total_cmp(&a),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => a.id.cmp(&b.id),
+        },
+    });
+}
+
+fn write_table(out: &mut impl Write, reports: &[FunctionReport]) -> Result<()> {
+    let rows: Vec<[String; 8]> = reports
+        .iter()
+        .map(|report| {
+            let location = report.location.clone().unwrap_or_default();
+            let [rate, errors, p95, p99, note] = match &report.stats {
+                Some(stats) => [
+                    format!("{}/s", format_value(stats.rate)),
+                    format_ratio(stats.error_ratio),
+                    format_latency(stats.p95),
+                    format_latency(stats.p99),
+                    String::new(),
+                ],
+                None => [
+                    "-".to_string(),
+                    "-".to_string(),
+                    "-".to_string(),
+                    "-".to_string(),
+                    "never called".to_string(),
+                ],
+            };
+
+            [
+                report.id.module.clone(),
+                report.id.function.clone(),
+                location,
+                rate,
+                errors,
+                p95,
+                p99,
+                note,
+            ]
+        })
+        .collect();
+
+    let header = [
+        "MODULE", "FUNCTION", "LOCATION", "RATE", "ERRORS", "P95", "P99", "",
+    ];
+    let widths: Vec<usize> = header
+        .iter()
+        .enumerate()
+        .map(|(i, header)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or_default()
+                .max(header.len())
+        })
+        .collect();
+
+    let header = header.map(ToString::to_string);
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (cell, width))| match i {
+                // The metrics are aligned to the right.
+                3..=6 => format!("{cell:>width$}"),
+                _ => format!("{cell:<width$}"),
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(out, "{}", line.trim_end())?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn format_ratio(ratio: f64) -> String {
+    if ratio.is_nan() {
+        "-".to_string()
+    } else {
+        format!("{:.2}%", ratio * 100.0)
+    }
+}
+
+pub(crate) fn format_latency(seconds: f64) -> String {
+    if !seconds.is_finite() {
+        "-".to_string()
+    } else if seconds < 1.0 {
+        format!("{:.1}ms", seconds * 1000.0)
+    } else {
+        format!("{seconds:.2}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use am_list::{Location, Position, Range};
+
+    fn function(module: &str, function: &str, line: usize) -> FunctionInfo {
+        FunctionInfo {
+            id: (module, function).into(),
+            definition: Some(Location {
+                file: "src/api.rs".to_string(),
+                range: Range {
+                    start: Position { line, column: 0 },
+                    end: Position { line, column: 10 },
+                },
+            }),
+            instrumentation: None,
+        }
+    }
+
+    #[test]
+    fn functions_are_joined_with_metrics() {
+        let list_users = FunctionId::from(("api", "list_users"));
+        let create_user = FunctionId::from(("api", "create_user"));
+        let metrics = FunctionMetrics {
+            calls: HashMap::from([(list_users.clone(), 1200.0), (create_user.clone(), 10.0)]),
+            rate: HashMap::from([(list_users.clone(), 4.0), (create_user.clone(), 0.5)]),
+            error_rate: HashMap::from([(create_user.clone(), 0.05)]),
+            p95: HashMap::from([(list_users.clone(), 0.0123), (create_user.clone(), 1.5)]),
+            p99: HashMap::from([(list_users, 0.25)]),
+        };
+
+        let functions = vec![
+            function("api", "list_users", 9),
+            function("api", "create_user", 41),
+            function("api", "delete_user", 80),
+            // Functions are found more than once if they are instrumented
+            // in multiple ways.
+            function("api", "list_users", 9),
+        ];
+
+        let mut reports = join_metrics(functions, &metrics);
+        assert_eq!(reports.len(), 3);
+
+        sort_reports(&mut reports, SortBy::Errors);
+        let names: Vec<&str> = reports.iter().map(|r| r.id.function.as_str()).collect();
+        assert_eq!(names, vec!["create_user", "list_users", "delete_user"]);
+
+        let mut out = vec![];
+        write_table(&mut out, &reports).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "MODULE  FUNCTION     LOCATION        RATE  ERRORS     P95      P99
+api     create_user  src/api.rs:42  0.5/s  10.00%   1.50s        -
+api     list_users   src/api.rs:10    4/s   0.00%  12.3ms  250.0ms
+api     delete_user  src/api.rs:81      -       -       -        -  never called
+"
+        );
+    }
+}
@@ -1,6 +1,6 @@
 use crate::interactive::{confirm, confirm_optional, user_input, user_input_optional};
 use anyhow::{bail, Context, Result};
-use autometrics_am::config::{AmConfig, Endpoint};
+use autometrics_am::config::{AmConfig, Endpoint, ScrapeOptions};
 use clap::Parser;
 use std::fs;
 use std::path::PathBuf;
@@ -42,6 +42,7 @@ pub async fn handle_command(args: Arguments) -> Result<()> {
         },
         pushgateway_enabled,
         prometheus_scrape_interval: scrape_interval,
+        ..Default::default()
     };
 
     let config = toml::to_string(&cfg)?;
@@ -59,9 +60,11 @@ fn prompt_endpoint() -> Result<Endpoint> {
 
     Ok(Endpoint {
         url: Url::parse(&endpoint)?,
+        additional_urls: vec![],
         job_name,
         honor_labels,
         prometheus_scrape_interval: scrape_interval,
+        scrape_options: ScrapeOptions::default(),
     })
 }
 
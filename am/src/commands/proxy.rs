@@ -1,4 +1,7 @@
-use crate::server::start_web_server;
+use crate::commands::start::ports::{
+    DEFAULT_ALERTMANAGER_PORT, DEFAULT_PROMETHEUS_PORT, DEFAULT_PUSHGATEWAY_PORT,
+};
+use crate::server::{start_web_server, WebServerOptions};
 use crate::terminal;
 use anyhow::{bail, Context, Result};
 use clap::Parser;
@@ -71,19 +74,27 @@ pub async fn handle_command(args: CliArguments) -> Result<()> {
 
     // Start web server for hosting the explorer, am api and proxies to the enabled services.
     let web_server_task = async move {
-        start_web_server(
-            &args.listen_address,
-            false,
-            false,
-            args.prometheus_url,
-            args.static_assets_url,
-            tx,
-            urls_tx,
-        )
-        .await
+        let options = WebServerOptions {
+            listen_address: args.listen_address,
+            enable_prometheus: false,
+            enable_pushgateway: false,
+            enable_alertmanager: false,
+            prometheus_port: DEFAULT_PROMETHEUS_PORT,
+            pushgateway_port: DEFAULT_PUSHGATEWAY_PORT,
+            alertmanager_port: DEFAULT_ALERTMANAGER_PORT,
+            prometheus_proxy_url: args.prometheus_url,
+            static_assets_url: args.static_assets_url,
+            config_manager: None,
+            log_buffers: None,
+            alert_history: None,
+            snapshot_source: None,
+            target_monitor: None,
+        };
+
+        start_web_server(options, tx, urls_tx).await
     };
 
-    terminal::wait_and_print_urls(urls_rx);
+    terminal::wait_and_print_urls(urls_rx, None);
 
     select! {
         biased;
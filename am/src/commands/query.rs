@@ -0,0 +1,471 @@
+use crate::commands::start::CLIENT;
+use anyhow::{bail, Context, Result};
+use autometrics_am::prometheus::{QueryData, QueryResponse, Sample};
+use clap::{Parser, ValueEnum};
+use itertools::Itertools;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// The characters used to draw sparklines, from low to high.
+const SPARKLINE_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The maximum width of a sparkline. Longer series are averaged into this
+/// many buckets.
+const SPARKLINE_WIDTH: usize = 60;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Arguments {
+    /// The PromQL query to evaluate.
+    query: String,
+
+    /// Evaluate the query over this range up until now, for example `1h`,
+    /// instead of only at the current time.
+    #[clap(long, value_parser = humantime::parse_duration)]
+    range: Option<Duration>,
+
+    /// The resolution of a range query. Defaults to a sixtieth of the range.
+    #[clap(long, value_parser = humantime::parse_duration, requires = "range")]
+    step: Option<Duration>,
+
+    /// How to print the result. Range vectors are shown as sparklines in the
+    /// table.
+    #[clap(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+
+    /// The URL of the web server of the running `am start` instance, whose
+    /// Prometheus is queried.
+    #[clap(long, env, default_value = super::DEFAULT_AM_URL)]
+    am_url: Url,
+
+    /// Query this Prometheus instead of the one of `am start`.
+    #[clap(long, env, alias = "prometheus-address")]
+    prometheus_url: Option<Url>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+pub async fn handle_command(args: Arguments) -> Result<()> {
+    let prometheus_url = resolve_prometheus_url(&args.am_url, args.prometheus_url)?;
+
+    let data = match args.range {
+        Some(range) => {
+            let step = args
+                .step
+                .unwrap_or_else(|| (range / 60).max(Duration::from_secs(1)));
+            let end = SystemTime::now();
+            let start = end
+                .checked_sub(range)
+                .filter(|start| *start >= UNIX_EPOCH)
+                .with_context(|| {
+                    format!(
+                        "The range of {} starts before 1970, use a shorter range",
+                        humantime::format_duration(range)
+                    )
+                })?;
+            range_query(&prometheus_url, &args.query, start, end, step).await?
+        }
+        None => instant_query(&prometheus_url, &args.query).await?,
+    };
+
+    let mut stdout = io::stdout().lock();
+    match args.output {
+        OutputFormat::Table => write_table(&mut stdout, &data)?,
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut stdout, &data)?;
+            writeln!(stdout)?;
+        }
+        OutputFormat::Csv => write_csv(&mut stdout, &data)?,
+    }
+
+    Ok(())
+}
+
+/// The Prometheus to query: either the one provided by the user, or the one
+/// of `am start` that is proxied on `/prometheus`.
+pub(crate) fn resolve_prometheus_url(am_url: &Url, prometheus_url: Option<Url>) -> Result<Url> {
+    match prometheus_url {
+        Some(url) => Ok(url),
+        None => Ok(am_url.join("prometheus")?),
+    }
+}
+
+/// Evaluate `query` at the current time.
+pub(crate) async fn instant_query(prometheus_url: &Url, query: &str) -> Result<QueryData> {
+    request(
+        prometheus_url,
+        "api/v1/query",
+        &[("query", query.to_string())],
+    )
+    .await
+}
+
+/// Evaluate `query` at every `step` between `start` and `end`.
+pub(crate) async fn range_query(
+    prometheus_url: &Url,
+    query: &str,
+    start: SystemTime,
+    end: SystemTime,
+    step: Duration,
+) -> Result<QueryData> {
+    let params = [
+        ("query", query.to_string()),
+        ("start", unix_seconds(start).to_string()),
+        ("end", unix_seconds(end).to_string()),
+        ("step", step.as_secs_f64().to_string()),
+    ];
+
+    request(prometheus_url, "api/v1/query_range", &params).await
+}
+
+async fn request(prometheus_url: &Url, path: &str, params: &[(&str, String)]) -> Result<QueryData> {
+    // Make sure that the path is appended to the URL, instead of replacing
+    // its last segment, such as `/prometheus`.
+    let mut base_url = prometheus_url.clone();
+    if !base_url.path().ends_with('/') {
+        base_url.set_path(&format!("{}/", base_url.path()));
+    }
+
+    let response = CLIENT
+        .get(base_url.join(path)?)
+        .query(params)
+        .send()
+        .await
+        .with_context(|| format!("Unable to reach Prometheus at {prometheus_url}"))?;
+
+    // Prometheus describes invalid queries in the body of the error response.
+    let status = response.status();
+    let body = response.text().await?;
+    match serde_json::from_str(&body) {
+        Ok(QueryResponse::Success { data }) => Ok(data),
+        Ok(QueryResponse::Error { error_type, error }) => {
+            bail!("Prometheus returned an error ({error_type}): {error}")
+        }
+        Err(_) => bail!("Prometheus responded with {status}: {body}"),
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+/// Format the labels of a series the way Prometheus does, for example
+/// `function_calls_total{function="list", module="api"}`.
+pub(crate) fn format_series(metric: &BTreeMap<String, String>) -> String {
+    let name = metric
+        .get("__name__")
+        .map(String::as_str)
+        .unwrap_or_default();
+    let labels = metric
+        .iter()
+        .filter(|(label, _)| *label != "__name__")
+        .map(|(label, value)| format!("{label}={value:?}"))
+        .join(", ");
+
+    if labels.is_empty() && !name.is_empty() {
+        name.to_string()
+    } else {
+        format!("{name}{{{labels}}}")
+    }
+}
+
+/// Format `value` with at most four decimals, using scientific notation for
+/// values that would otherwise be rounded to zero.
+pub(crate) fn format_value(value: f64) -> String {
+    if value.is_finite() && value != 0.0 && value.abs() < 0.001 {
+        return format!("{value:.3e}");
+    }
+
+    let formatted = format!("{value:.4}");
+    if formatted.contains('.') {
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    } else {
+        formatted
+    }
+}
+
+/// Draw `values` as a sparkline that is at most `width` characters wide.
+/// Values that are not finite are drawn as a space.
+fn sparkline(values: &[f64], width: usize) -> String {
+    let buckets: Vec<f64> = if values.len() <= width {
+        values.to_vec()
+    } else {
+        (0..width)
+            .map(|i| {
+                let bucket = &values[i * values.len() / width..(i + 1) * values.len() / width];
+                let finite: Vec<f64> = bucket.iter().copied().filter(|v| v.is_finite()).collect();
+                if finite.is_empty() {
+                    f64::NAN
+                } else {
+                    finite.iter().sum::<f64>() / finite.len() as f64
+                }
+            })
+            .collect()
+    };
+
+    let finite = buckets.iter().copied().filter(|v| v.is_finite());
+    let min = finite.clone().fold(f64::INFINITY, f64::min);
+    let max = finite.fold(f64::NEG_INFINITY, f64::max);
+
+    buckets
+        .iter()
+        .map(|value| {
+            if !value.is_finite() {
+                ' '
+            } else if max == min {
+                SPARKLINE_CHARS[SPARKLINE_CHARS.len() / 2]
+            } else {
+                let level = (value - min) / (max - min) * (SPARKLINE_CHARS.len() - 1) as f64;
+                SPARKLINE_CHARS[level.round() as usize]
+            }
+        })
+        .collect()
+}
+
+fn write_table(out: &mut impl Write, data: &QueryData) -> Result<()> {
+    match data {
+        QueryData::Vector(series) if series.is_empty() => writeln!(out, "No results")?,
+        QueryData::Matrix(series) if series.is_empty() => writeln!(out, "No results")?,
+        QueryData::Vector(series) => {
+            let rows: Vec<(String, String)> = series
+                .iter()
+                .map(|series| {
+                    (
+                        format_series(&series.metric),
+                        format_value(series.value.value()),
+                    )
+                })
+                .collect();
+            let width = column_width("SERIES", rows.iter().map(|(series, _)| series));
+
+            writeln!(out, "{:<width$}  VALUE", "SERIES")?;
+            for (series, value) in rows {
+                writeln!(out, "{series:<width$}  {value}")?;
+            }
+        }
+        QueryData::Matrix(series) => {
+            let names: Vec<String> = series
+                .iter()
+                .map(|series| format_series(&series.metric))
+                .collect();
+            let width = column_width("SERIES", names.iter());
+            let trend_width = series
+                .iter()
+                .map(|series| series.values.len().min(SPARKLINE_WIDTH))
+                .max()
+                .unwrap_or_default()
+                .max("TREND".len());
+
+            writeln!(
+                out,
+                "{:<width$}  {:<trend_width$}  {:>10}  {:>10}  {:>10}",
+                "SERIES", "TREND", "MIN", "MAX", "LAST"
+            )?;
+            for (name, series) in names.iter().zip(series) {
+                let values: Vec<f64> = series.values.iter().map(Sample::value).collect();
+                let finite = values.iter().copied().filter(|v| v.is_finite());
+                let min = finite.clone().fold(f64::NAN, f64::min);
+                let max = finite.fold(f64::NAN, f64::max);
+                let last = values.last().copied().unwrap_or(f64::NAN);
+
+                writeln!(
+                    out,
+                    "{name:<width$}  {:<trend_width$}  {:>10}  {:>10}  {:>10}",
+                    sparkline(&values, SPARKLINE_WIDTH),
+                    format_value(min),
+                    format_value(max),
+                    format_value(last)
+                )?;
+            }
+        }
+        QueryData::Scalar(sample) | QueryData::String(sample) => writeln!(out, "{}", sample.1)?,
+    }
+
+    Ok(())
+}
+
+fn column_width<'a>(header: &str, values: impl Iterator<Item = &'a String>) -> usize {
+    values
+        .map(|value| value.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(header.len())
+}
+
+/// Write the result as CSV, with a column for every label and one row per
+/// sample.
+fn write_csv(out: &mut impl Write, data: &QueryData) -> Result<()> {
+    let rows: Vec<(&BTreeMap<String, String>, &Sample)> = match data {
+        QueryData::Vector(series) => series
+            .iter()
+            .map(|series| (&series.metric, &series.value))
+            .collect(),
+        QueryData::Matrix(series) => series
+            .iter()
+            .flat_map(|series| series.values.iter().map(|sample| (&series.metric, sample)))
+            .collect(),
+        QueryData::Scalar(sample) | QueryData::String(sample) => {
+            writeln!(out, "timestamp,value")?;
+            writeln!(out, "{},{}", sample.0, csv_field(&sample.1))?;
+            return Ok(());
+        }
+    };
+
+    let labels: BTreeSet<&String> = rows.iter().flat_map(|(metric, _)| metric.keys()).collect();
+
+    let header = labels
+        .iter()
+        .map(|label| csv_field(label))
+        .chain(["timestamp".to_string(), "value".to_string()])
+        .join(",");
+    writeln!(out, "{header}")?;
+
+    for (metric, sample) in rows {
+        let row = labels
+            .iter()
+            .map(|label| csv_field(metric.get(*label).map(String::as_str).unwrap_or_default()))
+            .chain([sample.0.to_string(), csv_field(&sample.1)])
+            .join(",");
+        writeln!(out, "{row}")?;
+    }
+
+    Ok(())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Query;
+    use axum::routing::get;
+    use axum::{Router, Server};
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+
+    const VECTOR: &str = r#"{
+        "status": "success",
+        "data": {
+            "resultType": "vector",
+            "result": [
+                {
+                    "metric": { "__name__": "function_calls_total", "function": "list_users", "module": "api" },
+                    "value": [1700000000.123, "42"]
+                },
+                {
+                    "metric": { "function": "create_user", "description": "Create, \"quickly\"" },
+                    "value": [1700000000.123, "0.000012"]
+                }
+            ]
+        }
+    }"#;
+
+    const MATRIX: &str = r#"{
+        "status": "success",
+        "data": {
+            "resultType": "matrix",
+            "result": [
+                {
+                    "metric": { "function": "list_users" },
+                    "values": [[1700000000, "1"], [1700000015, "2"], [1700000030, "NaN"], [1700000045, "4"]]
+                }
+            ]
+        }
+    }"#;
+
+    fn parse(response: &str) -> QueryData {
+        match serde_json::from_str(response).unwrap() {
+            QueryResponse::Success { data } => data,
+            QueryResponse::Error { error, .. } => panic!("unexpected error: {error}"),
+        }
+    }
+
+    fn render(write: fn(&mut Vec<u8>, &QueryData) -> Result<()>, data: &QueryData) -> String {
+        let mut out = vec![];
+        write(&mut out, data).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn results_are_rendered() {
+        let vector = parse(VECTOR);
+        assert_eq!(
+            render(write_table, &vector),
+            r#"SERIES                                                       VALUE
+function_calls_total{function="list_users", module="api"}    42
+{description="Create, \"quickly\"", function="create_user"}  1.200e-5
+"#
+        );
+        assert_eq!(
+            render(write_csv, &vector),
+            r#"__name__,description,function,module,timestamp,value
+function_calls_total,,list_users,api,1700000000.123,42
+,"Create, ""quickly""",create_user,,1700000000.123,0.000012
+"#
+        );
+
+        let matrix = parse(MATRIX);
+        assert_eq!(
+            render(write_table, &matrix),
+            "SERIES                   TREND         MIN         MAX        LAST
+{function=\"list_users\"}  ▁▃ █            1           4           4
+"
+        );
+        assert_eq!(
+            render(write_csv, &matrix).lines().nth(3),
+            Some("list_users,1700000030,NaN")
+        );
+
+        assert_eq!(sparkline(&[5.0, 5.0], 60), "▅▅");
+        assert_eq!(sparkline(&[0.0, 2.0, 4.0, 6.0], 2), "▁█");
+    }
+
+    #[tokio::test]
+    async fn queries_are_sent_to_the_prometheus_path() {
+        let app = Router::new().route(
+            "/prometheus/api/v1/query",
+            get(|params: Query<HashMap<String, String>>| async move {
+                if params["query"] == "up" {
+                    VECTOR.to_string()
+                } else {
+                    r#"{"status":"error","errorType":"bad_data","error":"parse error"}"#.to_string()
+                }
+            }),
+        );
+        let server =
+            Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(app.into_make_service());
+        let url = Url::parse(&format!("http://{}/prometheus", server.local_addr())).unwrap();
+        tokio::spawn(server);
+
+        let QueryData::Vector(series) = instant_query(&url, "up").await.unwrap() else {
+            panic!("expected an instant vector");
+        };
+        assert_eq!(series.len(), 2);
+
+        let err = instant_query(&url, "up{")
+            .await
+            .expect_err("query is invalid");
+        assert_eq!(
+            err.to_string(),
+            "Prometheus returned an error (bad_data): parse error"
+        );
+    }
+}
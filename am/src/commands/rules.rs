@@ -0,0 +1,24 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+
+pub mod generate;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Arguments {
+    #[command(subcommand)]
+    pub command: SubCommands,
+}
+
+#[derive(Subcommand)]
+pub enum SubCommands {
+    /// Generate a Prometheus rules file for the objectives that are declared
+    /// in a project.
+    Generate(generate::Arguments),
+}
+
+pub fn handle_command(args: Arguments) -> Result<()> {
+    match args.command {
+        SubCommands::Generate(args) => generate::handle_command(args),
+    }
+}
@@ -0,0 +1,305 @@
+use am_list::ObjectiveInfo;
+use anyhow::{bail, Context, Result};
+use autometrics_am::prometheus::{Rule, RuleFile, RuleGroup};
+use clap::Parser;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+/// The windows over which the error ratios are recorded.
+const WINDOWS: &[&str] = &["5m", "30m", "1h", "2h", "6h", "1d", "3d"];
+
+/// The burn rates that trigger an alert, following the multiwindow,
+/// multi-burn-rate approach of the Google SRE workbook. An alert fires if the
+/// error budget is consumed `factor` times faster than allowed, over both the
+/// long and the short window.
+const BURN_RATES: &[BurnRate] = &[
+    BurnRate::new("page", "1h", "5m", "14.4"),
+    BurnRate::new("page", "6h", "30m", "6"),
+    BurnRate::new("ticket", "1d", "2h", "3"),
+    BurnRate::new("ticket", "3d", "6h", "1"),
+];
+
+const HEADER: &str = "# Generated by `am rules generate`. Load it using `am start --rules-file`.\n";
+
+#[derive(Parser, Clone)]
+pub struct Arguments {
+    /// The directory containing the project(s) to generate the rules for.
+    #[clap(default_value = ".")]
+    root: PathBuf,
+
+    /// Write the rules to this file instead of stdout.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub fn handle_command(args: Arguments) -> Result<()> {
+    let projects = am_list::list_all_project_objectives(&args.root)?;
+    let objectives: Vec<ObjectiveInfo> = projects
+        .into_values()
+        .flat_map(|(_, objectives)| objectives)
+        .collect();
+
+    if objectives.is_empty() {
+        bail!(
+            "No objectives found in {}. Objectives are currently detected in Rust and Python projects",
+            args.root.display()
+        );
+    }
+
+    let rules = generate_rules(objectives);
+    let yaml = format!("{HEADER}{}", serde_yaml::to_string(&rules)?);
+
+    match args.output {
+        Some(output) => {
+            fs::write(&output, yaml)
+                .with_context(|| format!("Unable to write {}", output.display()))?;
+            info!(
+                "Rules for {} objective(s) written to {}",
+                rules.groups.len(),
+                output.display()
+            );
+        }
+        None => print!("{yaml}"),
+    }
+
+    Ok(())
+}
+
+struct BurnRate {
+    severity: &'static str,
+    long_window: &'static str,
+    short_window: &'static str,
+    factor: &'static str,
+}
+
+impl BurnRate {
+    const fn new(
+        severity: &'static str,
+        long_window: &'static str,
+        short_window: &'static str,
+        factor: &'static str,
+    ) -> Self {
+        Self {
+            severity,
+            long_window,
+            short_window,
+            factor,
+        }
+    }
+}
+
+/// A single target of an objective, for which the error ratio is recorded and
+/// alerted on.
+struct Target<'a> {
+    /// Used for the `slo` label, either `success-rate` or `latency`.
+    category: &'static str,
+    alert: &'static str,
+    percentile: &'a str,
+    /// The label selector that matches the recorded error ratios of this
+    /// target.
+    selector: String,
+    /// Expression calculating the error ratio over the `{window}`.
+    error_ratio: String,
+    summary: String,
+}
+
+/// Generate recording and alerting rules for every distinct objective. All
+/// targets of objectives that share a name end up in the same group.
+fn generate_rules(objectives: Vec<ObjectiveInfo>) -> RuleFile {
+    let objectives: BTreeSet<ObjectiveInfo> = objectives
+        .into_iter()
+        .map(|objective| ObjectiveInfo {
+            location: None,
+            ..objective
+        })
+        .collect();
+
+    let mut groups: BTreeMap<String, Vec<Rule>> = BTreeMap::new();
+    for objective in &objectives {
+        let rules = groups.entry(objective.name.clone()).or_default();
+
+        if rules.is_empty() {
+            info!("Generating rules for objective {}", objective.name);
+        } else {
+            warn!(
+                "Objective {} is declared multiple times with different targets, generating rules for all of them",
+                objective.name
+            );
+        }
+
+        for target in targets(objective) {
+            rules.extend(target_rules(&target));
+        }
+    }
+
+    RuleFile {
+        groups: groups
+            .into_iter()
+            .map(|(name, rules)| RuleGroup {
+                name: format!("autometrics-objective-{name}"),
+                rules,
+            })
+            .collect(),
+    }
+}
+
+fn targets(objective: &ObjectiveInfo) -> Vec<Target<'_>> {
+    let name = &objective.name;
+    let mut targets = vec![];
+
+    if let Some(percentile) = &objective.success_rate {
+        let selector = format!(r#"objective_name="{name}",objective_percentile="{percentile}""#);
+
+        targets.push(Target {
+            category: "success-rate",
+            alert: "HighErrorRate",
+            percentile,
+            error_ratio: format!(
+                "sum by (objective_name, objective_percentile) (rate({{__name__=~\"function_calls(_count)?(_total)?\",{selector},result=\"error\"}}[{{window}}]))\n/\nsum by (objective_name, objective_percentile) (rate({{__name__=~\"function_calls(_count)?(_total)?\",{selector}}}[{{window}}]))"
+            ),
+            selector,
+            summary: format!("Less than {percentile}% of the calls of the `{name}` objective succeed"),
+        });
+    }
+
+    if let Some(latency) = &objective.latency {
+        let percentile = &latency.percentile;
+        let threshold = &latency.threshold;
+        let metric_selector = format!(
+            r#"objective_name="{name}",objective_percentile="{percentile}",objective_latency_threshold="{threshold}""#
+        );
+
+        targets.push(Target {
+            category: "latency",
+            alert: "HighLatency",
+            percentile,
+            error_ratio: format!(
+                "1 - (\n  sum by (objective_name, objective_percentile) (rate({{__name__=~\"function_calls_duration(_seconds)?_bucket\",{metric_selector},le=\"{threshold}\"}}[{{window}}]))\n  /\n  sum by (objective_name, objective_percentile) (rate({{__name__=~\"function_calls_duration(_seconds)?_count\",{metric_selector}}}[{{window}}]))\n)"
+            ),
+            selector: format!(
+                r#"objective_name="{name}",objective_percentile="{percentile}""#
+            ),
+            summary: format!(
+                "Less than {percentile}% of the calls of the `{name}` objective complete within {threshold}s"
+            ),
+        });
+    }
+
+    targets
+}
+
+/// The recording rules for the error ratio of `target` over every window, and
+/// an alert for every severity.
+fn target_rules(target: &Target) -> Vec<Rule> {
+    let slo = format!("autometrics-{}", target.category);
+    let series = |window: &str| {
+        format!(
+            r#"slo:sli_error:ratio_rate{window}{{slo="{slo}",{}}}"#,
+            target.selector
+        )
+    };
+
+    let recording_rules = WINDOWS.iter().map(|window| Rule::Recording {
+        record: format!("slo:sli_error:ratio_rate{window}"),
+        expr: target.error_ratio.replace("{window}", window),
+        labels: BTreeMap::from([("slo".to_string(), slo.clone())]),
+    });
+
+    let severities: BTreeSet<&str> = BURN_RATES.iter().map(|rate| rate.severity).collect();
+    let alerting_rules = severities.into_iter().map(|severity| {
+        let expr = BURN_RATES
+            .iter()
+            .filter(|rate| rate.severity == severity)
+            .map(|rate| {
+                let threshold = format!("({} * (1 - {} / 100))", rate.factor, target.percentile);
+                format!(
+                    "({} > {threshold}\nand\n{} > {threshold})",
+                    series(rate.long_window),
+                    series(rate.short_window)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\nor\n");
+
+        Rule::Alerting {
+            alert: target.alert.to_string(),
+            expr,
+            labels: BTreeMap::from([
+                ("category".to_string(), target.category.to_string()),
+                ("severity".to_string(), severity.to_string()),
+            ]),
+            annotations: BTreeMap::from([(
+                "summary".to_string(),
+                format!(
+                    "{}, the error budget is running out too fast",
+                    target.summary
+                ),
+            )]),
+        }
+    });
+
+    recording_rules.chain(alerting_rules).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use am_list::ObjectiveLatency;
+
+    #[test]
+    fn rules_are_generated_per_objective() {
+        let api = ObjectiveInfo {
+            name: "api".to_string(),
+            success_rate: Some("99.9".to_string()),
+            latency: Some(ObjectiveLatency {
+                threshold: "0.25".to_string(),
+                percentile: "99".to_string(),
+            }),
+            location: None,
+        };
+        let batch = ObjectiveInfo {
+            name: "batch".to_string(),
+            success_rate: Some("95".to_string()),
+            ..Default::default()
+        };
+
+        // The same objective is often found more than once, for example if it
+        // is declared inline in multiple decorators.
+        let rules = generate_rules(vec![api.clone(), batch, api]);
+
+        let names: Vec<&str> = rules
+            .groups
+            .iter()
+            .map(|group| group.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["autometrics-objective-api", "autometrics-objective-batch"]
+        );
+
+        // A recording rule for every window and an alert for every severity,
+        // for both the success rate and the latency target.
+        assert_eq!(rules.groups[0].rules.len(), 2 * (WINDOWS.len() + 2));
+        assert_eq!(rules.groups[1].rules.len(), WINDOWS.len() + 2);
+
+        let yaml = serde_yaml::to_string(&rules.groups[1].rules[0]).unwrap();
+        let expected = r#"record: slo:sli_error:ratio_rate5m
+expr: |-
+  sum by (objective_name, objective_percentile) (rate({__name__=~"function_calls(_count)?(_total)?",objective_name="batch",objective_percentile="95",result="error"}[5m]))
+  /
+  sum by (objective_name, objective_percentile) (rate({__name__=~"function_calls(_count)?(_total)?",objective_name="batch",objective_percentile="95"}[5m]))
+labels:
+  slo: autometrics-success-rate
+"#;
+        assert_eq!(expected, yaml);
+
+        let Rule::Alerting { expr, .. } = &rules.groups[1].rules[WINDOWS.len()] else {
+            panic!("expected an alerting rule after the recording rules");
+        };
+        assert!(expr.starts_with(
+            r#"(slo:sli_error:ratio_rate1h{slo="autometrics-success-rate",objective_name="batch",objective_percentile="95"} > (14.4 * (1 - 95 / 100))"#
+        ));
+    }
+}
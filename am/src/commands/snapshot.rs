@@ -0,0 +1,80 @@
+use crate::commands::start::snapshot::SnapshotInfo;
+use crate::commands::start::CLIENT;
+use crate::downloader::{download, DownloadOptions, DEFAULT_DOWNLOAD_RETRIES, INITIAL_BACKOFF};
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use indicatif::{HumanBytes, MultiProgress};
+use std::future;
+use std::path::PathBuf;
+use tracing::info;
+use url::Url;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Arguments {
+    /// The URL of the web server of the running `am start` instance.
+    #[clap(long, env, default_value = super::DEFAULT_AM_URL)]
+    am_url: Url,
+
+    /// The file to write the snapshot to. Defaults to
+    /// `am-snapshot-<NAME>.tar.gz` in the current directory.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub async fn handle_command(args: Arguments, mp: MultiProgress) -> Result<()> {
+    let response = CLIENT
+        .post(args.am_url.join("api/snapshots")?)
+        .send()
+        .await
+        .with_context(|| {
+            format!(
+                "Unable to reach am at {}, is `am start` running?",
+                args.am_url
+            )
+        })?;
+
+    let status = response.status();
+    if !status.is_success() {
+        bail!(
+            "Unable to create snapshot, am responded with {status}: {}",
+            response.text().await.unwrap_or_default()
+        );
+    }
+
+    let snapshot: SnapshotInfo = response.json().await?;
+
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("am-snapshot-{}.tar.gz", snapshot.name)));
+    if output.exists() {
+        bail!("{} already exists", output.display());
+    }
+
+    let url = args
+        .am_url
+        .join(&format!("api/snapshots/{}", snapshot.name))?;
+    let options = DownloadOptions {
+        retries: DEFAULT_DOWNLOAD_RETRIES,
+        initial_backoff: INITIAL_BACKOFF,
+    };
+
+    download(
+        url.as_str(),
+        &output,
+        format!("Downloading snapshot {}", snapshot.name),
+        &options,
+        &mp,
+        future::pending(),
+    )
+    .await?;
+
+    info!(
+        "Snapshot written to {} ({}). Use `am start --from-snapshot {}` to start from it",
+        output.display(),
+        HumanBytes(snapshot.size),
+        output.display()
+    );
+
+    Ok(())
+}
@@ -1,33 +1,71 @@
-use crate::dir::AutoCleanupDir;
-use crate::downloader::{download_github_release, unpack, verify_checksum};
-use crate::server::start_web_server;
+use crate::commands::start::alerts::{AlertHistory, ALERT_HISTORY_CAPACITY};
+use crate::commands::start::binary::resolve_binary_path;
+use crate::commands::start::docker::{watch_containers, DockerClient, DEFAULT_DOCKER_SOCKET};
+use crate::commands::start::file_sd::{file_sd_scrape_config, resolve_file_sd_jobs};
+use crate::commands::start::install::{mark_used, resolve_binary};
+use crate::commands::start::ports::select_port;
+use crate::commands::start::reload::{
+    watch_config_file, CliOverrides, PrometheusConfigManager, ScrapeSettings,
+    PROMETHEUS_CONFIG_FILE,
+};
+use crate::commands::start::retention::{retention_size_parser, retention_time_parser, Retention};
+use crate::commands::start::rules::{
+    check_rule_files, promtool_path, resolve_rule_files, write_autometrics_rules,
+    AUTOMETRICS_RULES_FILE,
+};
+use crate::commands::start::snapshot::{restore_snapshot, SnapshotSource};
+use crate::commands::start::supervisor::{ChildProcess, RestartPolicy, Supervisor};
+use crate::commands::start::targets::TargetMonitor;
+use crate::commands::start::tui::Dashboard;
+use crate::commands::workspace::{workspace_dir, workspace_name_parser};
+use crate::components::{Component, ALERTMANAGER, PROMETHEUS, PUSHGATEWAY};
+use crate::dir::{AutoCleanupDir, DataLocation};
+use crate::downloader::{ReleaseSource, DEFAULT_DOWNLOAD_RETRIES, DEFAULT_RELEASE_URL_TEMPLATE};
+use crate::server::{start_web_server, WebServerOptions};
 use crate::{interactive, terminal};
 use anyhow::{anyhow, bail, Context, Result};
-use autometrics_am::config::{endpoints_from_first_input, AmConfig};
+use autometrics_am::config::{endpoints_from_first_input, AmConfig, FileSdJob, ScrapeOptions};
 use autometrics_am::parser::endpoint_parser;
-use autometrics_am::prometheus;
 use autometrics_am::prometheus::ScrapeConfig;
+use autometrics_am::{alertmanager, prometheus};
 use clap::Parser;
 use directories::ProjectDirs;
 use futures_util::FutureExt;
 use indicatif::MultiProgress;
 use once_cell::sync::Lazy;
 use rand::distributions::{Alphanumeric, DistString};
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::{Seek, SeekFrom};
+use std::collections::{BTreeMap, HashMap};
 use std::net::SocketAddr;
-use std::path::Path;
-use std::process::Stdio;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 use std::{env, fs, vec};
-use tempfile::NamedTempFile;
-use tokio::sync::watch;
 use tokio::sync::watch::Receiver;
+use tokio::sync::{watch, Mutex};
 use tokio::{process, select};
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info, warn};
 use url::Url;
 
+pub(crate) mod alerts;
+pub(crate) mod binary;
+pub(crate) mod docker;
+pub(crate) mod file_sd;
+pub(crate) mod install;
+pub(crate) mod logs;
+pub(crate) mod manifest;
+pub(crate) mod ports;
+pub(crate) mod reload;
+pub(crate) mod retention;
+pub(crate) mod rules;
+pub(crate) mod snapshot;
+pub(crate) mod supervisor;
+pub(crate) mod targets;
+pub(crate) mod tui;
+
+/// The scrape interval used if neither the CLI arguments nor the config file
+/// specify one.
+const DEFAULT_SCRAPE_INTERVAL: Duration = Duration::from_secs(5);
+
 // Create a reqwest client that will be used to make HTTP requests. This allows
 // for keep-alives if we are making multiple requests to the same host.
 pub(crate) static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
@@ -52,14 +90,16 @@ pub struct CliArguments {
     metrics_endpoints: Vec<Url>,
 
     /// The Prometheus version to use. It will be downloaded if am has not
-    /// downloaded it already.
-    #[clap(
-        long,
-        env,
-        default_value = "v2.47.2",
-        help_heading = "Prometheus options"
-    )]
-    prometheus_version: String,
+    /// downloaded it already. [default: v2.47.2]
+    ///
+    /// If a Prometheus binary is provided, its version is checked against this
+    /// version instead.
+    #[clap(long, env, help_heading = "Prometheus options")]
+    prometheus_version: Option<String>,
+
+    /// Use this Prometheus binary instead of downloading Prometheus.
+    #[clap(long, env, help_heading = "Prometheus options")]
+    prometheus_binary: Option<PathBuf>,
 
     /// The default scrape interval for all Prometheus jobs.
     ///
@@ -67,6 +107,23 @@ pub struct CliArguments {
     #[clap(long, env, help_heading = "Prometheus options", value_parser = humantime::parse_duration)]
     scrape_interval: Option<Duration>,
 
+    /// The port Prometheus will listen on.
+    ///
+    /// Defaults to 9090, or a free port if 9090 is already in use. Use 0 to
+    /// always select a free port.
+    #[clap(long, env, help_heading = "Prometheus options")]
+    prometheus_port: Option<u16>,
+
+    /// How long Prometheus keeps its data, for example `30d` or `1w2d`.
+    /// [default: 15d]
+    #[clap(long, env, help_heading = "Prometheus options", value_parser = retention_time_parser)]
+    retention_time: Option<String>,
+
+    /// The maximum amount of disk space Prometheus uses for its data, for
+    /// example `512MB` or `10GB`. The oldest data is removed first.
+    #[clap(long, env, help_heading = "Prometheus options", value_parser = retention_size_parser)]
+    retention_size: Option<String>,
+
     /// The listen address for the web server of am.
     ///
     /// This includes am's HTTP API, the explorer and the proxy to the Prometheus, Gateway, etc.
@@ -88,86 +145,283 @@ pub struct CliArguments {
     #[clap(short, long, env, help_heading = "Pushgateway options")]
     pushgateway_enabled: Option<bool>,
 
-    /// The pushgateway version to use.
+    /// The pushgateway version to use. [default: v1.6.2]
+    ///
+    /// If a Pushgateway binary is provided, its version is checked against
+    /// this version instead.
+    #[clap(long, env, help_heading = "Pushgateway options")]
+    pushgateway_version: Option<String>,
+
+    /// Use this Pushgateway binary instead of downloading Pushgateway.
+    #[clap(long, env, help_heading = "Pushgateway options")]
+    pushgateway_binary: Option<PathBuf>,
+
+    /// The port Pushgateway will listen on.
+    ///
+    /// Defaults to 9091, or a free port if 9091 is already in use. Use 0 to
+    /// always select a free port.
+    #[clap(long, env, help_heading = "Pushgateway options")]
+    pushgateway_port: Option<u16>,
+
+    /// Enable Alertmanager.
+    ///
+    /// Prometheus will send the alerts of the autometrics rules (and any other
+    /// alerting rules) to Alertmanager, which is available at `/alertmanager`.
+    #[clap(long, env, help_heading = "Alertmanager options")]
+    alertmanager_enabled: Option<bool>,
+
+    /// The Alertmanager version to use. [default: v0.26.0]
+    ///
+    /// If an Alertmanager binary is provided, its version is checked against
+    /// this version instead.
+    #[clap(long, env, help_heading = "Alertmanager options")]
+    alertmanager_version: Option<String>,
+
+    /// Use this Alertmanager binary instead of downloading Alertmanager.
+    #[clap(long, env, help_heading = "Alertmanager options")]
+    alertmanager_binary: Option<PathBuf>,
+
+    /// The port Alertmanager will listen on.
+    ///
+    /// Defaults to 9093, or a free port if 9093 is already in use. Use 0 to
+    /// always select a free port.
+    #[clap(long, env, help_heading = "Alertmanager options")]
+    alertmanager_port: Option<u16>,
+
     #[clap(
         long,
         env,
-        default_value = "v1.6.2",
-        help_heading = "Pushgateway options"
+        default_value = "https://explorer.autometrics.dev",
+        help_heading = "Location for static assets used by the explorer"
     )]
-    pushgateway_version: String,
+    static_assets_url: Url,
 
+    /// Discover Docker containers and scrape them.
+    ///
+    /// Containers are scraped if they have the `autometrics.port` label. The
+    /// `autometrics.path` label sets the path of the metrics (defaults to
+    /// `/metrics`) and containers with the same `autometrics.job` label are
+    /// scraped as a single job.
+    #[clap(long, env, help_heading = "Docker options")]
+    docker_discovery: Option<bool>,
+
+    /// The unix socket of the Docker Engine API.
     #[clap(
         long,
         env,
-        default_value = "https://explorer.autometrics.dev",
-        help_heading = "Location for static assets used by the explorer"
+        default_value = DEFAULT_DOCKER_SOCKET,
+        help_heading = "Docker options"
     )]
-    static_assets_url: Url,
+    docker_socket: PathBuf,
+
+    /// The URL template used to download Prometheus and Pushgateway. This
+    /// allows using a mirror instead of GitHub. [default: https://github.com/{org}/{repo}/releases/download/v{version}/{package}]
+    ///
+    /// The `{org}`, `{repo}`, `{version}` and `{package}` placeholders are
+    /// replaced with the GitHub organization, repository, version and file
+    /// name of the release. The checksums are downloaded from the same
+    /// location, using `sha256sums.txt` as the package.
+    #[clap(long, env)]
+    release_url_template: Option<String>,
+
+    /// The amount of times a failed download of Prometheus or Pushgateway is
+    /// retried. [default: 3]
+    #[clap(long, env)]
+    download_retries: Option<u32>,
 
     /// Whenever to clean up files created by Prometheus/Pushgateway after successful execution
     #[clap(short = 'd', long, env)]
     ephemeral: bool,
 
-    /// Whenever to *NOT* load the autometrics rules file into Prometheus
+    /// Store the data of Prometheus, Pushgateway and Alertmanager in this
+    /// named workspace in the am data directory, instead of in
+    /// `.autometrics` in the current directory.
+    ///
+    /// This keeps the data when am is started from a different directory.
+    /// Use `am workspace` to list, delete or export workspaces.
+    #[clap(short, long, env = "AM_WORKSPACE", conflicts_with = "ephemeral", value_parser = workspace_name_parser)]
+    workspace: Option<String>,
+
+    /// Restore a snapshot created by `am snapshot` before starting, so
+    /// Prometheus starts with the data of the snapshot.
+    ///
+    /// The snapshot is restored into the workspace set by `--workspace`, which
+    /// must not contain any data yet. Without `--workspace`, a workspace named
+    /// after the file is used.
+    #[clap(long, env, conflicts_with = "ephemeral", value_name = "FILE")]
+    from_snapshot: Option<PathBuf>,
+
+    /// Whenever to *NOT* load the autometrics rules file into Prometheus. The
+    /// `rule-files` from the am.toml file are loaded regardless.
     #[clap(long, env)]
     no_rules: bool,
 
+    /// Use this rules file instead of the autometrics rules that are included
+    /// in am. Use `am rules generate` to generate a rules file for the
+    /// objectives that are declared in your project.
+    #[clap(long, env, conflicts_with = "no_rules")]
+    rules_file: Option<PathBuf>,
+
     /// Whenever to instruct Prometheus to scrape this `am` server as well
     #[clap(long, env, default_value = "false")]
     scrape_self: bool,
+
+    /// The maximum amount of times Prometheus or Pushgateway will be restarted
+    /// after crashing. Use 0 to disable restarting.
+    ///
+    /// A process that crashes repeatedly right after starting will not be
+    /// restarted, regardless of this setting.
+    #[clap(long, env, default_value = "5")]
+    max_restarts: u32,
+
+    /// Show a full-screen dashboard instead of the log messages.
+    ///
+    /// The dashboard shows the health of the scrape targets, the functions
+    /// with the highest request rate, error ratio or latency, the firing
+    /// alerts, and the status and output of Prometheus, Pushgateway and
+    /// Alertmanager.
+    #[clap(long, env)]
+    tui: bool,
 }
 
 #[derive(Debug, Clone)]
 struct Arguments {
     metrics_endpoints: Vec<Endpoint>,
-    prometheus_version: String,
+    prometheus_version: Option<String>,
+    prometheus_binary: Option<PathBuf>,
     prometheus_scrape_interval: Duration,
+    prometheus_port: Option<u16>,
+    retention: Retention,
     listen_address: SocketAddr,
     pushgateway_enabled: bool,
-    pushgateway_version: String,
+    pushgateway_version: Option<String>,
+    pushgateway_binary: Option<PathBuf>,
+    pushgateway_port: Option<u16>,
+    alertmanager_enabled: bool,
+    alertmanager_version: Option<String>,
+    alertmanager_binary: Option<PathBuf>,
+    alertmanager_port: Option<u16>,
+    docker_discovery: bool,
+    docker_socket: PathBuf,
+    release_url_template: String,
+    download_retries: u32,
     ephemeral_working_directory: bool,
+    workspace: Option<String>,
+    from_snapshot: Option<PathBuf>,
     no_rules: bool,
+    rules_file: Option<PathBuf>,
     static_assets_url: Url,
     scrape_self: bool,
+    max_restarts: u32,
+    tui: bool,
 }
 
 impl Arguments {
-    fn new(args: CliArguments, config: AmConfig) -> Self {
+    fn new(args: CliArguments, config: AmConfig, config_file: &Path) -> Self {
+        // Binaries from the CLI are relative to the current directory, while
+        // binaries from the config file are relative to the config file.
+        let current_dir = env::current_dir().unwrap_or_default();
+        let config_dir = current_dir.join(config_file.parent().unwrap_or(Path::new("")));
+
         Arguments {
             metrics_endpoints: endpoints_from_first_input(args.metrics_endpoints, config.endpoints)
                 .into_iter()
-                .filter_map(|e| e.try_into().ok())
+                .filter_map(|e| match e.try_into() {
+                    Ok(endpoint) => Some(endpoint),
+                    Err(err) => {
+                        warn!("Ignoring invalid endpoint: {err}");
+                        None
+                    }
+                })
                 .collect(),
-            prometheus_version: args.prometheus_version,
+            prometheus_version: args.prometheus_version.or(config.prometheus_version),
+            prometheus_binary: args
+                .prometheus_binary
+                .map(|path| resolve_binary_path(path, &current_dir))
+                .or_else(|| {
+                    config
+                        .prometheus_binary
+                        .map(|path| resolve_binary_path(path, &config_dir))
+                }),
             listen_address: args.listen_address,
             pushgateway_enabled: args
                 .pushgateway_enabled
                 .or(config.pushgateway_enabled)
                 .unwrap_or(false),
-            pushgateway_version: args.pushgateway_version,
+            pushgateway_version: args.pushgateway_version.or(config.pushgateway_version),
+            pushgateway_binary: args
+                .pushgateway_binary
+                .map(|path| resolve_binary_path(path, &current_dir))
+                .or_else(|| {
+                    config
+                        .pushgateway_binary
+                        .map(|path| resolve_binary_path(path, &config_dir))
+                }),
+            pushgateway_port: args.pushgateway_port.or(config.pushgateway_port),
+            alertmanager_enabled: args
+                .alertmanager_enabled
+                .or(config.alertmanager_enabled)
+                .unwrap_or(false),
+            alertmanager_version: args.alertmanager_version.or(config.alertmanager_version),
+            alertmanager_binary: args
+                .alertmanager_binary
+                .map(|path| resolve_binary_path(path, &current_dir))
+                .or_else(|| {
+                    config
+                        .alertmanager_binary
+                        .map(|path| resolve_binary_path(path, &config_dir))
+                }),
+            alertmanager_port: args.alertmanager_port.or(config.alertmanager_port),
+            docker_discovery: args
+                .docker_discovery
+                .or(config.docker_discovery)
+                .unwrap_or(false),
+            docker_socket: args.docker_socket,
+            release_url_template: args
+                .release_url_template
+                .or(config.release_url_template)
+                .unwrap_or_else(|| DEFAULT_RELEASE_URL_TEMPLATE.to_string()),
+            download_retries: args
+                .download_retries
+                .or(config.download_retries)
+                .unwrap_or(DEFAULT_DOWNLOAD_RETRIES),
             ephemeral_working_directory: args.ephemeral,
             prometheus_scrape_interval: args
                 .scrape_interval
                 .or(config.prometheus_scrape_interval)
-                .unwrap_or_else(|| Duration::from_secs(5)),
+                .unwrap_or(DEFAULT_SCRAPE_INTERVAL),
+            prometheus_port: args.prometheus_port.or(config.prometheus_port),
+            retention: Retention {
+                time: args.retention_time.or(config.prometheus_retention_time),
+                size: args.retention_size.or(config.prometheus_retention_size),
+            },
+            workspace: args.workspace,
+            from_snapshot: args.from_snapshot.map(|path| current_dir.join(path)),
             no_rules: args.no_rules,
+            rules_file: args
+                .rules_file
+                .map(|path| current_dir.join(path))
+                .or_else(|| config.rules_file.map(|path| config_dir.join(path))),
             static_assets_url: args.static_assets_url,
             scrape_self: args.scrape_self,
+            max_restarts: args.max_restarts,
+            tui: args.tui,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Endpoint {
-    url: Url,
-    job_name: String,
-    honor_labels: bool,
-    scrape_interval: Option<Duration>,
+    pub(crate) url: Url,
+    pub(crate) additional_urls: Vec<Url>,
+    pub(crate) job_name: String,
+    pub(crate) honor_labels: bool,
+    pub(crate) scrape_interval: Option<Duration>,
+    pub(crate) scrape_options: ScrapeOptions,
 }
 
 impl Endpoint {
-    fn new(
+    pub(crate) fn new(
         url: Url,
         job_name: String,
         honor_labels: bool,
@@ -175,24 +429,47 @@ impl Endpoint {
     ) -> Self {
         Self {
             url,
+            additional_urls: vec![],
             job_name,
             honor_labels,
             scrape_interval,
+            scrape_options: ScrapeOptions::default(),
         }
     }
+
+    /// All the URLs that are scraped as part of this endpoint.
+    pub(crate) fn urls(&self) -> impl Iterator<Item = &Url> {
+        std::iter::once(&self.url).chain(self.additional_urls.iter())
+    }
 }
 
 impl TryFrom<autometrics_am::config::Endpoint> for Endpoint {
     type Error = anyhow::Error;
 
     fn try_from(value: autometrics_am::config::Endpoint) -> Result<Self, Self::Error> {
+        let job_name = value
+            .job_name
+            .ok_or_else(|| anyhow!("TryFrom requires job_name"))?;
+
+        // Prometheus uses the same scheme and path for all targets of a job.
+        if let Some(url) = value
+            .additional_urls
+            .iter()
+            .find(|url| url.scheme() != value.url.scheme() || url.path() != value.url.path())
+        {
+            bail!(
+                "{url} must use the same scheme and path as {} (job {job_name})",
+                value.url
+            );
+        }
+
         Ok(Self {
             url: value.url,
-            job_name: value
-                .job_name
-                .ok_or_else(|| anyhow!("TryFrom requires job_name"))?,
+            additional_urls: value.additional_urls,
+            job_name,
             honor_labels: value.honor_labels.unwrap_or(false),
             scrape_interval: value.prometheus_scrape_interval,
+            scrape_options: value.scrape_options,
         })
     }
 }
@@ -201,7 +478,7 @@ impl From<Endpoint> for ScrapeConfig {
     /// Convert an InnerEndpoint to a Prometheus ScrapeConfig.
     ///
     /// Scrape config only supports http and https atm.
-    fn from(endpoint: Endpoint) -> Self {
+    fn from(mut endpoint: Endpoint) -> Self {
         let scheme = match endpoint.url.scheme() {
             "http" => Some(prometheus::Scheme::Http),
             "https" => Some(prometheus::Scheme::Https),
@@ -213,28 +490,93 @@ impl From<Endpoint> for ScrapeConfig {
             metrics_path = "/metrics";
         }
 
-        let host = match endpoint.url.port() {
-            Some(port) => format!("{}:{}", endpoint.url.host_str().unwrap(), port),
-            None => endpoint.url.host_str().unwrap().to_string(),
-        };
+        let metrics_path = metrics_path.to_string();
+
+        let targets = endpoint
+            .urls()
+            .map(|url| match url.port() {
+                Some(port) => format!("{}:{}", url.host_str().unwrap(), port),
+                None => url.host_str().unwrap().to_string(),
+            })
+            .collect();
+
+        let labels = std::mem::take(&mut endpoint.scrape_options.labels);
 
         ScrapeConfig {
-            job_name: endpoint.job_name,
-            static_configs: vec![prometheus::StaticScrapeConfig {
-                targets: vec![host],
-            }],
-            metrics_path: Some(metrics_path.to_string()),
+            static_configs: vec![prometheus::StaticScrapeConfig { targets, labels }],
+            metrics_path: Some(metrics_path),
             scheme,
-            honor_labels: Some(endpoint.honor_labels),
-            scrape_interval: endpoint.scrape_interval,
+            ..scrape_config(
+                endpoint.job_name,
+                endpoint.honor_labels,
+                endpoint.scrape_interval,
+                endpoint.scrape_options,
+            )
         }
     }
 }
 
-pub async fn handle_command(args: CliArguments, config: AmConfig, mp: MultiProgress) -> Result<()> {
-    let mut args = Arguments::new(args, config);
+/// Create a scrape config for `job_name` without any targets. The `labels` of
+/// the options are not included, since how they are applied depends on how
+/// the targets are discovered.
+pub(crate) fn scrape_config(
+    job_name: String,
+    honor_labels: bool,
+    scrape_interval: Option<Duration>,
+    options: ScrapeOptions,
+) -> ScrapeConfig {
+    ScrapeConfig {
+        job_name,
+        static_configs: vec![],
+        file_sd_configs: vec![],
+        metrics_path: None,
+        scheme: None,
+        honor_labels: Some(honor_labels),
+        scrape_interval,
+        scrape_timeout: options.scrape_timeout,
+        basic_auth: options.basic_auth.map(Into::into),
+        authorization: options.bearer_token.map(prometheus::Authorization::bearer),
+        tls_config: options.tls_config.map(Into::into),
+        params: options.params,
+        relabel_configs: vec![],
+        metric_relabel_configs: options
+            .metric_relabel_configs
+            .into_iter()
+            .map(Into::into)
+            .collect(),
+    }
+}
+
+pub async fn handle_command(
+    args: CliArguments,
+    mut config: AmConfig,
+    config_file: PathBuf,
+    mp: MultiProgress,
+) -> Result<()> {
+    let cli_overrides = CliOverrides {
+        endpoints: !args.metrics_endpoints.is_empty(),
+        scrape_interval: args.scrape_interval,
+    };
 
-    if args.metrics_endpoints.is_empty() && !args.pushgateway_enabled {
+    let file_sd_jobs = resolve_file_sd_jobs(config.file_sd.take(), &config_file)?;
+    let rule_files = resolve_rule_files(config.rule_files.take(), &config_file)?;
+
+    let mut args = Arguments::new(args, config, &config_file);
+
+    // The retention from the CLI is already validated, but the one from the
+    // config file is not.
+    if let Some(time) = &args.retention.time {
+        retention_time_parser(time)?;
+    }
+    if let Some(size) = &args.retention.size {
+        retention_size_parser(size)?;
+    }
+
+    if args.metrics_endpoints.is_empty()
+        && file_sd_jobs.is_empty()
+        && !args.pushgateway_enabled
+        && !args.docker_discovery
+    {
         info!("No metrics endpoints provided and pushgateway is not enabled. Please provide an endpoint.");
 
         // Ask for a metric endpoint and parse the input like a regular CLI argument
@@ -255,48 +597,212 @@ pub async fn handle_command(args: CliArguments, config: AmConfig, mp: MultiProgr
     fs::create_dir_all(&local_data)
         .with_context(|| format!("Unable to create data directory: {:?}", local_data))?;
 
-    if !args.metrics_endpoints.is_empty() {
-        info!("Checking if provided metrics endpoints work...");
+    // Snapshots are always restored into a workspace, which is named after
+    // the snapshot if no workspace was provided.
+    let workspace = match (&args.workspace, &args.from_snapshot) {
+        (Some(name), _) => Some(name.clone()),
+        (None, Some(snapshot)) => Some(snapshot_workspace_name(snapshot)?),
+        (None, None) => None,
+    };
 
-        // check if the provided endpoint works
-        for endpoint in &args.metrics_endpoints {
-            if let Err(err) = check_endpoint(&endpoint.url).await {
-                warn!(
-                    ?err,
-                    "Failed to make request to {} (job {})", endpoint.url, endpoint.job_name
+    let data_location = match &workspace {
+        Some(name) => {
+            let path = workspace_dir(&local_data, name);
+            let exists = path.exists();
+            fs::create_dir_all(&path)
+                .with_context(|| format!("Unable to create workspace: {:?}", path))?;
+
+            if let Some(snapshot) = &args.from_snapshot {
+                restore_snapshot(snapshot, &path, &mp).await?;
+                info!(
+                    "Restored snapshot {} into workspace {name}",
+                    snapshot.display()
                 );
+            } else if exists {
+                info!("Using workspace {name}");
+            } else {
+                info!("Created workspace {name}");
             }
+
+            mark_used(&path)?;
+            debug!(?path, "Workspace directory");
+
+            DataLocation::Workspace(path)
         }
-    }
+        None if args.ephemeral_working_directory => DataLocation::Ephemeral,
+        None => DataLocation::CurrentDir,
+    };
+
+    let prometheus_port = select_port(
+        PROMETHEUS.name,
+        args.prometheus_port,
+        PROMETHEUS.default_port,
+    )?;
+    let pushgateway_port = if args.pushgateway_enabled {
+        select_port(
+            PUSHGATEWAY.name,
+            args.pushgateway_port,
+            PUSHGATEWAY.default_port,
+        )?
+    } else {
+        PUSHGATEWAY.default_port
+    };
+    let alertmanager_port = if args.alertmanager_enabled {
+        select_port(
+            ALERTMANAGER.name,
+            args.alertmanager_port,
+            ALERTMANAGER.default_port,
+        )?
+    } else {
+        ALERTMANAGER.default_port
+    };
+
+    let mut internal_endpoints = vec![];
 
     if args.pushgateway_enabled {
-        let url = Url::parse("http://localhost:9091/pushgateway/metrics").unwrap();
+        let url = Url::parse(&format!(
+            "http://localhost:{pushgateway_port}/pushgateway/metrics"
+        ))
+        .unwrap();
         let endpoint = Endpoint::new(url, "am_pushgateway".to_string(), true, None);
-        args.metrics_endpoints.push(endpoint);
+        internal_endpoints.push(endpoint);
     }
 
     if args.scrape_self {
         let url = Url::parse(&format!("http://{}/self_metrics", args.listen_address)).unwrap();
         let endpoint = Endpoint::new(url, "am_self".to_string(), true, None);
-        args.metrics_endpoints.push(endpoint);
+        internal_endpoints.push(endpoint);
+    }
+
+    // The Prometheus config, the rules and all other generated files are
+    // written into a temporary directory for this instance. The Prometheus
+    // config will be rewritten whenever the scrape settings change.
+    let runtime_dir = AutoCleanupDir::new(
+        &format!(
+            "am-prometheus-{}",
+            Alphanumeric.sample_string(&mut rand::thread_rng(), 6)
+        ),
+        true,
+    )?;
+    let config_file_path = runtime_dir.join(PROMETHEUS_CONFIG_FILE);
+
+    if !args.no_rules {
+        write_autometrics_rules(&runtime_dir, args.rules_file.as_deref())?;
+    }
+
+    let alertmanager_config_path = runtime_dir.join("alertmanager.yml");
+    if args.alertmanager_enabled {
+        let config_file = fs::File::create(&alertmanager_config_path)?;
+        let webhook_url = format!("http://{}/api/alerts/webhook", args.listen_address);
+        serde_yaml::to_writer(&config_file, &generate_alertmanager_config(webhook_url))?;
+    }
+
+    let settings = ScrapeSettings {
+        endpoints: args.metrics_endpoints.clone(),
+        runtime_endpoints: vec![],
+        internal_endpoints,
+        discovered_endpoints: vec![],
+        file_sd_jobs,
+        scrape_interval: args.prometheus_scrape_interval,
+        enable_rules: !args.no_rules,
+        rule_files: rule_files.clone(),
+        alertmanager_port: args.alertmanager_enabled.then_some(alertmanager_port),
+    };
+    let config_manager = PrometheusConfigManager::new(
+        settings,
+        runtime_dir.to_path_buf(),
+        Url::parse(&format!(
+            "http://localhost:{prometheus_port}/prometheus/-/reload"
+        ))
+        .unwrap(),
+    );
+    config_manager.write()?;
+
+    debug!(
+        path = ?config_file_path,
+        "Created temporary file for Prometheus config serialization"
+    );
+
+    let endpoints = config_manager
+        .settings()
+        .all_endpoints()
+        .flat_map(Endpoint::urls)
+        .map(ToString::to_string)
+        .collect::<Vec<String>>();
+    if !endpoints.is_empty() {
+        let endpoints = endpoints.join(", ");
+        info!("Now sampling the following endpoints for metrics: {endpoints}");
     }
 
+    let config_manager = Arc::new(Mutex::new(config_manager));
+    let web_server_config_manager = config_manager.clone();
+
     let (tx, rx) = watch::channel(None);
     let (tx_url, rx_url) = watch::channel(HashMap::new());
 
+    let mut processes = vec![ChildProcess::Prometheus];
+    if args.pushgateway_enabled {
+        processes.push(ChildProcess::Pushgateway);
+    }
+    if args.alertmanager_enabled {
+        processes.push(ChildProcess::Alertmanager);
+    }
+
+    let (supervisor, rx_restarts) =
+        Supervisor::new(RestartPolicy::new(args.max_restarts), &processes);
+
+    let prometheus_url = Url::parse(&format!("http://localhost:{prometheus_port}/prometheus"))?;
+
+    // The health of the targets is reported whenever it changes, so
+    // endpoints that are down when am starts or that go down later are
+    // noticed.
+    let target_monitor = Arc::new(TargetMonitor::new(prometheus_url.clone()));
+    let targets_task = {
+        let target_monitor = target_monitor.clone();
+        async move { target_monitor.watch().await }
+    };
+
+    let dashboard = args.tui.then(|| Dashboard {
+        prometheus_url: prometheus_url.clone(),
+        target_monitor: target_monitor.clone(),
+        processes: processes.clone(),
+        urls: rx_url.clone(),
+        restarts: rx_restarts.clone(),
+        states: supervisor.process_states(),
+        log_buffers: supervisor.log_buffers(),
+    });
+
     let static_assets_url = args.static_assets_url.clone();
+    let log_buffers = supervisor.log_buffers();
+    let alert_history = args
+        .alertmanager_enabled
+        .then(|| Arc::new(AlertHistory::new(ALERT_HISTORY_CAPACITY)));
+    let snapshot_source = SnapshotSource {
+        prometheus_url,
+        tsdb_dir: data_location.dir(PROMETHEUS.id)?.join("data"),
+        runtime_dir: runtime_dir.to_path_buf(),
+        config_manager: config_manager.clone(),
+    };
     // Start web server for hosting the explorer, am api and proxies to the enabled services.
     let web_server_task = async move {
-        start_web_server(
-            &args.listen_address,
-            true,
-            args.pushgateway_enabled,
-            None,
+        let options = WebServerOptions {
+            listen_address: args.listen_address,
+            enable_prometheus: true,
+            enable_pushgateway: args.pushgateway_enabled,
+            enable_alertmanager: args.alertmanager_enabled,
+            prometheus_port,
+            pushgateway_port,
+            alertmanager_port,
+            prometheus_proxy_url: None,
             static_assets_url,
-            tx,
-            tx_url,
-        )
-        .await
+            config_manager: Some(web_server_config_manager),
+            log_buffers: Some(log_buffers),
+            alert_history,
+            snapshot_source: Some(Arc::new(snapshot_source)),
+            target_monitor: Some(target_monitor),
+        };
+
+        start_web_server(options, tx, tx_url).await
     };
 
     // Start Prometheus server
@@ -305,89 +811,141 @@ pub async fn handle_command(args: CliArguments, config: AmConfig, mp: MultiProgr
     let prometheus_multi_progress = mp.clone();
 
     let prom_rx = rx.clone();
+    // The config watcher validates reloaded rule files with the promtool that
+    // belongs to the Prometheus binary, once it is known.
+    let (promtool_tx, promtool_rx) = watch::channel(None);
+    let prometheus_data_location = &data_location;
+    let prom_supervisor = &supervisor;
 
-    let prometheus_task = async move {
-        let prometheus_version = prometheus_args.prometheus_version.trim_start_matches('v');
+    let release_source =
+        ReleaseSource::new(args.release_url_template.clone())?.with_retries(args.download_retries);
+    let prometheus_release_source = &release_source;
 
-        info!("Using Prometheus version: {}", prometheus_version);
+    let prometheus_task = async move {
+        let prometheus_binary = resolve_binary(
+            &PROMETHEUS,
+            prometheus_args.prometheus_binary,
+            prometheus_args.prometheus_version.as_deref(),
+            &prometheus_local_data,
+            prometheus_release_source,
+            &prometheus_multi_progress,
+        )
+        .await?;
 
-        let prometheus_path =
-            prometheus_local_data.join(format!("prometheus-{prometheus_version}"));
+        let promtool = promtool_path(&prometheus_binary);
+        promtool_tx.send_replace(Some(promtool.clone()));
 
-        // Check if prometheus is available
-        if !prometheus_path.exists() {
-            info!("Cached version of Prometheus not found, downloading Prometheus");
-            install_prometheus(
-                &prometheus_path,
-                prometheus_version,
-                prometheus_multi_progress,
-            )
-            .await?;
-            debug!("Downloaded Prometheus to: {:?}", &prometheus_path);
-        } else {
-            debug!("Found prometheus in: {:?}", prometheus_path);
+        let files_to_check: Vec<PathBuf> = rule_files
+            .iter()
+            .chain(prometheus_args.rules_file.iter())
+            .cloned()
+            .collect();
+        if !files_to_check.is_empty() {
+            check_rule_files(&promtool, &files_to_check).await?;
         }
 
-        let prometheus_config = generate_prom_config(
-            prometheus_args.prometheus_scrape_interval,
-            prometheus_args.metrics_endpoints,
-            !args.no_rules,
-        )?;
-
         start_prometheus(
-            &prometheus_path,
-            &prometheus_config,
-            args.ephemeral_working_directory,
-            !args.no_rules,
+            &prometheus_binary,
+            &config_file_path,
+            prometheus_data_location,
+            &prometheus_args.retention,
+            prometheus_port,
             prom_rx,
+            prom_supervisor,
         )
         .await
     };
 
+    let alertmanager_rx = rx.clone();
+
     let pushgateway_task = if args.pushgateway_enabled {
         let pushgateway_args = args.clone();
         let pushgateway_local_data = local_data.clone();
         let pushgateway_multi_progress = mp.clone();
+        let pushgateway_supervisor = &supervisor;
+        let pushgateway_data_location = &data_location;
+        let pushgateway_release_source = &release_source;
         async move {
-            let pushgateway_version = pushgateway_args.pushgateway_version.trim_start_matches('v');
-
-            info!("Using pushgateway version: {}", pushgateway_version);
-
-            let pushgateway_path =
-                pushgateway_local_data.join(format!("pushgateway-{pushgateway_version}"));
-
-            // Check if pushgateway is available
-            if !pushgateway_path.exists() {
-                info!("Cached version of pushgateway not found, downloading pushgateway");
-                install_pushgateway(
-                    &pushgateway_path,
-                    pushgateway_version,
-                    pushgateway_multi_progress,
-                )
-                .await?;
-                debug!("Downloaded pushgateway to: {:?}", &pushgateway_path);
-            } else {
-                debug!("Found pushgateway in: {:?}", &pushgateway_path);
-            }
+            let pushgateway_binary = resolve_binary(
+                &PUSHGATEWAY,
+                pushgateway_args.pushgateway_binary,
+                pushgateway_args.pushgateway_version.as_deref(),
+                &pushgateway_local_data,
+                pushgateway_release_source,
+                &pushgateway_multi_progress,
+            )
+            .await?;
 
-            start_pushgateway(&pushgateway_path, args.ephemeral_working_directory, rx).await
+            start_component(
+                ChildProcess::Pushgateway,
+                &pushgateway_binary,
+                vec![],
+                pushgateway_data_location,
+                pushgateway_port,
+                rx,
+                pushgateway_supervisor,
+            )
+            .await
         }
         .boxed()
     } else {
         async move { anyhow::Ok(()) }.boxed()
     };
 
-    if !args.metrics_endpoints.is_empty() {
-        let endpoints = args
-            .metrics_endpoints
-            .iter()
-            .map(|endpoint| endpoint.url.to_string())
-            .collect::<Vec<String>>()
-            .join(", ");
-        info!("Now sampling the following endpoints for metrics: {endpoints}");
-    }
+    let alertmanager_task = if args.alertmanager_enabled {
+        let alertmanager_args = args.clone();
+        let alertmanager_local_data = local_data.clone();
+        let alertmanager_multi_progress = mp.clone();
+        let alertmanager_supervisor = &supervisor;
+        let alertmanager_data_location = &data_location;
+        let alertmanager_release_source = &release_source;
+        async move {
+            let alertmanager_binary = resolve_binary(
+                &ALERTMANAGER,
+                alertmanager_args.alertmanager_binary,
+                alertmanager_args.alertmanager_version.as_deref(),
+                &alertmanager_local_data,
+                alertmanager_release_source,
+                &alertmanager_multi_progress,
+            )
+            .await?;
+
+            start_component(
+                ChildProcess::Alertmanager,
+                &alertmanager_binary,
+                vec![format!(
+                    "--config.file={}",
+                    alertmanager_config_path.display()
+                )],
+                alertmanager_data_location,
+                alertmanager_port,
+                alertmanager_rx,
+                alertmanager_supervisor,
+            )
+            .await
+        }
+        .boxed()
+    } else {
+        async move { anyhow::Ok(()) }.boxed()
+    };
+
+    let docker_task = if args.docker_discovery {
+        let client = DockerClient::new(args.docker_socket.clone());
+        watch_containers(client, config_manager.clone()).boxed()
+    } else {
+        async move { anyhow::Ok(()) }.boxed()
+    };
 
-    terminal::wait_and_print_urls(rx_url);
+    let config_watcher_task =
+        watch_config_file(config_file, cli_overrides, config_manager, promtool_rx);
+
+    let dashboard_task = match dashboard {
+        Some(dashboard) => dashboard.run(mp.clone()).boxed(),
+        None => {
+            terminal::wait_and_print_urls(rx_url, Some(rx_restarts));
+            std::future::pending().boxed()
+        }
+    };
 
     select! {
         biased;
@@ -397,6 +955,12 @@ pub async fn handle_command(args: CliArguments, config: AmConfig, mp: MultiProgr
             Ok(())
         }
 
+        result = dashboard_task => {
+            result.context("Unable to show the dashboard")?;
+            info!("Dashboard closed, exiting...");
+            Ok(())
+        }
+
         Err(err) = web_server_task => {
             bail!("Web server exited with an error: {err:?}");
         }
@@ -409,143 +973,26 @@ pub async fn handle_command(args: CliArguments, config: AmConfig, mp: MultiProgr
             bail!("Pushgateway exited with an error: {err:?}");
         }
 
-        else => {
-            Ok(())
+        Err(err) = alertmanager_task => {
+            bail!("Alertmanager exited with an error: {err:?}");
         }
-    }
-}
 
-/// Install the specified version of Prometheus into `prometheus_path`.
-///
-/// This function will first create a temporary file to download the Prometheus
-/// archive into. Then it will verify the downloaded archive against the
-/// downloaded checksum. Finally it will unpack the archive into
-/// `prometheus_path`.
-async fn install_prometheus(
-    prometheus_path: &Path,
-    prometheus_version: &str,
-    multi_progress: MultiProgress,
-) -> Result<()> {
-    let (os, arch) = determine_os_and_arch()?;
-    let base = format!("prometheus-{prometheus_version}.{os}-{arch}");
-    let package = format!("{base}.tar.gz");
-    let prefix = format!("{base}/");
-
-    let mut prometheus_archive = NamedTempFile::new()?;
-
-    let calculated_checksum = download_github_release(
-        prometheus_archive.as_file(),
-        "prometheus",
-        "prometheus",
-        prometheus_version,
-        &package,
-        &multi_progress,
-    )
-    .await?;
-
-    verify_checksum(
-        &calculated_checksum,
-        "prometheus",
-        "prometheus",
-        prometheus_version,
-        &package,
-    )
-    .await?;
-
-    // Make sure we set the position to the beginning of the file so that we can
-    // unpack it.
-    prometheus_archive.as_file_mut().seek(SeekFrom::Start(0))?;
-
-    unpack(
-        prometheus_archive.as_file(),
-        "prometheus",
-        prometheus_path,
-        &prefix,
-        &multi_progress,
-    )
-    .await
-}
-
-/// Install the specified version of Pushgateway into `pushgateway_path`.
-///
-/// This function will first create a temporary file to download the Pushgateway
-/// archive into. Then it will verify the downloaded archive against the
-/// downloaded checksum. Finally it will unpack the archive into
-/// `pushgateway_path`.
-async fn install_pushgateway(
-    pushgateway_path: &Path,
-    pushgateway_version: &str,
-    multi_progress: MultiProgress,
-) -> Result<()> {
-    let (os, arch) = determine_os_and_arch()?;
-
-    let base = format!("pushgateway-{pushgateway_version}.{os}-{arch}");
-    let package = format!("{base}.tar.gz");
-    let prefix = format!("{base}/");
-
-    let mut pushgateway_archive = NamedTempFile::new()?;
-
-    let calculated_checksum = download_github_release(
-        pushgateway_archive.as_file(),
-        "prometheus",
-        "pushgateway",
-        pushgateway_version,
-        &package,
-        &multi_progress,
-    )
-    .await?;
-
-    verify_checksum(
-        &calculated_checksum,
-        "prometheus",
-        "pushgateway",
-        pushgateway_version,
-        &package,
-    )
-    .await?;
-
-    // Make sure we set the position to the beginning of the file so that we can
-    // unpack it.
-    pushgateway_archive.as_file_mut().seek(SeekFrom::Start(0))?;
-
-    unpack(
-        pushgateway_archive.as_file(),
-        "pushgateway",
-        pushgateway_path,
-        &prefix,
-        &multi_progress,
-    )
-    .await
-}
+        Err(err) = config_watcher_task => {
+            bail!("Config file watcher exited with an error: {err:?}");
+        }
 
-/// Translates the OS and arch provided by Rust to the convention used by
-/// Prometheus.
-fn determine_os_and_arch() -> Result<(&'static str, &'static str)> {
-    use std::env::consts::{ARCH, OS};
-
-    let os = match OS {
-        "linux" => "linux",
-        "macos" => "darwin",
-        "windows" => "windows",
-        "freebsd" => "freebsd",
-        "netbsd" => "netbsd",
-        "openbsd" => "openbsd",
-        "dragonfly" => "dragonfly",
-        _ => bail!(format!("Unsupported OS: {}", ARCH)),
-    };
+        Err(err) = docker_task => {
+            bail!("Docker discovery exited with an error: {err:?}");
+        }
 
-    let arch = match ARCH {
-        "x86" => "386",
-        "x86_64" => "amd64",
-        "aarch64" => "arm64",
-        "s390x" => "s390x",
-        "powerpc64" => "powerpc64", // NOTE: Do we use this one, or the le one?
-        // "mips" => "mips", // NOTE: Not sure which mips to pick in this situation
-        // "arm" => "arm", // NOTE: Not sure which arm to pick in this situation
-        _ => bail!(format!("Unsupported architecture: {}", ARCH)),
-    };
+        Err(err) = targets_task => {
+            bail!("Target health monitoring exited with an error: {err:?}");
+        }
 
-    Ok((os, arch))
+        else => {
+            Ok(())
+        }
+    }
 }
 
 /// Generate a Prometheus configuration file.
@@ -553,17 +1000,25 @@ fn determine_os_and_arch() -> Result<(&'static str, &'static str)> {
 /// For now this will expand a simple template and only has support for a single
 /// endpoint.
 fn generate_prom_config(
+    runtime_dir: &Path,
     scrape_interval: Duration,
     metric_endpoints: Vec<Endpoint>,
+    file_sd_jobs: Vec<FileSdJob>,
     enable_rules: bool,
+    extra_rule_files: &[PathBuf],
+    alertmanager_port: Option<u16>,
 ) -> Result<prometheus::Config> {
-    let scrape_configs = metric_endpoints.into_iter().map(Into::into).collect();
+    let scrape_configs = metric_endpoints
+        .into_iter()
+        .map(Into::into)
+        .chain(file_sd_jobs.into_iter().map(file_sd_scrape_config))
+        .collect();
 
     let mut rule_files = Vec::new();
 
     if enable_rules {
-        let path = env::temp_dir().join("autometrics.rules.yml");
-        let path_str = path
+        let path_str = runtime_dir
+            .join(AUTOMETRICS_RULES_FILE)
             .into_os_string()
             .into_string()
             .map_err(|_| anyhow!("failed to convert OsString into String"))?;
@@ -571,6 +1026,14 @@ fn generate_prom_config(
         rule_files.push(path_str);
     }
 
+    for path in extra_rule_files {
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| anyhow!("failed to convert {} into String", path.display()))?;
+
+        rule_files.push(path_str.to_string());
+    }
+
     Ok(prometheus::Config {
         global: prometheus::GlobalConfig {
             scrape_interval,
@@ -578,158 +1041,144 @@ fn generate_prom_config(
         },
         scrape_configs,
         rule_files,
+        alerting: alertmanager_port.map(|port| prometheus::AlertingConfig {
+            alertmanagers: vec![prometheus::AlertmanagerConfig {
+                static_configs: vec![prometheus::StaticScrapeConfig {
+                    targets: vec![format!("localhost:{port}")],
+                    labels: BTreeMap::new(),
+                }],
+                path_prefix: Some(format!("/{}", ALERTMANAGER.id)),
+            }],
+        }),
     })
 }
 
-/// Checks whenever the endpoint works
-async fn check_endpoint(url: &Url) -> Result<()> {
-    let response = CLIENT
-        .get(url.as_str())
-        .timeout(Duration::from_secs(5))
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        bail!("endpoint did not return 2xx status code");
+/// Generate the Alertmanager configuration, which sends all alerts to the
+/// webhook receiver of am at `webhook_url`.
+fn generate_alertmanager_config(webhook_url: String) -> alertmanager::Config {
+    alertmanager::Config {
+        route: alertmanager::Route {
+            receiver: "am".to_string(),
+            group_by: vec!["alertname".to_string(), "objective_name".to_string()],
+        },
+        receivers: vec![alertmanager::Receiver {
+            name: "am".to_string(),
+            webhook_configs: vec![alertmanager::WebhookConfig {
+                url: webhook_url,
+                send_resolved: true,
+            }],
+        }],
     }
+}
 
-    Ok(())
+/// The name of the workspace that `snapshot` is restored into if no workspace
+/// was provided, which is its file name without the extension.
+fn snapshot_workspace_name(snapshot: &Path) -> Result<String> {
+    let file_name = snapshot
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let name = file_name
+        .strip_suffix(".tar.gz")
+        .or_else(|| file_name.strip_suffix(".tgz"))
+        .unwrap_or(&file_name);
+
+    workspace_name_parser(name).context(
+        "Unable to name the workspace after the snapshot, use --workspace to provide a name",
+    )
 }
 
 /// Start a prometheus process. This will block until the Prometheus process
-/// stops.
+/// stops, restarting it using `supervisor` if it crashes.
 async fn start_prometheus(
-    prometheus_path: &Path,
-    prometheus_config: &prometheus::Config,
-    ephemeral: bool,
-    enable_rules: bool,
-    mut rx: Receiver<Option<SocketAddr>>,
+    prometheus_binary: &Path,
+    config_file_path: &Path,
+    data_location: &DataLocation,
+    retention: &Retention,
+    port: u16,
+    rx: Receiver<Option<SocketAddr>>,
+    supervisor: &Supervisor,
 ) -> Result<()> {
-    // First write needed files to temp
-    let runtime_dir = AutoCleanupDir::new(
-        &format!(
-            "am-prometheus-{}",
-            Alphanumeric.sample_string(&mut rand::thread_rng(), 6)
-        ),
-        true,
-    )?;
-
-    let config_file_path = runtime_dir.join("prometheus.yml");
-    let config_file = File::create(&config_file_path)?;
-
-    debug!(
-        path = ?config_file_path,
-        "Created temporary file for Prometheus config serialization"
-    );
-
-    serde_yaml::to_writer(&config_file, &prometheus_config)?;
-
-    if enable_rules {
-        let rule_file = env::temp_dir().join("autometrics.rules.yml");
-        fs::write(
-            rule_file,
-            include_bytes!("../../../files/autometrics-shared/autometrics.rules.yml"),
-        )?;
-    }
-
-    // TODO: Capture prometheus output into a internal buffer and expose it
-    // through an api.
-
-    let work_dir = AutoCleanupDir::new("prometheus", ephemeral)?;
-
-    #[cfg(not(target_os = "windows"))]
-    let program = "prometheus";
-    #[cfg(target_os = "windows")]
-    let program = "prometheus.exe";
-
-    let prometheus_path = prometheus_path.join(program);
+    let mut args = vec![
+        format!("--config.file={}", config_file_path.display()),
+        // Required to create snapshots using `am snapshot`. Prometheus only
+        // listens on the loopback interface and the admin API is not exposed
+        // through the proxy of am, so it is only reachable from this machine.
+        "--web.enable-admin-api".to_string(),
+    ];
+    args.extend(retention.args());
+
+    start_component(
+        ChildProcess::Prometheus,
+        prometheus_binary,
+        args,
+        data_location,
+        port,
+        rx,
+        supervisor,
+    )
+    .await
+}
 
-    info!(bin_path = ?prometheus_path.display(), "Starting prometheus");
+/// Start the component of `process`, passing `extra_args` in addition to its
+/// default arguments. This will block until the process stops, restarting it
+/// using `supervisor` if it crashes.
+async fn start_component(
+    process: ChildProcess,
+    binary: &Path,
+    extra_args: Vec<String>,
+    data_location: &DataLocation,
+    port: u16,
+    mut rx: Receiver<Option<SocketAddr>>,
+    supervisor: &Supervisor,
+) -> Result<()> {
+    let component = process.component();
+    let work_dir = data_location.work_dir(component.id)?;
 
     let external_url = rx.wait_for(Option::is_some).await.map_or_else(
         |_| "localhost:6789".to_string(),
         |address| address.unwrap().to_string(),
     );
 
-    let child = process::Command::new(prometheus_path)
-        .arg(format!("--config.file={}", config_file_path.display()))
-        .arg("--web.listen-address=:9090")
-        .arg("--web.enable-lifecycle")
-        .arg(format!(
-            "--web.external-url=http://{external_url}/prometheus"
-        ))
-        .arg("--web.enable-remote-write-receiver")
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .current_dir(&work_dir)
-        .spawn()
-        .context("Unable to start Prometheus")?
-        .wait_with_output()
-        .await?;
-
-    if !child.status.success() {
-        if !child.stdout.is_empty() {
-            error!("Prometheus stdout:\n{}", String::from_utf8(child.stdout)?);
-        }
+    info!(bin_path = ?binary.display(), "Starting {}", component.name);
 
-        if !child.stderr.is_empty() {
-            error!("Prometheus stderr:\n{}", String::from_utf8(child.stderr)?);
-        }
+    let mut command = process::Command::new(binary);
+    command
+        .args(component.args(port, &external_url))
+        .args(extra_args)
+        .current_dir(&work_dir);
 
-        bail!("Prometheus exited with status {}", child.status)
-    }
+    tokio::spawn(wait_until_healthy(component, port));
 
-    Ok(())
+    supervisor.supervise(process, command).await
 }
 
-/// Start a prometheus process. This will block until the Prometheus process
-/// stops.
-async fn start_pushgateway(
-    pushgateway_path: &Path,
-    ephemeral: bool,
-    mut rx: Receiver<Option<SocketAddr>>,
-) -> Result<()> {
-    let work_dir = AutoCleanupDir::new("pushgateway", ephemeral)?;
-
-    let external_url = rx.wait_for(Option::is_some).await.map_or_else(
-        |_| "localhost:6789".to_string(),
-        |address| address.unwrap().to_string(),
-    );
-
-    info!("Starting Pushgateway");
-    let child = process::Command::new(pushgateway_path.join("pushgateway"))
-        .arg("--web.listen-address=:9091")
-        .arg(format!(
-            "--web.external-url=http://{external_url}/pushgateway"
-        ))
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .current_dir(&work_dir)
-        .spawn()
-        .context("Unable to start Pushgateway")?
-        .wait_with_output()
-        .await?;
+/// Poll the health check of `component` until it responds successfully, to
+/// let the user know when it is ready to be used.
+async fn wait_until_healthy(component: &'static Component, port: u16) {
+    let url = component.health_check_url(port);
 
-    if !child.status.success() {
-        if !child.stdout.is_empty() {
-            error!("Pushgateway stdout:\n{}", String::from_utf8(child.stdout)?);
-        }
-
-        if !child.stderr.is_empty() {
-            error!("Pushgateway stderr:\n{}", String::from_utf8(child.stderr)?);
+    for _ in 0..120 {
+        match CLIENT.get(&url).send().await {
+            Ok(response) if response.status().is_success() => {
+                debug!("{} is ready", component.name);
+                return;
+            }
+            _ => tokio::time::sleep(Duration::from_millis(500)).await,
         }
-
-        bail!("Pushgateway exited with status {}", child.status)
     }
 
-    Ok(())
+    warn!(
+        "{} did not become healthy within a minute, check its logs using /api/logs/{}",
+        component.name, component.id
+    );
 }
 
 #[cfg(test)]
 mod tests {
     use rstest::rstest;
+    use std::path::Path;
+    use std::time::Duration;
 
     #[rstest]
     #[case("127.0.0.1", "http://127.0.0.1:80/metrics")]
@@ -755,4 +1204,180 @@ mod tests {
         // We're not checking which specific error occurred, just that a error
         // occurred.
     }
+
+    const SCRAPE_OPTIONS_CONFIG: &str = r#"
+        [[endpoint]]
+        job-name = "secure_app"
+        url = "https://localhost:3443/metrics"
+        scrape-timeout = "3s"
+        bearer-token = "secret"
+        params = { format = ["prometheus"] }
+        labels = { team = "api" }
+
+        [endpoint.basic-auth]
+        username = "admin"
+        password = "hunter2"
+
+        [endpoint.tls-config]
+        insecure-skip-verify = true
+
+        [[endpoint.metric-relabel-configs]]
+        source-labels = ["__name__"]
+        regex = "go_.*"
+        action = "drop"
+    "#;
+
+    #[test]
+    fn scrape_options_are_passed_to_prometheus() {
+        let config: super::AmConfig = toml::from_str(SCRAPE_OPTIONS_CONFIG).unwrap();
+        let endpoint: super::Endpoint = config.endpoints.unwrap().remove(0).try_into().unwrap();
+        let scrape_config: super::ScrapeConfig = endpoint.into();
+
+        let yaml = serde_yaml::to_string(&scrape_config).unwrap();
+        let expected = r#"job_name: secure_app
+static_configs:
+- targets:
+  - localhost:3443
+  labels:
+    team: api
+metrics_path: /metrics
+scheme: https
+honor_labels: false
+scrape_timeout: 3s
+basic_auth:
+  username: admin
+  password: hunter2
+authorization:
+  type: Bearer
+  credentials: secret
+tls_config:
+  insecure_skip_verify: true
+params:
+  format:
+  - prometheus
+metric_relabel_configs:
+- source_labels:
+  - __name__
+  regex: go_.*
+  action: drop
+"#;
+        assert_eq!(expected, yaml);
+    }
+
+    #[test]
+    fn additional_urls_are_scraped_as_one_job() {
+        let config: super::AmConfig = toml::from_str(
+            r#"
+            [[endpoint]]
+            job-name = "api"
+            url = ":3000"
+            additional-urls = [":3001", "localhost:3002"]
+
+            [[endpoint]]
+            job-name = "mixed"
+            url = ":3000"
+            additional-urls = ["https://localhost:3001"]
+            "#,
+        )
+        .unwrap();
+        let mut endpoints = config.endpoints.unwrap();
+
+        let endpoint: super::Endpoint = endpoints.remove(0).try_into().unwrap();
+        let scrape_config: super::ScrapeConfig = endpoint.into();
+        assert_eq!(
+            scrape_config.static_configs[0].targets,
+            vec!["localhost:3000", "localhost:3001", "localhost:3002"]
+        );
+
+        let result: anyhow::Result<super::Endpoint> = endpoints.remove(0).try_into();
+        result.expect_err("expected targets with different schemes to be rejected");
+    }
+
+    #[test]
+    fn prometheus_sends_alerts_to_alertmanager() {
+        let runtime_dir = Path::new("/tmp/.autometrics/am-prometheus-abcdef");
+        let config = super::generate_prom_config(
+            runtime_dir,
+            Duration::from_secs(5),
+            vec![],
+            vec![],
+            false,
+            &[],
+            Some(9093),
+        )
+        .unwrap();
+
+        let yaml = serde_yaml::to_string(&config.alerting).unwrap();
+        let expected = r#"alertmanagers:
+- static_configs:
+  - targets:
+    - localhost:9093
+  path_prefix: /alertmanager
+"#;
+        assert_eq!(expected, yaml);
+
+        let config = super::generate_prom_config(
+            runtime_dir,
+            Duration::from_secs(5),
+            vec![],
+            vec![],
+            false,
+            &[],
+            None,
+        )
+        .unwrap();
+        assert!(config.alerting.is_none());
+    }
+
+    #[test]
+    fn rules_are_written_to_runtime_dir() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+
+        let team_rules = first.path().join("team.rules.yml");
+        std::fs::write(&team_rules, "groups: []\n").unwrap();
+
+        super::write_autometrics_rules(first.path(), None).unwrap();
+        super::write_autometrics_rules(second.path(), Some(&team_rules)).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(second.path().join(super::AUTOMETRICS_RULES_FILE)).unwrap(),
+            "groups: []\n"
+        );
+
+        // Every instance refers to the rules in its own runtime directory.
+        for dir in [first.path(), second.path()] {
+            let config = super::generate_prom_config(
+                dir,
+                Duration::from_secs(5),
+                vec![],
+                vec![],
+                true,
+                std::slice::from_ref(&team_rules),
+                None,
+            )
+            .unwrap();
+
+            assert_eq!(
+                config.rule_files,
+                vec![
+                    dir.join(super::AUTOMETRICS_RULES_FILE)
+                        .to_string_lossy()
+                        .to_string(),
+                    team_rules.to_string_lossy().to_string()
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn scrape_options_survive_config_round_trip() {
+        let config: super::AmConfig = toml::from_str(SCRAPE_OPTIONS_CONFIG).unwrap();
+        let serialized = toml::to_string(&config).unwrap();
+        let deserialized: super::AmConfig = toml::from_str(&serialized).unwrap();
+
+        assert_eq!(
+            config.endpoints.unwrap()[0].scrape_options,
+            deserialized.endpoints.unwrap()[0].scrape_options
+        );
+    }
 }
@@ -0,0 +1,50 @@
+use autometrics_am::alertmanager::Alert;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// The amount of alert notifications that are kept in memory.
+pub(crate) const ALERT_HISTORY_CAPACITY: usize = 500;
+
+pub(crate) type SharedAlertHistory = Arc<AlertHistory>;
+
+/// An alert notification as it was received from Alertmanager.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ReceivedAlert {
+    pub(crate) received_at: String,
+    #[serde(flatten)]
+    pub(crate) alert: Alert,
+}
+
+/// A bounded history of the alert notifications received from Alertmanager,
+/// oldest first.
+pub(crate) struct AlertHistory {
+    alerts: Mutex<VecDeque<ReceivedAlert>>,
+    capacity: usize,
+}
+
+impl AlertHistory {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            alerts: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    pub(crate) fn push(&self, alert: Alert) {
+        let mut alerts = self.alerts.lock().unwrap();
+
+        if alerts.len() >= self.capacity {
+            alerts.pop_front();
+        }
+        alerts.push_back(ReceivedAlert {
+            received_at: humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            alert,
+        });
+    }
+
+    pub(crate) fn alerts(&self) -> Vec<ReceivedAlert> {
+        self.alerts.lock().unwrap().iter().cloned().collect()
+    }
+}
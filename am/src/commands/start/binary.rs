@@ -0,0 +1,121 @@
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use tokio::process;
+use tracing::{debug, warn};
+
+/// Resolve a binary path provided by the user against `base_dir`. Bare
+/// program names, such as `prometheus`, are left as is so they are looked up
+/// in the `PATH`.
+pub(crate) fn resolve_binary_path(path: PathBuf, base_dir: &Path) -> PathBuf {
+    if path.components().count() == 1 {
+        path
+    } else {
+        base_dir.join(path)
+    }
+}
+
+/// Run `binary --version` to make sure that the binary works, and warn if its
+/// version does not match the `expected` version.
+///
+/// Returns the version reported by the binary, if it could be determined.
+pub(crate) async fn check_version(
+    name: &str,
+    binary: &Path,
+    expected: Option<&str>,
+) -> Result<Option<String>> {
+    let output = process::Command::new(binary)
+        .arg("--version")
+        .output()
+        .await
+        .with_context(|| format!("Unable to run {name} binary {}", binary.display()))?;
+
+    if !output.status.success() {
+        bail!(
+            "{name} binary {} exited with {} when checking its version",
+            binary.display(),
+            output.status
+        );
+    }
+
+    // Depending on the version, the output is written to either stdout or
+    // stderr.
+    let output = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let Some(version) = parse_version(&output) else {
+        warn!(
+            "Unable to determine the version of {name} binary {}",
+            binary.display()
+        );
+        return Ok(None);
+    };
+
+    debug!("{name} binary {} has version {version}", binary.display());
+
+    if let Some(expected) = expected {
+        let expected = expected.trim_start_matches('v');
+        if version != expected {
+            warn!(
+                "{name} binary {} has version {version}, but version {expected} was requested",
+                binary.display()
+            );
+        }
+    }
+
+    Ok(Some(version))
+}
+
+/// Parse the version from the output of `--version`, which looks like:
+/// `prometheus, version 2.47.2 (branch: HEAD, revision: 3f3172cde1ee37f1c7b3a5f3d9b031190509b3ad)`
+fn parse_version(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let mut words = line.split_whitespace();
+        words.find(|word| *word == "version")?;
+        words
+            .next()
+            .map(|version| version.trim_start_matches('v').to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_version_output() {
+        let output = "prometheus, version 2.47.2 (branch: HEAD, revision: 3f3172cde1ee37f1c7b3a5f3d9b031190509b3ad)
+  build user:       root@79f2ad339b40
+  build date:       20231012-16:07:10
+  go version:       go1.21.3
+  platform:         linux/amd64
+  tags:             netgo,builtinassets,stringlabels";
+
+        assert_eq!(parse_version(output), Some("2.47.2".to_string()));
+        assert_eq!(
+            parse_version("pushgateway, version v1.6.2 (branch: HEAD)"),
+            Some("1.6.2".to_string())
+        );
+        assert_eq!(parse_version("usage: prometheus [<flags>]"), None);
+    }
+
+    #[test]
+    fn bare_program_names_are_not_resolved() {
+        let base_dir = Path::new("/srv/app");
+
+        assert_eq!(
+            resolve_binary_path(PathBuf::from("prometheus"), base_dir),
+            PathBuf::from("prometheus")
+        );
+        assert_eq!(
+            resolve_binary_path(PathBuf::from("bin/prometheus"), base_dir),
+            PathBuf::from("/srv/app/bin/prometheus")
+        );
+        assert_eq!(
+            resolve_binary_path(PathBuf::from("/usr/bin/prometheus"), base_dir),
+            PathBuf::from("/usr/bin/prometheus")
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use super::reload::SharedConfigManager;
+use super::Endpoint;
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use url::Url;
+
+/// The default location of the socket of the Docker Engine API.
+pub(crate) const DEFAULT_DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+/// How often the running containers are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The label containing the port the metrics are served on. Only containers
+/// with this label are scraped.
+const PORT_LABEL: &str = "autometrics.port";
+/// The label containing the path the metrics are served on. Defaults to
+/// `/metrics`.
+const PATH_LABEL: &str = "autometrics.path";
+/// The label containing the job name. Containers with the same job name are
+/// scraped as part of the same job. Defaults to the name of the container.
+const JOB_LABEL: &str = "autometrics.job";
+
+/// A container as it is returned by the `/containers/json` endpoint of the
+/// Docker Engine API. Only the fields that am uses are included.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct Container {
+    pub(crate) id: String,
+    #[serde(default)]
+    pub(crate) names: Vec<String>,
+    #[serde(default)]
+    pub(crate) labels: HashMap<String, String>,
+    #[serde(default)]
+    pub(crate) ports: Vec<ContainerPort>,
+    #[serde(default)]
+    pub(crate) network_settings: Option<NetworkSettings>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ContainerPort {
+    pub(crate) private_port: u16,
+    pub(crate) public_port: Option<u16>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct NetworkSettings {
+    #[serde(default)]
+    pub(crate) networks: BTreeMap<String, Network>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct Network {
+    #[serde(rename = "IPAddress", default)]
+    pub(crate) ip_address: String,
+}
+
+impl Container {
+    /// The name of the container, without the leading slash Docker adds.
+    fn name(&self) -> String {
+        self.names
+            .first()
+            .map(|name| name.trim_start_matches('/').to_string())
+            .unwrap_or_else(|| self.id.chars().take(12).collect())
+    }
+
+    /// The URL at which the metrics of this container can be scraped. If the
+    /// metrics port is published, the published port on localhost is used,
+    /// since container IPs are not reachable from the host on every platform.
+    fn metrics_url(&self) -> Result<Url> {
+        let port: u16 = self.labels[PORT_LABEL]
+            .parse()
+            .with_context(|| format!("invalid {PORT_LABEL} label"))?;
+
+        let path = self
+            .labels
+            .get(PATH_LABEL)
+            .map(String::as_str)
+            .unwrap_or("/metrics");
+
+        let published_port = self
+            .ports
+            .iter()
+            .find(|p| p.private_port == port)
+            .and_then(|p| p.public_port);
+
+        let address = match published_port {
+            Some(public_port) => format!("localhost:{public_port}"),
+            None => {
+                let ip_address = self
+                    .network_settings
+                    .iter()
+                    .flat_map(|settings| settings.networks.values())
+                    .map(|network| network.ip_address.as_str())
+                    .find(|ip_address| !ip_address.is_empty())
+                    .context("port is not published and container has no IP address")?;
+                format!("{ip_address}:{port}")
+            }
+        };
+
+        Ok(Url::parse(&format!("http://{address}"))?.join(path)?)
+    }
+}
+
+/// Turn all containers with the autometrics labels into endpoints. Containers
+/// with the same job name are combined into a single endpoint.
+///
+/// Also returns why containers that cannot be scraped were skipped.
+pub(crate) fn container_endpoints(containers: &[Container]) -> (Vec<Endpoint>, Vec<String>) {
+    let mut endpoints: Vec<Endpoint> = vec![];
+    let mut skipped = vec![];
+
+    for container in containers {
+        if !container.labels.contains_key(PORT_LABEL) {
+            continue;
+        }
+
+        let name = container.name();
+        let url = match container.metrics_url() {
+            Ok(url) => url,
+            Err(err) => {
+                skipped.push(format!("Unable to scrape container {name}: {err:#}"));
+                continue;
+            }
+        };
+
+        let job_name = container.labels.get(JOB_LABEL).cloned().unwrap_or(name);
+
+        match endpoints.iter_mut().find(|e| e.job_name == job_name) {
+            Some(endpoint) if endpoint.url.path() == url.path() => {
+                endpoint.additional_urls.push(url)
+            }
+            Some(_) => skipped.push(format!(
+                "Unable to scrape container {} as part of job {job_name}, since it uses a different {PATH_LABEL}",
+                container.name()
+            )),
+            None => endpoints.push(Endpoint::new(url, job_name, false, None)),
+        }
+    }
+
+    (endpoints, skipped)
+}
+
+/// A minimal client for the Docker Engine API, which is reached through its
+/// unix socket.
+pub(crate) struct DockerClient {
+    socket_path: PathBuf,
+}
+
+impl DockerClient {
+    pub(crate) fn new(socket_path: PathBuf) -> Self {
+        Self { socket_path }
+    }
+
+    /// List all running containers that have the `autometrics.port` label.
+    pub(crate) async fn list_containers(&self) -> Result<Vec<Container>> {
+        let filters = format!(r#"{{"label":["{PORT_LABEL}"]}}"#);
+        let filters: String = url::form_urlencoded::byte_serialize(filters.as_bytes()).collect();
+
+        self.get(&format!("/containers/json?filters={filters}"))
+            .await
+    }
+
+    #[cfg(unix)]
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        use http::header::HOST;
+        use hyper::{Body, Request};
+        use tokio::net::UnixStream;
+
+        let stream = UnixStream::connect(&self.socket_path)
+            .await
+            .with_context(|| format!("unable to connect to {}", self.socket_path.display()))?;
+
+        let (mut sender, connection) = hyper::client::conn::handshake(stream).await?;
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                debug!(?err, "Docker connection closed");
+            }
+        });
+
+        let request = Request::get(path)
+            .header(HOST, "docker")
+            .body(Body::empty())?;
+        let response = sender.send_request(request).await?;
+
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+
+        if !status.is_success() {
+            bail!(
+                "Docker responded with {status}: {}",
+                String::from_utf8_lossy(&body)
+            );
+        }
+
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    #[cfg(not(unix))]
+    async fn get<T: DeserializeOwned>(&self, _path: &str) -> Result<T> {
+        bail!("Docker discovery is only supported on unix platforms")
+    }
+}
+
+/// Periodically check the running containers and update the endpoints of
+/// Prometheus whenever containers with the autometrics labels are started or
+/// stopped.
+///
+/// Problems are logged once, when they first occur, instead of on every poll.
+pub(crate) async fn watch_containers(
+    client: DockerClient,
+    manager: SharedConfigManager,
+) -> Result<()> {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    let mut last_error: Option<String> = None;
+    let mut skipped: Vec<String> = vec![];
+    let mut conflicting: Vec<String> = vec![];
+
+    debug!(socket = ?client.socket_path, "Watching Docker containers");
+
+    loop {
+        interval.tick().await;
+
+        let containers = match client.list_containers().await {
+            Ok(containers) => {
+                last_error = None;
+                containers
+            }
+            Err(err) => {
+                let err = format!("{err:#}");
+                if last_error.as_ref() != Some(&err) {
+                    warn!("Unable to list Docker containers: {err}");
+                }
+                last_error = Some(err);
+                continue;
+            }
+        };
+
+        let (endpoints, skipped_containers) = container_endpoints(&containers);
+        for reason in &skipped_containers {
+            if !skipped.contains(reason) {
+                warn!("{reason}");
+            }
+        }
+        skipped = skipped_containers;
+
+        let mut manager = manager.lock().await;
+        let settings = manager.settings();
+
+        // Endpoints configured by the user take precedence over the ones that
+        // are discovered. This is checked on every poll, since the configured
+        // endpoints change when the am.toml file is reloaded.
+        let (discovered, conflicts): (Vec<_>, Vec<_>) = endpoints
+            .into_iter()
+            .partition(|endpoint| !settings.has_configured_job(&endpoint.job_name));
+
+        let conflicts: Vec<String> = conflicts
+            .into_iter()
+            .map(|endpoint| endpoint.job_name)
+            .collect();
+        for job_name in &conflicts {
+            if !conflicting.contains(job_name) {
+                warn!("Not sampling containers of job {job_name}, since a job with that name already exists");
+            }
+        }
+        conflicting = conflicts;
+
+        if discovered == settings.discovered_endpoints {
+            continue;
+        }
+
+        for endpoint in &discovered {
+            if !settings.discovered_endpoints.contains(endpoint) {
+                let urls: Vec<String> = endpoint.urls().map(ToString::to_string).collect();
+                info!(
+                    "Now sampling {} (job {})",
+                    urls.join(", "),
+                    endpoint.job_name
+                );
+            }
+        }
+
+        for endpoint in &settings.discovered_endpoints {
+            if !discovered.iter().any(|e| e.job_name == endpoint.job_name) {
+                info!("Stopped sampling job {}", endpoint.job_name);
+            }
+        }
+
+        if let Err(err) = manager
+            .update(|settings| settings.discovered_endpoints = discovered)
+            .await
+        {
+            warn!("Unable to update the discovered containers: {err:?}");
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use axum::Router;
+    use tokio::net::UnixListener;
+
+    const CONTAINERS: &str = r#"[
+        {
+            "Id": "8dfafdbc3a40",
+            "Names": ["/api-1"],
+            "Labels": { "autometrics.port": "3000", "autometrics.job": "api" },
+            "Ports": [{ "PrivatePort": 3000, "PublicPort": 49153, "Type": "tcp" }],
+            "NetworkSettings": { "Networks": { "bridge": { "IPAddress": "172.17.0.2" } } }
+        },
+        {
+            "Id": "9cd87474be90",
+            "Names": ["/api-2"],
+            "Labels": { "autometrics.port": "3000", "autometrics.job": "api" },
+            "Ports": [],
+            "NetworkSettings": { "Networks": { "bridge": { "IPAddress": "172.17.0.3" } } }
+        },
+        {
+            "Id": "3176a2479c92",
+            "Names": ["/worker"],
+            "Labels": { "autometrics.port": "9464", "autometrics.path": "/api/metrics" },
+            "Ports": [],
+            "NetworkSettings": { "Networks": { "bridge": { "IPAddress": "172.17.0.4" } } }
+        }
+    ]"#;
+
+    #[tokio::test]
+    async fn discovers_labeled_containers() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("docker.sock");
+
+        // Stand-in for the Docker Engine API, listening on a unix socket.
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        let app = Router::new().route("/containers/json", get(|| async { CONTAINERS }));
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let app = app.clone();
+                tokio::spawn(hyper::server::conn::Http::new().serve_connection(stream, app));
+            }
+        });
+
+        let client = DockerClient::new(socket_path);
+        let containers = client.list_containers().await.unwrap();
+        let (endpoints, skipped) = container_endpoints(&containers);
+
+        assert!(skipped.is_empty());
+        assert_eq!(endpoints.len(), 2);
+
+        assert_eq!(endpoints[0].job_name, "api");
+        let urls: Vec<&str> = endpoints[0].urls().map(Url::as_str).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "http://localhost:49153/metrics",
+                "http://172.17.0.3:3000/metrics"
+            ]
+        );
+
+        assert_eq!(endpoints[1].job_name, "worker");
+        assert_eq!(
+            endpoints[1].url.as_str(),
+            "http://172.17.0.4:9464/api/metrics"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use super::scrape_config;
+use anyhow::{Context, Result};
+use autometrics_am::config::FileSdJob;
+use autometrics_am::prometheus::{FileSdConfig, RelabelConfig, ScrapeConfig};
+use std::env;
+use std::path::Path;
+use tracing::warn;
+
+/// Resolve the files of all `jobs` against the directory containing the
+/// am.toml file at `config_file`, since Prometheus runs in its own working
+/// directory.
+pub(crate) fn resolve_file_sd_jobs(
+    jobs: Option<Vec<FileSdJob>>,
+    config_file: &Path,
+) -> Result<Vec<FileSdJob>> {
+    let base_dir = env::current_dir()
+        .context("Unable to determine the current directory")?
+        .join(config_file.parent().unwrap_or(Path::new("")));
+
+    let jobs = jobs
+        .unwrap_or_default()
+        .into_iter()
+        .map(|mut job| {
+            job.files = job.files.iter().map(|file| base_dir.join(file)).collect();
+
+            for file in &job.files {
+                let is_glob = file.to_string_lossy().contains(['*', '?', '[']);
+                if !is_glob && !file.exists() {
+                    warn!(
+                        "Targets file {} (job {}) does not exist yet, Prometheus will pick it up once it is created",
+                        file.display(),
+                        job.job_name
+                    );
+                }
+            }
+
+            job
+        })
+        .collect();
+
+    Ok(jobs)
+}
+
+/// Convert a file-based service discovery job into a Prometheus scrape
+/// config. Since the targets are not known up front, the `labels` of the job
+/// are added to every target using relabeling.
+pub(crate) fn file_sd_scrape_config(job: FileSdJob) -> ScrapeConfig {
+    let mut options = job.scrape_options;
+    let labels = std::mem::take(&mut options.labels);
+
+    ScrapeConfig {
+        file_sd_configs: vec![FileSdConfig {
+            files: job
+                .files
+                .iter()
+                .map(|file| file.to_string_lossy().to_string())
+                .collect(),
+            refresh_interval: job.refresh_interval,
+        }],
+        metrics_path: job.metrics_path,
+        scheme: job.scheme,
+        relabel_configs: labels
+            .into_iter()
+            .map(|(label, value)| RelabelConfig::set_label(label, value))
+            .collect(),
+        ..scrape_config(
+            job.job_name,
+            job.honor_labels.unwrap_or(false),
+            job.prometheus_scrape_interval,
+            options,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use autometrics_am::config::AmConfig;
+
+    #[test]
+    fn file_sd_job_is_passed_to_prometheus() {
+        let config: AmConfig = toml::from_str(
+            r#"
+            [[file-sd]]
+            job-name = "compose"
+            files = ["targets/*.json"]
+            refresh-interval = "30s"
+            labels = { env = "dev" }
+            "#,
+        )
+        .unwrap();
+
+        let jobs = resolve_file_sd_jobs(config.file_sd, Path::new("/srv/app/am.toml")).unwrap();
+        let yaml = serde_yaml::to_string(&file_sd_scrape_config(jobs[0].clone())).unwrap();
+
+        let expected = r#"job_name: compose
+file_sd_configs:
+- files:
+  - /srv/app/targets/*.json
+  refresh_interval: 30s
+honor_labels: false
+relabel_configs:
+- target_label: env
+  replacement: dev
+  action: replace
+"#;
+        assert_eq!(expected, yaml);
+    }
+}
@@ -0,0 +1,367 @@
+use super::binary::check_version;
+use super::manifest::Manifest;
+use crate::components::{Component, COMPONENTS};
+use crate::downloader::{
+    download_checksums, download_release, file_checksum, unpack, verify_checksum, ReleaseSource,
+};
+use anyhow::{Context, Result};
+use indicatif::MultiProgress;
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{debug, info, warn};
+
+/// The file inside of an installation that is touched whenever `am start`
+/// uses it.
+pub(crate) const LAST_USED_FILE: &str = ".last-used";
+
+/// A version of a component that is installed in the local data directory.
+#[derive(Debug, Clone)]
+pub(crate) struct Installation {
+    pub(crate) component: &'static Component,
+    pub(crate) version: String,
+    pub(crate) path: PathBuf,
+    /// The size of the installation on disk, in bytes.
+    pub(crate) size: u64,
+    /// When `am start` last used this installation. Falls back to the time it
+    /// was installed, for installations that were never used.
+    pub(crate) last_used: Option<SystemTime>,
+}
+
+/// Find all installations in `local_data`, sorted by component and then by
+/// version, newest first.
+pub(crate) fn installations(local_data: &Path) -> Result<Vec<Installation>> {
+    let entries = match fs::read_dir(local_data) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Unable to read {}", local_data.display()))
+        }
+    };
+
+    let mut installations = vec![];
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let file_name = entry.file_name();
+        let Some(name) = file_name.to_str() else {
+            continue;
+        };
+
+        let Some((component, version)) = COMPONENTS.iter().find_map(|component| {
+            let version = name.strip_prefix(component.id)?.strip_prefix('-')?;
+            Some((*component, version))
+        }) else {
+            continue;
+        };
+
+        let path = entry.path();
+        let last_used = fs::metadata(path.join(LAST_USED_FILE))
+            .or_else(|_| entry.metadata())
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        installations.push(Installation {
+            component,
+            version: version.to_string(),
+            size: dir_size(&path)?,
+            path,
+            last_used,
+        });
+    }
+
+    installations.sort_by(|a, b| {
+        a.component
+            .id
+            .cmp(b.component.id)
+            .then_with(|| compare_versions(&b.version, &a.version))
+    });
+
+    Ok(installations)
+}
+
+/// Record that the installation at `path` was used just now.
+pub(crate) fn mark_used(path: &Path) -> Result<()> {
+    fs::write(path.join(LAST_USED_FILE), "")
+        .with_context(|| format!("Unable to update {}", path.display()))
+}
+
+pub(crate) fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+
+    Ok(size)
+}
+
+/// Compare two versions such as `2.47.2` by their numeric parts, so that
+/// `2.10.0` is considered newer than `2.9.0`.
+pub(crate) fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn parts(version: &str) -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split(['.', '-'])
+            .map_while(|part| part.parse().ok())
+            .collect()
+    }
+
+    parts(a).cmp(&parts(b)).then_with(|| a.cmp(b))
+}
+
+/// Determine the binary of `component` to run. This is either the `binary`
+/// provided by the user, or the binary of the installation of `version` in
+/// `local_data`, which is installed first if needed.
+pub(crate) async fn resolve_binary(
+    component: &Component,
+    binary: Option<PathBuf>,
+    version: Option<&str>,
+    local_data: &Path,
+    source: &ReleaseSource,
+    multi_progress: &MultiProgress,
+) -> Result<PathBuf> {
+    if let Some(binary) = binary {
+        info!("Using {} binary: {}", component.name, binary.display());
+        check_version(component.name, &binary, version).await?;
+        return Ok(binary);
+    }
+
+    let version = version
+        .unwrap_or(component.default_version)
+        .trim_start_matches('v');
+
+    info!("Using {} version: {}", component.name, version);
+
+    let path = component.install_dir(local_data, version);
+
+    // Check if the component is available, and that it is not corrupted
+    ensure_installed(component, &path, version, source, multi_progress).await?;
+
+    let binary = path.join(component.binary_name());
+    check_version(component.name, &binary, Some(version)).await?;
+
+    if let Err(err) = mark_used(&path) {
+        debug!("Unable to record that {} was used: {err:#}", component.name);
+    }
+
+    Ok(binary)
+}
+
+/// Make sure that a valid installation of `component` exists in `path`. If
+/// there is no installation, or if it does not match its manifest (for
+/// example because a previous install was interrupted), it is (re)installed.
+pub(crate) async fn ensure_installed(
+    component: &Component,
+    path: &Path,
+    version: &str,
+    source: &ReleaseSource,
+    multi_progress: &MultiProgress,
+) -> Result<()> {
+    let name = component.name;
+
+    if !path.exists() {
+        info!("Cached version of {name} not found, downloading {name}");
+    } else {
+        match verify(path) {
+            Ok(()) => {
+                debug!("Found {name} in: {:?}", path);
+                return Ok(());
+            }
+            Err(err) => {
+                warn!("Cached version of {name} is invalid ({err:#}), reinstalling {name}");
+                fs::remove_dir_all(path)
+                    .with_context(|| format!("Unable to remove {}", path.display()))?;
+            }
+        }
+    }
+
+    install(component, path, version, source, multi_progress).await?;
+    debug!("Downloaded {name} to: {:?}", path);
+    Ok(())
+}
+
+/// Verify the installation in `path` against its manifest.
+pub(crate) fn verify(path: &Path) -> Result<()> {
+    Manifest::read(path)?.verify(path)
+}
+
+/// Install the specified version of `component` into `path`.
+///
+/// This function will first download the archive into a `.part` file next to
+/// `path`, resuming a previous download if there is one. Then it will verify
+/// the downloaded archive against the downloaded checksum. Finally it will
+/// unpack the archive into `path`.
+pub(crate) async fn install(
+    component: &Component,
+    path: &Path,
+    version: &str,
+    source: &ReleaseSource,
+    multi_progress: &MultiProgress,
+) -> Result<()> {
+    let version = version.trim_start_matches('v');
+    let package = component.package(version)?;
+    let prefix = format!("{}/", component.package_base(version)?);
+
+    let parent = path.parent().unwrap_or(Path::new("."));
+    let archive_path = parent.join(format!("{package}.part"));
+
+    let calculated_checksum = download_release(
+        &archive_path,
+        source,
+        component.github_org,
+        component.github_repo,
+        version,
+        &package,
+        multi_progress,
+    )
+    .await?;
+
+    let checksums =
+        download_checksums(source, component.github_org, component.github_repo, version).await?;
+    if let Err(err) = verify_checksum(&calculated_checksum, &checksums, &package) {
+        // Don't try to resume a corrupted download the next time.
+        fs::remove_file(&archive_path)?;
+        return Err(err);
+    }
+
+    let archive = File::open(&archive_path)?;
+    unpack_atomically(
+        &archive,
+        component,
+        path,
+        &package,
+        &prefix,
+        &calculated_checksum,
+        multi_progress,
+    )
+    .await?;
+
+    fs::remove_file(&archive_path)
+        .with_context(|| format!("Unable to remove {}", archive_path.display()))
+}
+
+/// Install `component` from a local `archive` into `path`, without using the
+/// network. The archive is verified against the `checksums` file, which uses
+/// the same format as the `sha256sums.txt` file of a release.
+pub(crate) async fn install_from_archive(
+    component: &Component,
+    path: &Path,
+    archive: &Path,
+    checksums: &Path,
+    multi_progress: &MultiProgress,
+) -> Result<()> {
+    let package = archive
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("Invalid archive file name")?;
+    let base = package
+        .strip_suffix(".tar.gz")
+        .with_context(|| format!("{package} is not a .tar.gz archive"))?;
+    let prefix = format!("{base}/");
+
+    let calculated_checksum =
+        file_checksum(archive).with_context(|| format!("Unable to read {}", archive.display()))?;
+
+    let checksums = fs::read_to_string(checksums)
+        .with_context(|| format!("Unable to read {}", checksums.display()))?;
+    verify_checksum(&calculated_checksum, &checksums, package)?;
+
+    let file =
+        File::open(archive).with_context(|| format!("Unable to open {}", archive.display()))?;
+    unpack_atomically(
+        &file,
+        component,
+        path,
+        package,
+        &prefix,
+        &calculated_checksum,
+        multi_progress,
+    )
+    .await
+}
+
+/// Unpack `archive` into a temporary directory next to `path` and write the
+/// manifest, before renaming it to `path`. This makes sure that `path` never
+/// contains a partial installation.
+async fn unpack_atomically(
+    archive: &File,
+    component: &Component,
+    path: &Path,
+    package: &str,
+    prefix: &str,
+    archive_sha256: &str,
+    multi_progress: &MultiProgress,
+) -> Result<()> {
+    let parent = path.parent().unwrap_or(Path::new("."));
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(component.id);
+    let temp_dir = tempfile::Builder::new()
+        .prefix(&format!(".{file_name}-"))
+        .tempdir_in(parent)
+        .with_context(|| {
+            format!(
+                "Unable to create a temporary directory in {}",
+                parent.display()
+            )
+        })?;
+
+    unpack(
+        archive,
+        component.id,
+        temp_dir.path(),
+        prefix,
+        multi_progress,
+    )
+    .await?;
+
+    Manifest::create(temp_dir.path(), package, archive_sha256)?.write(temp_dir.path())?;
+
+    // Once it is renamed, dropping the temporary directory does nothing.
+    fs::rename(temp_dir.path(), path)
+        .with_context(|| format!("Unable to move {} into {}", component.id, path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn installations_are_sorted_by_version() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "prometheus-2.9.0",
+            "prometheus-2.47.2",
+            "pushgateway-1.6.2",
+            "unrelated",
+        ] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+        }
+        fs::write(dir.path().join("prometheus-2.47.2/prometheus"), "binary").unwrap();
+
+        let installations = installations(dir.path()).unwrap();
+        let versions: Vec<_> = installations
+            .iter()
+            .map(|i| format!("{}-{}", i.component.id, i.version))
+            .collect();
+
+        assert_eq!(
+            versions,
+            vec!["prometheus-2.47.2", "prometheus-2.9.0", "pushgateway-1.6.2"]
+        );
+        assert_eq!(installations[0].size, 6);
+        assert!(installations[0].last_used.is_some());
+    }
+}
@@ -0,0 +1,242 @@
+use crate::commands::start::supervisor::ChildProcess;
+use crate::interactive::OutputSink;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::broadcast;
+use tracing::trace;
+
+/// The amount of lines that are kept in memory for every child process.
+pub(crate) const LOG_BUFFER_CAPACITY: usize = 1000;
+
+/// The log buffers of all child processes, keyed by [`ChildProcess::id`].
+pub(crate) type LogBuffers = HashMap<&'static str, Arc<LogBuffer>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct LogLine {
+    /// Sequence number of this line, which is unique for a single process.
+    pub(crate) seq: u64,
+    pub(crate) timestamp: String,
+    pub(crate) stream: LogStream,
+    pub(crate) line: String,
+}
+
+/// A bounded buffer containing the most recent output of a child process.
+/// New lines are also broadcast to all subscribers.
+pub(crate) struct LogBuffer {
+    inner: Mutex<LogBufferInner>,
+    tx: broadcast::Sender<LogLine>,
+}
+
+struct LogBufferInner {
+    lines: VecDeque<LogLine>,
+    capacity: usize,
+    next_seq: u64,
+}
+
+impl LogBuffer {
+    pub(crate) fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity.max(1));
+
+        Self {
+            inner: Mutex::new(LogBufferInner {
+                lines: VecDeque::with_capacity(capacity),
+                capacity,
+                next_seq: 0,
+            }),
+            tx,
+        }
+    }
+
+    pub(crate) fn push(&self, stream: LogStream, line: String) -> LogLine {
+        let mut inner = self.inner.lock().unwrap();
+
+        let line = LogLine {
+            seq: inner.next_seq,
+            timestamp: humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            stream,
+            line,
+        };
+        inner.next_seq += 1;
+
+        if inner.lines.len() >= inner.capacity {
+            inner.lines.pop_front();
+        }
+        inner.lines.push_back(line.clone());
+
+        // Sending only fails if there are no subscribers, which is fine.
+        let _ = self.tx.send(line.clone());
+
+        line
+    }
+
+    /// All the lines that are currently in the buffer.
+    pub(crate) fn lines(&self) -> Vec<LogLine> {
+        self.lines_since(0)
+    }
+
+    /// All the lines in the buffer with a sequence number of at least `seq`.
+    pub(crate) fn lines_since(&self, seq: u64) -> Vec<LogLine> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .lines
+            .iter()
+            .filter(|line| line.seq >= seq)
+            .cloned()
+            .collect()
+    }
+
+    /// The sequence number that will be given to the next line.
+    pub(crate) fn next_seq(&self) -> u64 {
+        self.inner.lock().unwrap().next_seq
+    }
+
+    /// Returns the lines that are currently in the buffer, together with a
+    /// receiver for all lines that are added afterwards.
+    pub(crate) fn subscribe(&self) -> (Vec<LogLine>, broadcast::Receiver<LogLine>) {
+        // Hold the lock while subscribing, so no lines can be added in between.
+        let inner = self.inner.lock().unwrap();
+        let rx = self.tx.subscribe();
+        (inner.lines.iter().cloned().collect(), rx)
+    }
+}
+
+/// The log messages of am itself are stored as stderr output while they are
+/// captured.
+impl OutputSink for LogBuffer {
+    fn write_line(&self, line: String) {
+        self.push(LogStream::Stderr, line);
+    }
+}
+
+/// The log level of a line of output of a child process. Prometheus and
+/// Pushgateway use logfmt, so the level is read from its `level` field.
+#[derive(Debug, PartialEq)]
+enum Level {
+    Error,
+    Warn,
+    Debug,
+}
+
+impl Level {
+    fn from_line(line: &str) -> Self {
+        let level = line
+            .split_whitespace()
+            .find_map(|field| field.strip_prefix("level="));
+
+        match level {
+            Some("error") => Level::Error,
+            Some("warn") => Level::Warn,
+            _ => Level::Debug,
+        }
+    }
+}
+
+macro_rules! log_child_line {
+    ($target:literal, $level:expr, $name:expr, $line:expr) => {
+        match $level {
+            Level::Error => tracing::error!(target: $target, "{}: {}", $name, $line),
+            Level::Warn => tracing::warn!(target: $target, "{}: {}", $name, $line),
+            Level::Debug => tracing::debug!(target: $target, "{}: {}", $name, $line),
+        }
+    };
+}
+
+/// Forward a line of output of a child process to tracing. Every process
+/// uses its own target, for example `am::prometheus`. Only warnings and errors
+/// are shown by default, all other output is logged at the debug level.
+fn log_line(process: ChildProcess, line: &str) {
+    let level = Level::from_line(line);
+    let name = process.name();
+
+    match process {
+        ChildProcess::Prometheus => log_child_line!("am::prometheus", level, name, line),
+        ChildProcess::Pushgateway => log_child_line!("am::pushgateway", level, name, line),
+        ChildProcess::Alertmanager => log_child_line!("am::alertmanager", level, name, line),
+    }
+}
+
+/// Read the output of a child process line by line, until it is closed. Every
+/// line is forwarded to tracing and stored in `buffer`.
+pub(crate) async fn capture_output<R>(
+    process: ChildProcess,
+    stream: LogStream,
+    output: R,
+    buffer: &LogBuffer,
+) where
+    R: AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(output);
+    let mut line = Vec::new();
+
+    loop {
+        line.clear();
+
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(0) => break,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&line).trim_end().to_string();
+                log_line(process, &line);
+                buffer.push(stream, line);
+            }
+            Err(err) => {
+                trace!(?err, "Unable to read output of {}", process.name());
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_is_bounded() {
+        let buffer = LogBuffer::new(2);
+
+        buffer.push(LogStream::Stdout, "first".to_string());
+        buffer.push(LogStream::Stderr, "second".to_string());
+        buffer.push(LogStream::Stdout, "third".to_string());
+
+        let lines: Vec<String> = buffer.lines().into_iter().map(|line| line.line).collect();
+        assert_eq!(lines, vec!["second", "third"]);
+        assert_eq!(buffer.next_seq(), 3);
+        assert_eq!(buffer.lines_since(2).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn subscribers_receive_new_lines() {
+        let buffer = LogBuffer::new(10);
+        buffer.push(LogStream::Stdout, "backlog".to_string());
+
+        let (backlog, mut rx) = buffer.subscribe();
+        buffer.push(LogStream::Stdout, "live".to_string());
+
+        assert_eq!(backlog.len(), 1);
+        assert_eq!(rx.recv().await.unwrap().line, "live");
+    }
+
+    #[test]
+    fn level_is_read_from_logfmt() {
+        assert_eq!(
+            Level::from_line("ts=2023-11-01 caller=main.go:1 level=warn msg=\"oops\""),
+            Level::Warn
+        );
+        assert_eq!(Level::from_line("level=error msg=boom"), Level::Error);
+        assert_eq!(
+            Level::from_line("level=info msg=\"Server is ready\""),
+            Level::Debug
+        );
+        assert_eq!(Level::from_line("not logfmt at all"), Level::Debug);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::downloader::file_checksum;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The name of the manifest file inside of an installation.
+pub(crate) const MANIFEST_FILE: &str = ".manifest.json";
+
+/// Records what was installed into a directory, so that an installation can
+/// be verified before it is used.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub(crate) struct Manifest {
+    /// The name of the archive the installation was unpacked from.
+    pub(crate) package: String,
+    /// The sha256 checksum of the archive.
+    pub(crate) archive_sha256: String,
+    /// The sha256 checksum of every installed file, keyed by its path relative
+    /// to the installation directory.
+    pub(crate) files: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    /// Create a manifest for all files in `dir`.
+    pub(crate) fn create(dir: &Path, package: &str, archive_sha256: &str) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        hash_files(dir, dir, &mut files)?;
+
+        Ok(Self {
+            package: package.to_string(),
+            archive_sha256: archive_sha256.to_string(),
+            files,
+        })
+    }
+
+    pub(crate) fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let manifest = fs::read(&path)
+            .with_context(|| format!("Unable to read manifest {}", path.display()))?;
+
+        serde_json::from_slice(&manifest)
+            .with_context(|| format!("Invalid manifest {}", path.display()))
+    }
+
+    pub(crate) fn write(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(MANIFEST_FILE);
+        fs::write(&path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Unable to write manifest {}", path.display()))
+    }
+
+    /// Verify that all files in the manifest are present in `dir` and that
+    /// their contents did not change. Files that are not in the manifest are
+    /// ignored.
+    pub(crate) fn verify(&self, dir: &Path) -> Result<()> {
+        for (file, expected) in &self.files {
+            let path = dir.join(file);
+            let checksum = match file_checksum(&path) {
+                Ok(checksum) => checksum,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => bail!("{file} is missing"),
+                Err(err) => {
+                    return Err(err).with_context(|| format!("Unable to read {}", path.display()))
+                }
+            };
+
+            if &checksum != expected {
+                bail!("checksum of {file} does not match");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn hash_files(base: &Path, dir: &Path, files: &mut BTreeMap<String, String>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if entry.file_type()?.is_dir() {
+            hash_files(base, &path, files)?;
+            continue;
+        }
+
+        let relative = path.strip_prefix(base)?;
+        if relative == Path::new(MANIFEST_FILE) {
+            continue;
+        }
+
+        let key = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.insert(key, file_checksum(&path)?);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_modified_and_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("console_libraries")).unwrap();
+        fs::write(dir.path().join("prometheus"), "binary").unwrap();
+        fs::write(dir.path().join("console_libraries/menu.lib"), "menu").unwrap();
+
+        let manifest = Manifest::create(dir.path(), "prometheus.tar.gz", "abc").unwrap();
+        manifest.write(dir.path()).unwrap();
+
+        let manifest = Manifest::read(dir.path()).unwrap();
+        assert_eq!(
+            manifest.files.keys().collect::<Vec<_>>(),
+            vec!["console_libraries/menu.lib", "prometheus"]
+        );
+        manifest
+            .verify(dir.path())
+            .expect("expected files to match");
+
+        // Files that are added after the installation are ignored.
+        fs::write(dir.path().join(".last-used"), "").unwrap();
+        manifest
+            .verify(dir.path())
+            .expect("expected files to match");
+
+        fs::write(dir.path().join("prometheus"), "truncated").unwrap();
+        manifest
+            .verify(dir.path())
+            .expect_err("expected modified file to be detected");
+
+        fs::remove_file(dir.path().join("prometheus")).unwrap();
+        manifest
+            .verify(dir.path())
+            .expect_err("expected missing file to be detected");
+    }
+}
@@ -0,0 +1,75 @@
+use anyhow::{bail, Context, Result};
+use std::net::{Ipv4Addr, TcpListener};
+use tracing::info;
+
+pub(crate) const DEFAULT_PROMETHEUS_PORT: u16 = 9090;
+pub(crate) const DEFAULT_PUSHGATEWAY_PORT: u16 = 9091;
+pub(crate) const DEFAULT_ALERTMANAGER_PORT: u16 = 9093;
+
+/// Determine the port that the process `name` will listen on:
+///
+/// - If a port was requested, that port is used. It is an error if the port is
+///   already in use.
+/// - If port 0 was requested, a free port is selected.
+/// - If no port was requested, `default` is used if it is available, otherwise
+///   a free port is selected.
+///
+/// Note that the port is only reserved while it is checked, so another process
+/// could still claim it before the child process has started.
+pub(crate) fn select_port(name: &str, requested: Option<u16>, default: u16) -> Result<u16> {
+    match requested {
+        Some(0) => free_port(),
+        Some(port) => {
+            if !is_available(port) {
+                bail!("Unable to start {name}: port {port} is already in use");
+            }
+
+            Ok(port)
+        }
+        None if is_available(default) => Ok(default),
+        None => {
+            let port = free_port()?;
+            info!("Port {default} is already in use, {name} will listen on port {port} instead");
+            Ok(port)
+        }
+    }
+}
+
+fn is_available(port: u16) -> bool {
+    TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).is_ok()
+}
+
+fn free_port() -> Result<u16> {
+    let listener =
+        TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).context("Unable to find a free port")?;
+    Ok(listener.local_addr()?.port())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falls_back_to_free_port_if_default_is_in_use() {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let taken = listener.local_addr().unwrap().port();
+
+        let port = select_port("Prometheus", None, taken).unwrap();
+        assert_ne!(port, taken);
+        assert_ne!(port, 0);
+    }
+
+    #[test]
+    fn requested_port_must_be_available() {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let taken = listener.local_addr().unwrap().port();
+
+        assert!(select_port("Prometheus", Some(taken), DEFAULT_PROMETHEUS_PORT).is_err());
+
+        drop(listener);
+        assert_eq!(
+            select_port("Prometheus", Some(taken), DEFAULT_PROMETHEUS_PORT).unwrap(),
+            taken
+        );
+    }
+}
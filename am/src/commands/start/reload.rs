@@ -0,0 +1,386 @@
+use super::file_sd::resolve_file_sd_jobs;
+use super::rules::{check_rule_files, resolve_rule_files};
+use super::{generate_prom_config, Endpoint, CLIENT};
+use anyhow::{bail, Context, Result};
+use autometrics_am::config::{endpoints_from_first_input, FileSdJob};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::{watch, Mutex};
+use tracing::{debug, error, info, warn};
+use url::Url;
+
+/// The name of the Prometheus configuration file in the runtime directory.
+pub(crate) const PROMETHEUS_CONFIG_FILE: &str = "prometheus.yml";
+
+/// How often the am.toml file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub(crate) type SharedConfigManager = Arc<Mutex<PrometheusConfigManager>>;
+
+/// All the settings that are used to generate the Prometheus configuration
+/// and which can change while `am start` is running.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ScrapeSettings {
+    /// The endpoints that were provided by the user, either through the CLI
+    /// or through the am.toml file.
+    pub(crate) endpoints: Vec<Endpoint>,
+
+    /// Endpoints that were added through the `/api/endpoints` API while am is
+    /// running. These are not affected by changes to the am.toml file.
+    pub(crate) runtime_endpoints: Vec<Endpoint>,
+
+    /// Endpoints that am adds itself, such as the Pushgateway or its own web
+    /// server. These are not affected by changes to the am.toml file.
+    pub(crate) internal_endpoints: Vec<Endpoint>,
+
+    /// Endpoints of the Docker containers that are discovered while am is
+    /// running. These are not affected by changes to the am.toml file.
+    pub(crate) discovered_endpoints: Vec<Endpoint>,
+
+    /// Jobs whose targets are discovered by Prometheus itself, using the
+    /// files configured in the am.toml file.
+    pub(crate) file_sd_jobs: Vec<FileSdJob>,
+
+    pub(crate) scrape_interval: Duration,
+    pub(crate) enable_rules: bool,
+
+    /// Rule files provided by the user, which are loaded regardless of
+    /// `enable_rules`.
+    pub(crate) rule_files: Vec<PathBuf>,
+
+    /// The port of the local Alertmanager instance that Prometheus sends its
+    /// alerts to, if it is enabled.
+    pub(crate) alertmanager_port: Option<u16>,
+}
+
+impl ScrapeSettings {
+    pub(crate) fn all_endpoints(&self) -> impl Iterator<Item = &Endpoint> {
+        self.endpoints
+            .iter()
+            .chain(self.runtime_endpoints.iter())
+            .chain(self.internal_endpoints.iter())
+            .chain(self.discovered_endpoints.iter())
+    }
+
+    /// Whether a job with `job_name` is part of the configuration.
+    pub(crate) fn has_job(&self, job_name: &str) -> bool {
+        self.all_endpoints()
+            .any(|endpoint| endpoint.job_name == job_name)
+            || self.file_sd_jobs.iter().any(|job| job.job_name == job_name)
+    }
+
+    /// Whether a job with `job_name` is part of the configuration, not
+    /// counting the endpoints that were discovered.
+    pub(crate) fn has_configured_job(&self, job_name: &str) -> bool {
+        self.endpoints
+            .iter()
+            .chain(self.runtime_endpoints.iter())
+            .chain(self.internal_endpoints.iter())
+            .any(|endpoint| endpoint.job_name == job_name)
+            || self.file_sd_jobs.iter().any(|job| job.job_name == job_name)
+    }
+
+    /// Remove the discovered endpoints that use the same job name as a
+    /// configured one, since Prometheus rejects duplicate job names and the
+    /// configured endpoints take precedence.
+    pub(crate) fn remove_conflicting_discovered_endpoints(&mut self) {
+        let discovered = std::mem::take(&mut self.discovered_endpoints);
+        self.discovered_endpoints = discovered
+            .into_iter()
+            .filter(|endpoint| !self.has_configured_job(&endpoint.job_name))
+            .collect();
+    }
+}
+
+/// Keeps track of the Prometheus configuration file of a running Prometheus
+/// instance. Whenever the settings are changed the configuration is written
+/// to disk and Prometheus is instructed to reload it.
+pub(crate) struct PrometheusConfigManager {
+    settings: ScrapeSettings,
+    /// The directory of this am instance containing the configuration file,
+    /// the rules and other generated files.
+    runtime_dir: PathBuf,
+    reload_url: Url,
+}
+
+impl PrometheusConfigManager {
+    pub(crate) fn new(settings: ScrapeSettings, runtime_dir: PathBuf, reload_url: Url) -> Self {
+        Self {
+            settings,
+            runtime_dir,
+            reload_url,
+        }
+    }
+
+    pub(crate) fn settings(&self) -> &ScrapeSettings {
+        &self.settings
+    }
+
+    pub(crate) fn config_file_path(&self) -> PathBuf {
+        self.runtime_dir.join(PROMETHEUS_CONFIG_FILE)
+    }
+
+    /// Generate the Prometheus configuration from the current settings and
+    /// write it to the configuration file.
+    pub(crate) fn write(&self) -> Result<()> {
+        let config = generate_prom_config(
+            &self.runtime_dir,
+            self.settings.scrape_interval,
+            self.settings.all_endpoints().cloned().collect(),
+            self.settings.file_sd_jobs.clone(),
+            self.settings.enable_rules,
+            &self.settings.rule_files,
+            self.settings.alertmanager_port,
+        )?;
+
+        let config_file_path = self.config_file_path();
+        let config_file = File::create(&config_file_path)?;
+        serde_yaml::to_writer(&config_file, &config)?;
+
+        debug!(path = ?config_file_path, "Written Prometheus config");
+        Ok(())
+    }
+
+    /// Apply `update` to the settings, write the new configuration and let
+    /// Prometheus reload it.
+    ///
+    /// If any of these steps fail, the previous settings are restored and
+    /// written back to disk. Prometheus will keep using its previous
+    /// configuration if it was unable to load the new one.
+    pub(crate) async fn update<F>(&mut self, update: F) -> Result<()>
+    where
+        F: FnOnce(&mut ScrapeSettings),
+    {
+        let previous = self.settings.clone();
+        update(&mut self.settings);
+
+        let result = match self.write() {
+            Ok(_) => self.reload().await,
+            Err(err) => Err(err),
+        };
+
+        if result.is_err() {
+            self.settings = previous;
+            if let Err(err) = self.write() {
+                error!(?err, "Unable to restore the previous Prometheus config");
+            }
+        }
+
+        result
+    }
+
+    /// Instruct Prometheus to reload its configuration file, using its
+    /// lifecycle API.
+    async fn reload(&self) -> Result<()> {
+        let response = CLIENT
+            .post(self.reload_url.as_str())
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .context("unable to reach Prometheus")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            bail!("Prometheus rejected the new configuration ({status}): {body}");
+        }
+
+        Ok(())
+    }
+}
+
+/// Settings passed on the command line, which take precedence over the
+/// am.toml file and thus are not affected when it is reloaded.
+#[derive(Debug, Clone)]
+pub(crate) struct CliOverrides {
+    pub(crate) endpoints: bool,
+    pub(crate) scrape_interval: Option<Duration>,
+}
+
+/// Watch the am.toml file at `path` and apply any changes to the endpoints,
+/// file_sd jobs, rule files or the scrape interval to the running Prometheus
+/// instance.
+///
+/// The rule files are validated with the promtool from `promtool`, once the
+/// Prometheus binary has been resolved. Invalid configurations are logged and
+/// the previous configuration is kept.
+pub(crate) async fn watch_config_file(
+    path: PathBuf,
+    overrides: CliOverrides,
+    manager: SharedConfigManager,
+    promtool: watch::Receiver<Option<PathBuf>>,
+) -> Result<()> {
+    let mut last_modified = modified_time(&path);
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+    debug!(?path, "Watching config file for changes");
+
+    loop {
+        interval.tick().await;
+
+        // A file that (temporarily) does not exist is not considered a change,
+        // since editors might remove the file before writing the new version.
+        let modified = modified_time(&path);
+        if modified.is_none() || modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+
+        info!("Detected a change in {}, reloading", path.display());
+
+        let config = match crate::load_config(Some(path.clone())).await {
+            Ok(config) => config,
+            Err(err) => {
+                error!("Unable to reload config, keeping the previous configuration: {err:?}");
+                continue;
+            }
+        };
+
+        let endpoints: Vec<Endpoint> = if overrides.endpoints {
+            debug!("Endpoints were provided on the command line, ignoring config endpoints");
+            manager.lock().await.settings().endpoints.clone()
+        } else {
+            match endpoints_from_first_input(vec![], config.endpoints)
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect()
+            {
+                Ok(endpoints) => endpoints,
+                Err(err) => {
+                    error!(
+                        "Invalid endpoint in config, keeping the previous configuration: {err:?}"
+                    );
+                    continue;
+                }
+            }
+        };
+
+        let file_sd_jobs = match resolve_file_sd_jobs(config.file_sd, &path) {
+            Ok(jobs) => jobs,
+            Err(err) => {
+                error!(
+                    "Invalid file_sd job in config, keeping the previous configuration: {err:?}"
+                );
+                continue;
+            }
+        };
+
+        let rule_files = match resolve_rule_files(config.rule_files, &path) {
+            Ok(files) => files,
+            Err(err) => {
+                error!("Invalid rule files in config, keeping the previous configuration: {err:?}");
+                continue;
+            }
+        };
+
+        let promtool = promtool.borrow().clone();
+        if let Some(promtool) = promtool {
+            if let Err(err) = check_rule_files(&promtool, &rule_files).await {
+                error!("Unable to validate the rule files, keeping the previous configuration: {err:?}");
+                continue;
+            }
+        }
+
+        let scrape_interval = overrides
+            .scrape_interval
+            .or(config.prometheus_scrape_interval)
+            .unwrap_or(super::DEFAULT_SCRAPE_INTERVAL);
+
+        let result = manager
+            .lock()
+            .await
+            .update(|settings| {
+                settings.endpoints = endpoints;
+                settings.file_sd_jobs = file_sd_jobs;
+                settings.rule_files = rule_files;
+                settings.remove_conflicting_discovered_endpoints();
+                settings.scrape_interval = scrape_interval;
+            })
+            .await;
+
+        match result {
+            Ok(_) => info!("Prometheus configuration reloaded"),
+            Err(err) => {
+                warn!("Unable to reload Prometheus, keeping the previous configuration: {err:?}")
+            }
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn update_restores_previous_config_on_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_file_path = dir.path().join(PROMETHEUS_CONFIG_FILE);
+
+        let settings = ScrapeSettings {
+            endpoints: vec![Endpoint::new(
+                Url::parse("http://localhost:3000/metrics").unwrap(),
+                "am_0".to_string(),
+                false,
+                None,
+            )],
+            runtime_endpoints: vec![],
+            internal_endpoints: vec![],
+            discovered_endpoints: vec![],
+            file_sd_jobs: vec![],
+            scrape_interval: Duration::from_secs(5),
+            enable_rules: false,
+            rule_files: vec![],
+            alertmanager_port: None,
+        };
+
+        // Nothing is listening on this port, so the reload will always fail.
+        let reload_url = Url::parse("http://127.0.0.1:1/prometheus/-/reload").unwrap();
+        let mut manager =
+            PrometheusConfigManager::new(settings, dir.path().to_path_buf(), reload_url);
+        manager.write().unwrap();
+
+        let original = std::fs::read_to_string(&config_file_path).unwrap();
+
+        manager
+            .update(|settings| settings.endpoints.clear())
+            .await
+            .expect_err("expected the reload to fail");
+
+        assert_eq!(manager.settings().endpoints.len(), 1);
+        assert_eq!(
+            original,
+            std::fs::read_to_string(&config_file_path).unwrap()
+        );
+    }
+
+    #[test]
+    fn configured_jobs_take_precedence_over_discovered_ones() {
+        let endpoint = |job_name: &str| {
+            Endpoint::new(
+                Url::parse("http://localhost:3000/metrics").unwrap(),
+                job_name.to_string(),
+                false,
+                None,
+            )
+        };
+
+        let mut settings = ScrapeSettings {
+            endpoints: vec![endpoint("api")],
+            discovered_endpoints: vec![endpoint("api"), endpoint("worker")],
+            ..Default::default()
+        };
+        assert!(settings.has_configured_job("api"));
+        assert!(!settings.has_configured_job("worker"));
+
+        settings.remove_conflicting_discovered_endpoints();
+        assert_eq!(settings.discovered_endpoints, vec![endpoint("worker")]);
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{bail, Result};
+
+/// The units Prometheus accepts in durations, from large to small. A duration
+/// can combine several of them, as long as they are in this order.
+const DURATION_UNITS: &[&str] = &["y", "w", "d", "h", "m", "s", "ms"];
+
+/// The units Prometheus accepts in sizes. These are powers of 1024.
+const SIZE_UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB", "PB", "EB"];
+
+/// How long Prometheus keeps its data and how much space it may use. Settings
+/// that are not set use the defaults of Prometheus, which keeps data for 15
+/// days regardless of its size.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Retention {
+    pub(crate) time: Option<String>,
+    pub(crate) size: Option<String>,
+}
+
+impl Retention {
+    /// The `--storage.tsdb.retention.*` arguments for Prometheus.
+    pub(crate) fn args(&self) -> Vec<String> {
+        let time = self
+            .time
+            .iter()
+            .map(|time| format!("--storage.tsdb.retention.time={time}"));
+        let size = self
+            .size
+            .iter()
+            .map(|size| format!("--storage.tsdb.retention.size={size}"));
+
+        time.chain(size).collect()
+    }
+}
+
+/// Parses a duration in the format used by Prometheus, such as `15d` or
+/// `1w2d`. Prometheus does not start if the retention is invalid, so it is
+/// better to catch this early.
+pub(crate) fn retention_time_parser(input: &str) -> Result<String> {
+    let mut rest = input;
+    let mut units = DURATION_UNITS;
+
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit_len = rest[digits..]
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len() - digits);
+        let unit = &rest[digits..digits + unit_len];
+
+        match units.iter().position(|u| *u == unit) {
+            Some(position) if digits > 0 => units = &units[position + 1..],
+            _ => bail!(
+                "invalid retention time {input:?}, expected a duration such as `15d` or `1w2d`"
+            ),
+        }
+
+        rest = &rest[digits + unit_len..];
+    }
+
+    if input.is_empty() {
+        bail!("retention time cannot be empty");
+    }
+
+    Ok(input.to_string())
+}
+
+/// Parses a size in the format used by Prometheus, such as `512MB` or
+/// `1.5GB`.
+pub(crate) fn retention_size_parser(input: &str) -> Result<String> {
+    let number_len = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(number_len);
+
+    if number.parse::<f64>().is_err() || !SIZE_UNITS.contains(&unit) {
+        bail!(
+            "invalid retention size {input:?}, expected a size such as `512MB`, using one of the units {}",
+            SIZE_UNITS.join(", ")
+        );
+    }
+
+    Ok(input.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retention_is_validated() {
+        assert_eq!(retention_time_parser("15d").unwrap(), "15d");
+        assert_eq!(retention_time_parser("1y2w30m").unwrap(), "1y2w30m");
+        assert!(retention_time_parser("").is_err());
+        assert!(retention_time_parser("15").is_err());
+        assert!(retention_time_parser("d").is_err());
+        assert!(retention_time_parser("2h1d").is_err());
+        assert!(retention_time_parser("15 days").is_err());
+
+        assert_eq!(retention_size_parser("512MB").unwrap(), "512MB");
+        assert_eq!(retention_size_parser("1.5GB").unwrap(), "1.5GB");
+        assert!(retention_size_parser("512").is_err());
+        assert!(retention_size_parser("512mb").is_err());
+        assert!(retention_size_parser("GB").is_err());
+
+        let retention = Retention {
+            time: Some("30d".to_string()),
+            size: Some("10GB".to_string()),
+        };
+        assert_eq!(
+            retention.args(),
+            vec![
+                "--storage.tsdb.retention.time=30d",
+                "--storage.tsdb.retention.size=10GB"
+            ]
+        );
+    }
+}
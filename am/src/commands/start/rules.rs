@@ -0,0 +1,163 @@
+use anyhow::{bail, Context, Result};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+use tokio::process;
+use tracing::{debug, warn};
+
+/// The autometrics rules that are included in am.
+const AUTOMETRICS_RULES: &[u8] =
+    include_bytes!("../../../../files/autometrics-shared/autometrics.rules.yml");
+
+/// The name of the autometrics rules file in the runtime directory.
+pub(crate) const AUTOMETRICS_RULES_FILE: &str = "autometrics.rules.yml";
+
+/// Write the autometrics rules into `runtime_dir`, for Prometheus to load.
+/// These are either the rules that are included in am, or the contents of
+/// `rules_file`, such as a file generated by `am rules generate`.
+pub(crate) fn write_autometrics_rules(runtime_dir: &Path, rules_file: Option<&Path>) -> Result<()> {
+    let path = runtime_dir.join(AUTOMETRICS_RULES_FILE);
+
+    match rules_file {
+        Some(rules_file) => fs::copy(rules_file, &path)
+            .map(|_| ())
+            .with_context(|| format!("Unable to copy rules file {}", rules_file.display())),
+        None => fs::write(&path, AUTOMETRICS_RULES)
+            .with_context(|| format!("Unable to write {}", path.display())),
+    }
+}
+
+/// Resolve the rule files against the directory containing the am.toml file
+/// at `config_file`, since Prometheus runs in its own working directory.
+pub(crate) fn resolve_rule_files(
+    files: Option<Vec<PathBuf>>,
+    config_file: &Path,
+) -> Result<Vec<PathBuf>> {
+    let base_dir = env::current_dir()
+        .context("Unable to determine the current directory")?
+        .join(config_file.parent().unwrap_or(Path::new("")));
+
+    let files: Vec<PathBuf> = files
+        .unwrap_or_default()
+        .into_iter()
+        .map(|file| base_dir.join(file))
+        .collect();
+
+    for file in &files {
+        if !file.is_file() {
+            bail!("Rule file {} does not exist", file.display());
+        }
+    }
+
+    Ok(files)
+}
+
+/// The path of promtool, which is shipped alongside the Prometheus binary in
+/// the release archive.
+pub(crate) fn promtool_path(prometheus_binary: &Path) -> PathBuf {
+    let promtool = if cfg!(target_os = "windows") {
+        "promtool.exe"
+    } else {
+        "promtool"
+    };
+
+    prometheus_binary.with_file_name(promtool)
+}
+
+/// Validate the rule files with `promtool check rules`, so that mistakes are
+/// reported before Prometheus is started. Every file is checked separately,
+/// which makes it clear which file the errors belong to.
+///
+/// The check is skipped if promtool is not available, which is the case for
+/// some custom Prometheus binaries.
+pub(crate) async fn check_rule_files(promtool: &Path, files: &[PathBuf]) -> Result<()> {
+    let mut errors = vec![];
+
+    for file in files {
+        let output = match process::Command::new(promtool)
+            .arg("check")
+            .arg("rules")
+            .arg(file)
+            .output()
+            .await
+        {
+            Ok(output) => output,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                warn!(
+                    "Unable to find promtool at {}, the rule files are not validated",
+                    promtool.display()
+                );
+                return Ok(());
+            }
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Unable to run promtool {}", promtool.display()))
+            }
+        };
+
+        if output.status.success() {
+            debug!("Rule file {} is valid", file.display());
+            continue;
+        }
+
+        let output = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        errors.push(format!(
+            "  {}:\n    {}",
+            file.display(),
+            output.trim().replace('\n', "\n    ")
+        ));
+    }
+
+    if !errors.is_empty() {
+        bail!("Invalid rule files:\n{}", errors.join("\n"));
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[tokio::test]
+    async fn errors_are_reported_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Stand-in for promtool, which rejects files containing "invalid".
+        let promtool = dir.path().join("promtool");
+        fs::write(
+            &promtool,
+            "#!/bin/sh\nif grep -q invalid \"$3\"; then echo \"$3: invalid rule\" >&2; exit 1; fi\n",
+        )
+        .unwrap();
+        fs::set_permissions(&promtool, fs::Permissions::from_mode(0o755)).unwrap();
+
+        fs::write(dir.path().join("team.rules.yml"), "groups: []\n").unwrap();
+        fs::write(dir.path().join("broken.rules.yml"), "invalid\n").unwrap();
+
+        let files = resolve_rule_files(
+            Some(vec!["team.rules.yml".into(), "broken.rules.yml".into()]),
+            &dir.path().join("am.toml"),
+        )
+        .unwrap();
+
+        let err = check_rule_files(&promtool_path(&dir.path().join("prometheus")), &files)
+            .await
+            .expect_err("expected the broken rule file to be rejected");
+        let message = err.to_string();
+        assert!(message.contains("broken.rules.yml: invalid rule"));
+        assert!(!message.contains("team.rules.yml"));
+
+        resolve_rule_files(
+            Some(vec!["missing.rules.yml".into()]),
+            &dir.path().join("am.toml"),
+        )
+        .expect_err("expected a missing rule file to be rejected");
+    }
+}
@@ -0,0 +1,329 @@
+use crate::commands::start::reload::SharedConfigManager;
+use crate::commands::start::CLIENT;
+use crate::downloader::unpack;
+use anyhow::{bail, Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use indicatif::MultiProgress;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{debug, warn};
+use url::Url;
+
+/// The directory that contains all files of a snapshot archive. The TSDB is
+/// stored in `prometheus/data` inside of it, which matches the layout of a
+/// workspace, and the Prometheus config and rules in `config`.
+const SNAPSHOT_PREFIX: &str = "am-snapshot";
+
+/// Creates snapshots of the data of the local Prometheus instance.
+#[derive(Clone)]
+pub(crate) struct SnapshotSource {
+    /// The URL of the local Prometheus instance, including the `/prometheus`
+    /// path it is served on.
+    pub(crate) prometheus_url: Url,
+
+    /// The TSDB directory of Prometheus, in which it creates the snapshots.
+    pub(crate) tsdb_dir: PathBuf,
+
+    /// The directory containing the generated Prometheus config and rules.
+    /// The archives are stored in here as well, so they are removed on exit.
+    pub(crate) runtime_dir: PathBuf,
+
+    /// The config manager, which holds the additional rule files from the
+    /// am.toml file. These change whenever the am.toml file is reloaded.
+    pub(crate) config_manager: SharedConfigManager,
+}
+
+pub(crate) type SharedSnapshotSource = Arc<SnapshotSource>;
+
+/// A snapshot that is packaged and ready to be downloaded.
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct SnapshotInfo {
+    pub(crate) name: String,
+    /// The size of the archive, in bytes.
+    pub(crate) size: u64,
+}
+
+/// The response of the snapshot API of Prometheus.
+#[derive(Deserialize)]
+struct PrometheusSnapshot {
+    data: PrometheusSnapshotData,
+}
+
+#[derive(Deserialize)]
+struct PrometheusSnapshotData {
+    name: String,
+}
+
+impl SnapshotSource {
+    /// Let Prometheus create a snapshot of its TSDB using the admin API, and
+    /// package it together with the config and rules into an archive.
+    pub(crate) async fn create(&self) -> Result<SnapshotInfo> {
+        let url = format!("{}/api/v1/admin/tsdb/snapshot", self.prometheus_url);
+        let response = CLIENT
+            .post(&url)
+            .send()
+            .await
+            .context("Unable to reach Prometheus")?;
+
+        let status = response.status();
+        if !status.is_success() {
+            bail!(
+                "Prometheus responded with {status}: {}",
+                response.text().await.unwrap_or_default()
+            );
+        }
+
+        let name = response.json::<PrometheusSnapshot>().await?.data.name;
+        let snapshot_dir = self.tsdb_dir.join("snapshots").join(&name);
+        let archive_path = self
+            .archive_path(&name)
+            .context("Prometheus returned an invalid snapshot name")?;
+
+        debug!(?snapshot_dir, ?archive_path, "Packaging snapshot");
+
+        let config_files = self.config_files().await?;
+        let archive = archive_path.clone();
+        let snapshot = snapshot_dir.clone();
+        let result =
+            tokio::task::spawn_blocking(move || write_archive(&archive, &snapshot, &config_files))
+                .await?;
+
+        // The archive contains a copy of the snapshot, so it is removed right
+        // away instead of piling up in the TSDB directory.
+        if let Err(err) = fs::remove_dir_all(&snapshot_dir) {
+            warn!(?err, "Unable to remove snapshot {}", snapshot_dir.display());
+        }
+
+        result?;
+
+        Ok(SnapshotInfo {
+            size: fs::metadata(&archive_path)?.len(),
+            name,
+        })
+    }
+
+    /// The location of the archive of snapshot `name`. Returns `None` if the
+    /// name is not one that Prometheus could have generated, which prevents
+    /// escaping the snapshot directory.
+    pub(crate) fn archive_path(&self, name: &str) -> Option<PathBuf> {
+        let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+
+        valid.then(|| {
+            self.runtime_dir
+                .join("snapshots")
+                .join(format!("{name}.tar.gz"))
+        })
+    }
+
+    /// The generated config and rules in the runtime directory, followed by
+    /// the additional rule files.
+    async fn config_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = vec![];
+        for entry in fs::read_dir(&self.runtime_dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "yml") {
+                files.push(path);
+            }
+        }
+
+        files.sort();
+        files.extend(
+            self.config_manager
+                .lock()
+                .await
+                .settings()
+                .rule_files
+                .iter()
+                .cloned(),
+        );
+        Ok(files)
+    }
+}
+
+/// Write the TSDB snapshot in `snapshot_dir` and the `config_files` into a
+/// `.tar.gz` archive at `output`.
+fn write_archive(output: &Path, snapshot_dir: &Path, config_files: &[PathBuf]) -> Result<()> {
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let file =
+        File::create(output).with_context(|| format!("Unable to create {}", output.display()))?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+    // The directories are added explicitly, since unpacking the archive
+    // does not create the parent directories of the entries.
+    archive.append_dir(SNAPSHOT_PREFIX, snapshot_dir)?;
+    archive.append_dir(format!("{SNAPSHOT_PREFIX}/prometheus"), snapshot_dir)?;
+    archive
+        .append_dir_all(format!("{SNAPSHOT_PREFIX}/prometheus/data"), snapshot_dir)
+        .with_context(|| format!("Unable to archive {}", snapshot_dir.display()))?;
+
+    archive.append_dir(format!("{SNAPSHOT_PREFIX}/config"), snapshot_dir)?;
+    for path in config_files {
+        let Some(file_name) = path.file_name() else {
+            continue;
+        };
+
+        archive
+            .append_path_with_name(
+                path,
+                Path::new(SNAPSHOT_PREFIX).join("config").join(file_name),
+            )
+            .with_context(|| format!("Unable to archive {}", path.display()))?;
+    }
+
+    archive.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Restore the snapshot `archive` into `workspace_dir`, so Prometheus starts
+/// with the data of the snapshot. The workspace must not contain any data yet.
+///
+/// The archive is unpacked into a temporary directory first and moved into
+/// place afterwards, so a failed restore does not leave partial data behind
+/// that would prevent restoring the snapshot again.
+pub(crate) async fn restore_snapshot(
+    archive: &Path,
+    workspace_dir: &Path,
+    multi_progress: &MultiProgress,
+) -> Result<()> {
+    if workspace_dir.join("prometheus").join("data").exists() {
+        bail!(
+            "{} already contains data, use a new workspace to restore the snapshot into",
+            workspace_dir.display()
+        );
+    }
+
+    let file =
+        File::open(archive).with_context(|| format!("Unable to open {}", archive.display()))?;
+    let name = archive
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    fs::create_dir_all(workspace_dir)?;
+    let temp_dir = tempfile::Builder::new()
+        .prefix(".snapshot-")
+        .tempdir_in(workspace_dir)
+        .with_context(|| {
+            format!(
+                "Unable to create a temporary directory in {}",
+                workspace_dir.display()
+            )
+        })?;
+
+    unpack(
+        &file,
+        &name,
+        temp_dir.path(),
+        SNAPSHOT_PREFIX,
+        multi_progress,
+    )
+    .await
+    .with_context(|| format!("Unable to restore snapshot {}", archive.display()))?;
+
+    let unpacked_config_dir = temp_dir.path().join("config");
+    if unpacked_config_dir.is_dir() {
+        let config_dir = workspace_dir.join("config");
+        fs::create_dir_all(&config_dir)?;
+
+        for entry in fs::read_dir(&unpacked_config_dir)? {
+            let entry = entry?;
+            fs::rename(entry.path(), config_dir.join(entry.file_name()))
+                .with_context(|| format!("Unable to move {} into place", entry.path().display()))?;
+        }
+    }
+
+    // The data is moved last, since the restore is complete once it exists.
+    // Dropping the temporary directory removes whatever is left in it.
+    let data_dir = workspace_dir.join("prometheus").join("data");
+    fs::create_dir_all(workspace_dir.join("prometheus"))?;
+    fs::rename(temp_dir.path().join("prometheus").join("data"), &data_dir)
+        .with_context(|| format!("Unable to move the snapshot into {}", data_dir.display()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::commands::start::reload::{PrometheusConfigManager, ScrapeSettings};
+    use tokio::sync::Mutex;
+
+    #[tokio::test]
+    async fn snapshot_is_restored_into_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let snapshot_dir = dir.path().join("20231115T101010Z-2be650b6d019eb54");
+        let block_dir = snapshot_dir.join("01HF8RGVG3V2E5M4M1BZ5ZJ8R3");
+        fs::create_dir_all(block_dir.join("chunks")).unwrap();
+        fs::write(block_dir.join("meta.json"), "{}").unwrap();
+        fs::write(block_dir.join("chunks").join("000001"), "chunks").unwrap();
+
+        let config_file = dir.path().join("prometheus.yml");
+        fs::write(&config_file, "global: {}").unwrap();
+
+        let archive = dir.path().join("snapshot.tar.gz");
+        write_archive(&archive, &snapshot_dir, &[config_file]).unwrap();
+
+        let workspace_dir = dir.path().join("workspaces").join("load-test");
+        let mp = MultiProgress::new();
+
+        // A failed restore leaves nothing behind that prevents a retry.
+        let truncated = dir.path().join("truncated.tar.gz");
+        let bytes = fs::read(&archive).unwrap();
+        fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+        restore_snapshot(&truncated, &workspace_dir, &mp)
+            .await
+            .expect_err("archive is truncated");
+        assert!(!workspace_dir.join("prometheus").join("data").exists());
+
+        restore_snapshot(&archive, &workspace_dir, &mp)
+            .await
+            .unwrap();
+
+        let data_dir = workspace_dir.join("prometheus").join("data");
+        assert_eq!(
+            fs::read_to_string(data_dir.join("01HF8RGVG3V2E5M4M1BZ5ZJ8R3/chunks/000001")).unwrap(),
+            "chunks"
+        );
+        assert!(workspace_dir.join("config").join("prometheus.yml").exists());
+
+        // Restoring into a workspace with data would mix up the data.
+        restore_snapshot(&archive, &workspace_dir, &mp)
+            .await
+            .expect_err("workspace already contains data");
+    }
+
+    /// A config manager without any rule files, which is never reloaded.
+    pub(crate) fn config_manager() -> SharedConfigManager {
+        let reload_url = Url::parse("http://127.0.0.1:1/prometheus/-/reload").unwrap();
+
+        Arc::new(Mutex::new(PrometheusConfigManager::new(
+            ScrapeSettings::default(),
+            PathBuf::from("/tmp/am-prometheus-abc123"),
+            reload_url,
+        )))
+    }
+
+    #[test]
+    fn archive_names_cannot_escape_the_runtime_dir() {
+        let source = SnapshotSource {
+            prometheus_url: Url::parse("http://localhost:9090/prometheus").unwrap(),
+            tsdb_dir: PathBuf::from("/data"),
+            runtime_dir: PathBuf::from("/tmp/am-prometheus-abc123"),
+            config_manager: config_manager(),
+        };
+
+        assert_eq!(
+            source.archive_path("20231115T101010Z-2be650b6d019eb54"),
+            Some(PathBuf::from(
+                "/tmp/am-prometheus-abc123/snapshots/20231115T101010Z-2be650b6d019eb54.tar.gz"
+            ))
+        );
+        assert_eq!(source.archive_path("../../etc/passwd"), None);
+        assert_eq!(source.archive_path(""), None);
+    }
+}
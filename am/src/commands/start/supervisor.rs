@@ -0,0 +1,370 @@
+use crate::commands::start::logs::{
+    capture_output, LogBuffer, LogBuffers, LogStream, LOG_BUFFER_CAPACITY,
+};
+use crate::components::{Component, ALERTMANAGER, PROMETHEUS, PUSHGATEWAY};
+use anyhow::{anyhow, Context, Result};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::process;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+/// The amount of times each supervised process has been restarted, keyed by
+/// the name of the process.
+pub(crate) type RestartCounts = HashMap<&'static str, u32>;
+
+/// The state of each supervised process, keyed by the name of the process.
+/// Processes that have not been started yet, for example because they are
+/// still being downloaded, are missing.
+pub(crate) type ProcessStates = HashMap<&'static str, ProcessState>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProcessState {
+    Running,
+    /// The process crashed and is waiting to be restarted.
+    Restarting,
+    /// The process exited successfully.
+    Exited,
+    /// The process crashed and will not be restarted anymore.
+    Failed,
+}
+
+/// The processes that am starts and supervises.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ChildProcess {
+    Prometheus,
+    Pushgateway,
+    Alertmanager,
+}
+
+impl ChildProcess {
+    /// The component that is run by this process.
+    pub(crate) fn component(&self) -> &'static Component {
+        match self {
+            ChildProcess::Prometheus => &PROMETHEUS,
+            ChildProcess::Pushgateway => &PUSHGATEWAY,
+            ChildProcess::Alertmanager => &ALERTMANAGER,
+        }
+    }
+
+    /// The name of the process as it is shown to the user.
+    pub(crate) fn name(&self) -> &'static str {
+        self.component().name
+    }
+
+    /// The identifier of the process as it is used in the am API.
+    pub(crate) fn id(&self) -> &'static str {
+        self.component().id
+    }
+}
+
+/// Determines how often and how fast a crashed process will be restarted.
+#[derive(Debug, Clone)]
+pub(crate) struct RestartPolicy {
+    /// The maximum amount of restarts, after which the process is considered
+    /// to be failed.
+    pub(crate) max_restarts: u32,
+
+    /// The delay before the first restart. This delay is doubled for every
+    /// consecutive crash, up until `max_backoff`.
+    pub(crate) initial_backoff: Duration,
+    pub(crate) max_backoff: Duration,
+
+    /// A process that exits before it has been running for this long is
+    /// considered to have crashed on startup.
+    pub(crate) min_uptime: Duration,
+
+    /// The amount of consecutive crashes on startup, after which the process
+    /// is considered to be crash looping and will not be restarted anymore.
+    pub(crate) crash_loop_threshold: u32,
+}
+
+impl RestartPolicy {
+    pub(crate) fn new(max_restarts: u32) -> Self {
+        Self {
+            max_restarts,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            min_uptime: Duration::from_secs(10),
+            crash_loop_threshold: 3,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Decision {
+    Restart(Duration),
+    GiveUp(String),
+}
+
+/// Keeps track of the crashes of a single process and decides whether it
+/// should be restarted according to its [`RestartPolicy`].
+#[derive(Debug)]
+struct RestartTracker {
+    policy: RestartPolicy,
+    restarts: u32,
+    consecutive_crashes: u32,
+}
+
+impl RestartTracker {
+    fn new(policy: RestartPolicy) -> Self {
+        Self {
+            policy,
+            restarts: 0,
+            consecutive_crashes: 0,
+        }
+    }
+
+    fn record_crash(&mut self, uptime: Duration) -> Decision {
+        if uptime < self.policy.min_uptime {
+            self.consecutive_crashes += 1;
+        } else {
+            // The process was running fine for a while, so we start counting
+            // (and backing off) from scratch.
+            self.consecutive_crashes = 1;
+        }
+
+        if self.restarts >= self.policy.max_restarts {
+            return Decision::GiveUp(format!(
+                "process has been restarted {} times already",
+                self.restarts
+            ));
+        }
+
+        if self.consecutive_crashes >= self.policy.crash_loop_threshold {
+            return Decision::GiveUp(format!(
+                "process crashed {} times in a row within {:?} after starting",
+                self.consecutive_crashes, self.policy.min_uptime
+            ));
+        }
+
+        self.restarts += 1;
+
+        let exponent = self.consecutive_crashes.saturating_sub(1).min(16);
+        let backoff = self
+            .policy
+            .initial_backoff
+            .saturating_mul(2u32.pow(exponent))
+            .min(self.policy.max_backoff);
+
+        Decision::Restart(backoff)
+    }
+}
+
+/// Supervises the child processes of am. All processes share the same restart
+/// policy, and their restart counts and output are made available to the rest
+/// of am.
+pub(crate) struct Supervisor {
+    policy: RestartPolicy,
+    restart_counts: watch::Sender<RestartCounts>,
+    states: watch::Sender<ProcessStates>,
+    log_buffers: LogBuffers,
+}
+
+impl Supervisor {
+    /// Create a new supervisor for `processes`. The returned receiver is
+    /// updated whenever one of the processes is restarted.
+    pub(crate) fn new(
+        policy: RestartPolicy,
+        processes: &[ChildProcess],
+    ) -> (Self, watch::Receiver<RestartCounts>) {
+        let (restart_counts, rx) = watch::channel(RestartCounts::new());
+
+        let log_buffers = processes
+            .iter()
+            .map(|process| (process.id(), Arc::new(LogBuffer::new(LOG_BUFFER_CAPACITY))))
+            .collect();
+
+        let (states, _) = watch::channel(ProcessStates::new());
+
+        let supervisor = Self {
+            policy,
+            restart_counts,
+            states,
+            log_buffers,
+        };
+
+        (supervisor, rx)
+    }
+
+    /// The buffers containing the most recent output of every process.
+    pub(crate) fn log_buffers(&self) -> LogBuffers {
+        self.log_buffers.clone()
+    }
+
+    /// A receiver that is updated whenever the state of a process changes.
+    pub(crate) fn process_states(&self) -> watch::Receiver<ProcessStates> {
+        self.states.subscribe()
+    }
+
+    fn set_state(&self, name: &'static str, state: ProcessState) {
+        self.states.send_modify(|states| {
+            states.insert(name, state);
+        });
+    }
+
+    /// Run `command` and restart it according to the restart policy whenever
+    /// it exits with a non-zero exit code.
+    ///
+    /// The output of the process is forwarded to tracing and stored in its log
+    /// buffer while it is running. If the process crashes, its output since it
+    /// was last started will be logged as an error.
+    ///
+    /// This will block until the process exits successfully, or until the
+    /// process is considered to be failed. A failed process is logged and
+    /// marked as [`ProcessState::Failed`], but does not return an error, so
+    /// the web server and the other processes keep running.
+    pub(crate) async fn supervise(
+        &self,
+        process: ChildProcess,
+        mut command: process::Command,
+    ) -> Result<()> {
+        let name = process.name();
+        let logs = self
+            .log_buffers
+            .get(process.id())
+            .ok_or_else(|| anyhow!("{name} is not supervised"))?;
+
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut tracker = RestartTracker::new(self.policy.clone());
+
+        loop {
+            let started = Instant::now();
+            let first_seq = logs.next_seq();
+
+            let mut child = command
+                .spawn()
+                .with_context(|| format!("Unable to start {name}"))?;
+            self.set_state(name, ProcessState::Running);
+
+            // Both are piped, so they are always available right after spawning.
+            let stdout = child.stdout.take().expect("stdout should be piped");
+            let stderr = child.stderr.take().expect("stderr should be piped");
+
+            let (status, _, _) = tokio::join!(
+                child.wait(),
+                capture_output(process, LogStream::Stdout, stdout, logs),
+                capture_output(process, LogStream::Stderr, stderr, logs),
+            );
+            let status = status?;
+
+            if status.success() {
+                self.set_state(name, ProcessState::Exited);
+                info!("{name} exited");
+                return Ok(());
+            }
+
+            let output = logs
+                .lines_since(first_seq)
+                .into_iter()
+                .map(|line| line.line)
+                .join("\n");
+
+            if !output.is_empty() {
+                error!("{name} output:\n{output}");
+            }
+
+            match tracker.record_crash(started.elapsed()) {
+                Decision::Restart(backoff) => {
+                    self.set_state(name, ProcessState::Restarting);
+                    warn!(
+                        "{name} exited with status {status}, restarting in {}",
+                        humantime::format_duration(backoff)
+                    );
+
+                    tokio::time::sleep(backoff).await;
+
+                    self.restart_counts.send_modify(|counts| {
+                        counts.insert(name, tracker.restarts);
+                    });
+                }
+                Decision::GiveUp(reason) => {
+                    self.set_state(name, ProcessState::Failed);
+                    error!("{name} exited with status {status}, giving up: {reason}");
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RestartPolicy {
+        RestartPolicy {
+            max_restarts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(3),
+            min_uptime: Duration::from_secs(10),
+            crash_loop_threshold: 3,
+        }
+    }
+
+    #[test]
+    fn backoff_increases_exponentially() {
+        let mut tracker = RestartTracker::new(RestartPolicy {
+            crash_loop_threshold: 10,
+            ..policy()
+        });
+
+        let short = Duration::from_secs(1);
+        assert_eq!(
+            tracker.record_crash(short),
+            Decision::Restart(Duration::from_secs(1))
+        );
+        assert_eq!(
+            tracker.record_crash(short),
+            Decision::Restart(Duration::from_secs(2))
+        );
+        assert_eq!(
+            tracker.record_crash(short),
+            Decision::Restart(Duration::from_secs(3))
+        );
+    }
+
+    #[test]
+    fn detects_crash_loop() {
+        let mut tracker = RestartTracker::new(policy());
+
+        let short = Duration::from_secs(1);
+        assert!(matches!(tracker.record_crash(short), Decision::Restart(_)));
+        assert!(matches!(tracker.record_crash(short), Decision::Restart(_)));
+        assert!(matches!(tracker.record_crash(short), Decision::GiveUp(_)));
+    }
+
+    #[test]
+    fn long_running_process_resets_backoff() {
+        let mut tracker = RestartTracker::new(policy());
+
+        let short = Duration::from_secs(1);
+        let long = Duration::from_secs(60);
+        assert!(matches!(tracker.record_crash(short), Decision::Restart(_)));
+        assert!(matches!(tracker.record_crash(short), Decision::Restart(_)));
+        assert_eq!(
+            tracker.record_crash(long),
+            Decision::Restart(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn gives_up_after_max_restarts() {
+        let mut tracker = RestartTracker::new(RestartPolicy {
+            max_restarts: 2,
+            ..policy()
+        });
+
+        let long = Duration::from_secs(60);
+        assert!(matches!(tracker.record_crash(long), Decision::Restart(_)));
+        assert!(matches!(tracker.record_crash(long), Decision::Restart(_)));
+        assert!(matches!(tracker.record_crash(long), Decision::GiveUp(_)));
+    }
+}
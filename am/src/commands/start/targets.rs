@@ -0,0 +1,244 @@
+use crate::commands::start::CLIENT;
+use crate::terminal;
+use anyhow::{bail, Context, Result};
+use autometrics_am::prometheus::{ActiveTarget, TargetHealth, TargetsResponse};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
+use url::Url;
+
+/// How often the targets are requested from Prometheus.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the targets are requested while Prometheus is starting or has
+/// not scraped every target yet.
+const STARTUP_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub(crate) type SharedTargetMonitor = Arc<TargetMonitor>;
+
+/// The health of a scrape target, as it was last seen by am.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub(crate) struct TargetStatus {
+    pub(crate) job: String,
+    pub(crate) scrape_url: String,
+    pub(crate) health: TargetHealth,
+    /// The error of the last scrape, if it failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) last_error: Option<String>,
+    pub(crate) last_scrape: String,
+    /// When am first saw the target with its current health.
+    pub(crate) since: String,
+}
+
+/// The health of all targets, as it is returned by the `/api/targets` API.
+#[derive(Debug, Serialize)]
+pub(crate) struct TargetsSummary {
+    pub(crate) up: usize,
+    pub(crate) down: usize,
+    pub(crate) unknown: usize,
+    pub(crate) targets: Vec<TargetStatus>,
+}
+
+/// A change in the health of a target. `previous` is `None` if the target
+/// was not seen before.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Transition {
+    pub(crate) previous: Option<TargetHealth>,
+    pub(crate) target: TargetStatus,
+}
+
+/// Keeps track of the health of the targets of the local Prometheus instance,
+/// by polling its targets API.
+pub(crate) struct TargetMonitor {
+    /// The URL of the local Prometheus instance, including the `/prometheus`
+    /// path it is served on.
+    prometheus_url: Url,
+    targets: Mutex<Vec<TargetStatus>>,
+}
+
+impl TargetMonitor {
+    pub(crate) fn new(prometheus_url: Url) -> Self {
+        Self {
+            prometheus_url,
+            targets: Mutex::new(vec![]),
+        }
+    }
+
+    /// All targets, sorted by job and URL.
+    pub(crate) fn targets(&self) -> Vec<TargetStatus> {
+        self.targets.lock().unwrap().clone()
+    }
+
+    pub(crate) fn summary(&self) -> TargetsSummary {
+        let targets = self.targets();
+        let count = |health| {
+            targets
+                .iter()
+                .filter(|target| target.health == health)
+                .count()
+        };
+
+        TargetsSummary {
+            up: count(TargetHealth::Up),
+            down: count(TargetHealth::Down),
+            unknown: count(TargetHealth::Unknown),
+            targets,
+        }
+    }
+
+    /// Poll the targets of Prometheus and print every target that goes down
+    /// or comes back up. This replaces the one-off check of the endpoints
+    /// when am starts, since it also catches endpoints that go down later.
+    ///
+    /// Until every target has been scraped once, Prometheus is polled more
+    /// often, so endpoints that are down when am starts are reported right
+    /// after their first scrape.
+    pub(crate) async fn watch(&self) -> Result<()> {
+        let mut interval = STARTUP_POLL_INTERVAL;
+
+        loop {
+            tokio::time::sleep(interval).await;
+
+            let targets = match self.fetch().await {
+                Ok(targets) => targets,
+                Err(err) => {
+                    // Prometheus is not available while it is starting or
+                    // restarting, which will show up in its own logs.
+                    debug!(?err, "Unable to retrieve the targets from Prometheus");
+                    continue;
+                }
+            };
+
+            interval = if targets
+                .iter()
+                .any(|target| target.health == TargetHealth::Unknown)
+            {
+                STARTUP_POLL_INTERVAL
+            } else {
+                POLL_INTERVAL
+            };
+
+            for transition in self.update(targets, SystemTime::now()) {
+                if let Err(err) = terminal::print_target_transition(&transition) {
+                    warn!(?err, "Unable to print target health");
+                }
+            }
+        }
+    }
+
+    async fn fetch(&self) -> Result<Vec<ActiveTarget>> {
+        let url = format!("{}/api/v1/targets?state=active", self.prometheus_url);
+        let response = CLIENT
+            .get(&url)
+            .timeout(POLL_INTERVAL)
+            .send()
+            .await
+            .context("Unable to reach Prometheus")?;
+
+        let status = response.status();
+        if !status.is_success() {
+            bail!("Prometheus responded with {status}");
+        }
+
+        let response: TargetsResponse = response.json().await?;
+        Ok(response.data.active_targets)
+    }
+
+    /// Replace the targets with `active_targets` and return the targets that
+    /// went down or came back up. Targets that are up once they have been
+    /// scraped for the first time are not reported.
+    fn update(&self, active_targets: Vec<ActiveTarget>, now: SystemTime) -> Vec<Transition> {
+        let mut targets = self.targets.lock().unwrap();
+        let previous: HashMap<(String, String), TargetStatus> = targets
+            .drain(..)
+            .map(|target| ((target.job.clone(), target.scrape_url.clone()), target))
+            .collect();
+
+        let now = humantime::format_rfc3339_seconds(now).to_string();
+        let mut transitions = vec![];
+
+        for target in active_targets {
+            let key = (target.scrape_pool, target.scrape_url);
+            let previous = previous.get(&key);
+            let previous_health = previous.map(|previous| previous.health);
+
+            let status = TargetStatus {
+                since: match previous {
+                    Some(previous) if previous.health == target.health => previous.since.clone(),
+                    _ => now.clone(),
+                },
+                job: key.0,
+                scrape_url: key.1,
+                health: target.health,
+                last_error: (!target.last_error.is_empty()).then_some(target.last_error),
+                last_scrape: target.last_scrape,
+            };
+
+            let reported = match status.health {
+                TargetHealth::Down => previous_health != Some(TargetHealth::Down),
+                TargetHealth::Up => previous_health == Some(TargetHealth::Down),
+                TargetHealth::Unknown => false,
+            };
+            if reported {
+                transitions.push(Transition {
+                    previous: previous_health,
+                    target: status.clone(),
+                });
+            }
+
+            targets.push(status);
+        }
+
+        targets.sort_by(|a, b| (&a.job, &a.scrape_url).cmp(&(&b.job, &b.scrape_url)));
+        transitions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(health: TargetHealth, last_error: &str) -> ActiveTarget {
+        ActiveTarget {
+            scrape_pool: "api".to_string(),
+            scrape_url: "http://localhost:3000/metrics".to_string(),
+            labels: Default::default(),
+            health,
+            last_error: last_error.to_string(),
+            last_scrape: "2023-11-20T10:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn health_transitions_are_reported() {
+        let monitor = TargetMonitor::new(Url::parse("http://localhost:9090/prometheus").unwrap());
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+
+        // Targets that are up right away are not worth mentioning.
+        let transitions = monitor.update(vec![target(TargetHealth::Unknown, "")], at(0));
+        assert!(transitions.is_empty());
+        let transitions = monitor.update(vec![target(TargetHealth::Up, "")], at(5));
+        assert!(transitions.is_empty());
+
+        let refused = "Get \"http://localhost:3000/metrics\": dial tcp [::1]:3000: connect: connection refused";
+        let transitions = monitor.update(vec![target(TargetHealth::Down, refused)], at(10));
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].previous, Some(TargetHealth::Up));
+        assert_eq!(transitions[0].target.last_error.as_deref(), Some(refused));
+
+        // Staying down is not a transition, and keeps the original time.
+        let transitions = monitor.update(vec![target(TargetHealth::Down, refused)], at(15));
+        assert!(transitions.is_empty());
+
+        let summary = monitor.summary();
+        assert_eq!((summary.up, summary.down, summary.unknown), (0, 1, 0));
+        assert_eq!(summary.targets[0].since, "1970-01-01T00:00:10Z");
+
+        let transitions = monitor.update(vec![target(TargetHealth::Up, "")], at(20));
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].previous, Some(TargetHealth::Down));
+        assert_eq!(transitions[0].target.last_error, None);
+    }
+}
@@ -0,0 +1,675 @@
+use crate::commands::functions::{
+    format_latency, format_ratio, sort_reports, FunctionMetrics, FunctionReport, SortBy,
+};
+use crate::commands::query::{format_value, instant_query};
+use crate::commands::start::logs::{LogBuffer, LogBuffers, LogStream, LOG_BUFFER_CAPACITY};
+use crate::commands::start::supervisor::{
+    ChildProcess, ProcessState, ProcessStates, RestartCounts,
+};
+use crate::commands::start::targets::{SharedTargetMonitor, TargetStatus};
+use crate::interactive;
+use anyhow::{bail, Context, Result};
+use autometrics_am::prometheus::{QueryData, TargetHealth};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use futures_util::StreamExt;
+use indicatif::{MultiProgress, ProgressDrawTarget};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table};
+use ratatui::{Frame, Terminal};
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Stdout};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::select;
+use tokio::sync::watch;
+use tracing::debug;
+use url::Url;
+
+/// How often the functions and alerts are queried from Prometheus.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often the dashboard is redrawn, so new log lines show up.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The window over which the rates and latencies of the functions are
+/// calculated, which matches the default of `am functions`.
+const FUNCTIONS_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// The amount of functions that are shown.
+const TOP_FUNCTIONS: usize = 10;
+
+/// The sort orders that can be cycled through using `s`.
+const SORT_ORDERS: &[SortBy] = &[SortBy::Rate, SortBy::Errors, SortBy::P95, SortBy::P99];
+
+/// A full-screen dashboard, which replaces the URLs and log messages that are
+/// normally printed by `am start`.
+pub(crate) struct Dashboard {
+    /// The URL of the local Prometheus instance, including the `/prometheus`
+    /// path it is served on.
+    pub(crate) prometheus_url: Url,
+    /// Keeps track of the health of the scrape targets.
+    pub(crate) target_monitor: SharedTargetMonitor,
+    pub(crate) processes: Vec<ChildProcess>,
+    pub(crate) urls: watch::Receiver<HashMap<&'static str, String>>,
+    pub(crate) restarts: watch::Receiver<RestartCounts>,
+    pub(crate) states: watch::Receiver<ProcessStates>,
+    pub(crate) log_buffers: LogBuffers,
+}
+
+impl Dashboard {
+    /// Show the dashboard until the user quits it.
+    pub(crate) async fn run(self, multi_progress: MultiProgress) -> Result<()> {
+        if !io::stdout().is_terminal() {
+            bail!("The dashboard can only be shown in a terminal");
+        }
+
+        // The log messages of am itself are shown in the dashboard as well,
+        // next to the output of the child processes.
+        let am_logs = Arc::new(LogBuffer::new(LOG_BUFFER_CAPACITY));
+        let _capture = interactive::capture_output(am_logs.clone());
+        multi_progress.set_draw_target(ProgressDrawTarget::hidden());
+
+        let mut log_sources = vec![("am", am_logs)];
+        for process in &self.processes {
+            if let Some(buffer) = self.log_buffers.get(process.id()) {
+                log_sources.push((process.name(), buffer.clone()));
+            }
+        }
+
+        let mut state = DashboardState {
+            sort: SORT_ORDERS[0],
+            log_sources,
+            ..Default::default()
+        };
+
+        let mut terminal = DashboardTerminal::enter()?;
+        let mut events = EventStream::new();
+        let mut redraw = tokio::time::interval(REDRAW_INTERVAL);
+        let mut refresh = tokio::time::interval(REFRESH_INTERVAL);
+
+        loop {
+            state.processes = self.process_statuses();
+            state.targets = self.target_monitor.targets();
+            state.explorer_url = self.urls.borrow().get("Explorer").cloned();
+            terminal.draw(|frame| render(frame, &state))?;
+
+            select! {
+                _ = redraw.tick() => {}
+                _ = refresh.tick() => {
+                    let result =
+                        tokio::time::timeout(REFRESH_INTERVAL, state.refresh(&self.prometheus_url))
+                            .await
+                            .context("Prometheus did not respond in time");
+                    state.error = match result.and_then(|result| result) {
+                        Ok(()) => None,
+                        Err(err) => {
+                            debug!(?err, "Unable to refresh the dashboard");
+                            Some(format!("{err:#}"))
+                        }
+                    };
+                }
+                event = events.next() => match event {
+                    Some(Ok(Event::Key(key))) => {
+                        if state.handle_key(key) == KeyResult::Quit {
+                            return Ok(());
+                        }
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => return Err(err.into()),
+                    None => return Ok(()),
+                },
+            }
+        }
+    }
+
+    fn process_statuses(&self) -> Vec<ProcessStatus> {
+        let urls = self.urls.borrow();
+        let restarts = self.restarts.borrow();
+        let states = self.states.borrow();
+
+        self.processes
+            .iter()
+            .map(|process| {
+                let name = process.name();
+                ProcessStatus {
+                    name,
+                    state: states.get(name).copied(),
+                    restarts: restarts.get(name).copied().unwrap_or_default(),
+                    url: urls.get(name).cloned(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Switches the terminal to the alternate screen while the dashboard is shown,
+/// and restores it when dropped, even if am exits with an error.
+struct DashboardTerminal {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+}
+
+impl DashboardTerminal {
+    fn enter() -> Result<Self> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+
+        let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+        terminal.hide_cursor()?;
+        terminal.clear()?;
+
+        Ok(Self { terminal })
+    }
+
+    fn draw(&mut self, f: impl FnOnce(&mut Frame)) -> Result<()> {
+        self.terminal.draw(f)?;
+        Ok(())
+    }
+}
+
+impl Drop for DashboardTerminal {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+        let _ = self.terminal.show_cursor();
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum KeyResult {
+    Continue,
+    Quit,
+}
+
+#[derive(Debug, Clone)]
+struct ProcessStatus {
+    name: &'static str,
+    /// `None` if the process has not been started yet.
+    state: Option<ProcessState>,
+    restarts: u32,
+    url: Option<String>,
+}
+
+/// An alert that is currently firing, according to the `ALERTS` metric.
+#[derive(Debug, Clone, PartialEq)]
+struct FiringAlert {
+    name: String,
+    labels: String,
+}
+
+#[derive(Default)]
+struct DashboardState {
+    explorer_url: Option<String>,
+    processes: Vec<ProcessStatus>,
+    targets: Vec<TargetStatus>,
+    functions: Vec<FunctionReport>,
+    alerts: Vec<FiringAlert>,
+    /// The error of the last refresh, if it failed.
+    error: Option<String>,
+    sort: SortBy,
+    /// The log buffers that can be shown, by name. Only one of them is shown
+    /// at a time.
+    log_sources: Vec<(&'static str, Arc<LogBuffer>)>,
+    selected_log: usize,
+}
+
+impl DashboardState {
+    /// Query the functions and alerts from Prometheus.
+    async fn refresh(&mut self, prometheus_url: &Url) -> Result<()> {
+        let (functions, alerts) = tokio::try_join!(
+            FunctionMetrics::fetch(prometheus_url, FUNCTIONS_WINDOW),
+            instant_query(prometheus_url, r#"ALERTS{alertstate="firing"}"#),
+        )?;
+
+        self.functions = functions.reports();
+        sort_reports(&mut self.functions, self.sort);
+        self.alerts = firing_alerts(alerts);
+
+        Ok(())
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> KeyResult {
+        if key.kind != KeyEventKind::Press {
+            return KeyResult::Continue;
+        }
+
+        // The terminal is in raw mode, so ctrl + c does not send SIGINT.
+        let ctrl_c =
+            key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c');
+        if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+            return KeyResult::Quit;
+        }
+
+        let sources = self.log_sources.len().max(1);
+        match key.code {
+            KeyCode::Tab => self.selected_log = (self.selected_log + 1) % sources,
+            KeyCode::BackTab => self.selected_log = (self.selected_log + sources - 1) % sources,
+            KeyCode::Char('s') => {
+                let current = SORT_ORDERS.iter().position(|sort| *sort == self.sort);
+                self.sort = SORT_ORDERS[current.map_or(0, |i| (i + 1) % SORT_ORDERS.len())];
+                sort_reports(&mut self.functions, self.sort);
+            }
+            _ => {}
+        }
+
+        KeyResult::Continue
+    }
+}
+
+fn firing_alerts(data: QueryData) -> Vec<FiringAlert> {
+    let QueryData::Vector(series) = data else {
+        return vec![];
+    };
+
+    let mut alerts: Vec<FiringAlert> = series
+        .into_iter()
+        .map(|series| {
+            let labels = series
+                .metric
+                .iter()
+                .filter(|(name, _)| {
+                    !matches!(name.as_str(), "__name__" | "alertname" | "alertstate")
+                })
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            FiringAlert {
+                name: series.metric.get("alertname").cloned().unwrap_or_default(),
+                labels,
+            }
+        })
+        .collect();
+
+    alerts.sort_by(|a, b| (&a.name, &a.labels).cmp(&(&b.name, &b.labels)));
+    alerts
+}
+
+fn render(frame: &mut Frame, state: &DashboardState) {
+    let top_height = state.processes.len().max(state.targets.len()).max(1) as u16 + 3;
+    let [header, top, middle, logs] = split(
+        frame.size(),
+        Direction::Vertical,
+        [
+            Constraint::Length(2),
+            Constraint::Length(top_height.min(10)),
+            Constraint::Length(TOP_FUNCTIONS as u16 + 3),
+            Constraint::Min(5),
+        ],
+    );
+
+    render_header(frame, header, state);
+
+    let [processes, targets] = split(
+        top,
+        Direction::Horizontal,
+        [Constraint::Percentage(45), Constraint::Percentage(55)],
+    );
+    render_processes(frame, processes, state);
+    render_targets(frame, targets, state);
+
+    let [functions, alerts] = split(
+        middle,
+        Direction::Horizontal,
+        [Constraint::Percentage(65), Constraint::Percentage(35)],
+    );
+    render_functions(frame, functions, state);
+    render_alerts(frame, alerts, state);
+
+    render_logs(frame, logs, state);
+}
+
+fn split<const N: usize>(
+    area: Rect,
+    direction: Direction,
+    constraints: [Constraint; N],
+) -> [Rect; N] {
+    let chunks = Layout::default()
+        .direction(direction)
+        .constraints(constraints)
+        .split(area);
+
+    std::array::from_fn(|i| chunks[i])
+}
+
+fn block(title: String) -> Block<'static> {
+    Block::default().borders(Borders::ALL).title(Span::styled(
+        format!(" {title} "),
+        Style::default().add_modifier(Modifier::BOLD),
+    ))
+}
+
+fn header_style() -> Style {
+    Style::default()
+        .fg(Color::DarkGray)
+        .add_modifier(Modifier::BOLD)
+}
+
+fn render_header(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let mut title = vec![
+        Span::styled(
+            " am ",
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            concat!("v", env!("CARGO_PKG_VERSION")),
+            Style::default().fg(Color::Magenta),
+        ),
+    ];
+    if let Some(url) = &state.explorer_url {
+        title.push(Span::raw(format!("   explorer {url}")));
+    }
+
+    let status = match &state.error {
+        Some(error) => Span::styled(
+            format!(" Unable to query Prometheus: {error}"),
+            Style::default().fg(Color::Red),
+        ),
+        None => Span::styled(
+            " q quit · tab switch logs · s change sort order",
+            Style::default().fg(Color::DarkGray),
+        ),
+    };
+
+    let paragraph = Paragraph::new(vec![Line::from(title), Line::from(status)]);
+    frame.render_widget(paragraph, area);
+}
+
+fn render_processes(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let rows = state.processes.iter().map(|process| {
+        let (label, color) = match process.state {
+            None => ("starting", Color::Yellow),
+            Some(ProcessState::Running) => ("running", Color::Green),
+            Some(ProcessState::Restarting) => ("restarting", Color::Yellow),
+            Some(ProcessState::Exited) => ("exited", Color::DarkGray),
+            Some(ProcessState::Failed) => ("failed", Color::Red),
+        };
+
+        Row::new(vec![
+            Span::raw(process.name),
+            Span::styled(label, Style::default().fg(color)),
+            Span::raw(process.restarts.to_string()),
+            Span::raw(process.url.clone().unwrap_or_default()),
+        ])
+    });
+
+    let table = Table::new(rows)
+        .header(Row::new(vec!["NAME", "STATE", "RESTARTS", "URL"]).style(header_style()))
+        .widths(&[
+            Constraint::Length(12),
+            Constraint::Length(10),
+            Constraint::Length(8),
+            Constraint::Min(10),
+        ])
+        .block(block("Processes".to_string()));
+    frame.render_widget(table, area);
+}
+
+fn render_targets(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let up = state
+        .targets
+        .iter()
+        .filter(|target| target.health == TargetHealth::Up)
+        .count();
+    let rows = state.targets.iter().map(|target| {
+        let (label, color) = match target.health {
+            TargetHealth::Up => ("up", Color::Green),
+            TargetHealth::Down => ("down", Color::Red),
+            TargetHealth::Unknown => ("unknown", Color::Yellow),
+        };
+
+        Row::new(vec![
+            Span::raw(target.job.clone()),
+            Span::raw(target.scrape_url.clone()),
+            Span::styled(label, Style::default().fg(color)),
+            Span::styled(
+                target.last_error.clone().unwrap_or_default(),
+                Style::default().fg(Color::Red),
+            ),
+        ])
+    });
+
+    let table = Table::new(rows)
+        .header(Row::new(vec!["JOB", "URL", "HEALTH", "LAST ERROR"]).style(header_style()))
+        .widths(&[
+            Constraint::Percentage(20),
+            Constraint::Percentage(35),
+            Constraint::Length(7),
+            Constraint::Percentage(45),
+        ])
+        .block(block(format!("Targets ({up}/{} up)", state.targets.len())));
+    frame.render_widget(table, area);
+}
+
+fn render_functions(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let rows = state
+        .functions
+        .iter()
+        .take(TOP_FUNCTIONS)
+        .filter_map(|report| {
+            let stats = report.stats.as_ref()?;
+            let errors = Style::default().fg(if stats.error_ratio > 0.0 {
+                Color::Red
+            } else {
+                Color::Reset
+            });
+
+            Some(Row::new(vec![
+                Span::raw(report.id.module.clone()),
+                Span::raw(report.id.function.clone()),
+                Span::raw(format!("{}/s", format_value(stats.rate))),
+                Span::styled(format_ratio(stats.error_ratio), errors),
+                Span::raw(format_latency(stats.p95)),
+                Span::raw(format_latency(stats.p99)),
+            ]))
+        });
+
+    let sort = match state.sort {
+        SortBy::Rate => "rate",
+        SortBy::Errors => "errors",
+        SortBy::P95 => "p95",
+        SortBy::P99 => "p99",
+        SortBy::Module => "module",
+        SortBy::Function => "function",
+    };
+
+    let table = Table::new(rows)
+        .header(
+            Row::new(vec!["MODULE", "FUNCTION", "RATE", "ERRORS", "P95", "P99"])
+                .style(header_style()),
+        )
+        .widths(&[
+            Constraint::Percentage(25),
+            Constraint::Percentage(30),
+            Constraint::Length(10),
+            Constraint::Length(8),
+            Constraint::Length(9),
+            Constraint::Length(9),
+        ])
+        .block(block(format!("Top functions by {sort}")));
+    frame.render_widget(table, area);
+}
+
+fn render_alerts(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let lines: Vec<Line> = if state.alerts.is_empty() {
+        vec![Line::from(Span::styled(
+            "No alerts are firing",
+            Style::default().fg(Color::DarkGray),
+        ))]
+    } else {
+        state
+            .alerts
+            .iter()
+            .map(|alert| {
+                Line::from(vec![
+                    Span::styled(
+                        alert.name.clone(),
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw(format!(" {}", alert.labels)),
+                ])
+            })
+            .collect()
+    };
+
+    let paragraph =
+        Paragraph::new(lines).block(block(format!("Firing alerts ({})", state.alerts.len())));
+    frame.render_widget(paragraph, area);
+}
+
+fn render_logs(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let Some((name, buffer)) = state.log_sources.get(state.selected_log) else {
+        frame.render_widget(block("Logs".to_string()), area);
+        return;
+    };
+
+    // Only the most recent lines that fit inside the borders are shown.
+    let height = area.height.saturating_sub(2) as usize;
+    let lines = buffer.lines();
+    let lines: Vec<Line> = lines[lines.len().saturating_sub(height)..]
+        .iter()
+        .map(|line| {
+            let style = match line.stream {
+                LogStream::Stdout => Style::default(),
+                LogStream::Stderr if *name == "am" => Style::default(),
+                LogStream::Stderr => Style::default().fg(Color::DarkGray),
+            };
+            Line::from(Span::styled(line.line.clone(), style))
+        })
+        .collect();
+
+    let sources = state
+        .log_sources
+        .iter()
+        .map(|(source, _)| {
+            if source == name {
+                format!("[{source}]")
+            } else {
+                source.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let paragraph = Paragraph::new(lines).block(block(format!("Logs: {sources}")));
+    frame.render_widget(paragraph, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::functions::FunctionStats;
+    use ratatui::backend::TestBackend;
+
+    #[test]
+    fn dashboard_is_rendered() {
+        let logs = Arc::new(LogBuffer::new(10));
+        logs.push(
+            LogStream::Stderr,
+            "level=info msg=\"Server is ready to receive web requests.\"".to_string(),
+        );
+
+        let mut state = DashboardState {
+            processes: vec![ProcessStatus {
+                name: "Prometheus",
+                state: Some(ProcessState::Running),
+                restarts: 1,
+                url: Some("http://127.0.0.1:9090/prometheus".to_string()),
+            }],
+            targets: vec![
+                TargetStatus {
+                    job: "api".to_string(),
+                    scrape_url: "http://localhost:3000/metrics".to_string(),
+                    health: TargetHealth::Up,
+                    last_error: None,
+                    last_scrape: "2023-11-20T10:00:00Z".to_string(),
+                    since: "2023-11-20T09:00:00Z".to_string(),
+                },
+                TargetStatus {
+                    job: "worker".to_string(),
+                    scrape_url: "http://localhost:3001/metrics".to_string(),
+                    health: TargetHealth::Down,
+                    last_error: Some("connection refused".to_string()),
+                    last_scrape: "2023-11-20T10:00:00Z".to_string(),
+                    since: "2023-11-20T09:30:00Z".to_string(),
+                },
+            ],
+            functions: vec![
+                FunctionReport {
+                    id: ("api", "list_users").into(),
+                    location: None,
+                    stats: Some(FunctionStats {
+                        rate: 4.0,
+                        error_ratio: 0.0,
+                        p95: 0.0123,
+                        p99: 0.25,
+                    }),
+                },
+                FunctionReport {
+                    id: ("api", "create_user").into(),
+                    location: None,
+                    stats: Some(FunctionStats {
+                        rate: 0.5,
+                        error_ratio: 0.1,
+                        p95: 1.5,
+                        p99: f64::NAN,
+                    }),
+                },
+            ],
+            alerts: vec![FiringAlert {
+                name: "HighErrorRate".to_string(),
+                labels: "function=create_user".to_string(),
+            }],
+            log_sources: vec![("am", Arc::new(LogBuffer::new(10))), ("Prometheus", logs)],
+            ..Default::default()
+        };
+
+        // Switch to the logs of Prometheus and sort by the error ratio.
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert_eq!(state.handle_key(key(KeyCode::Tab)), KeyResult::Continue);
+        assert_eq!(
+            state.handle_key(key(KeyCode::Char('s'))),
+            KeyResult::Continue
+        );
+        assert_eq!(state.handle_key(key(KeyCode::Char('q'))), KeyResult::Quit);
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|frame| render(frame, &state)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let screen: Vec<String> = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol.as_str())
+                    .collect()
+            })
+            .collect();
+        let screen = screen.join("\n");
+
+        for expected in [
+            "running",
+            "Targets (1/2 up)",
+            "down",
+            "connection refused",
+            "Top functions by errors",
+            "Firing alerts (1)",
+            "HighErrorRate function=create_user",
+            "Logs: am [Prometheus]",
+            "Server is ready to receive web requests.",
+        ] {
+            assert!(screen.contains(expected), "{expected:?} not in:\n{screen}");
+        }
+
+        // Functions with errors are sorted first.
+        assert_eq!(state.functions[0].id.function, "create_user");
+    }
+}
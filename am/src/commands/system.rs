@@ -1,7 +1,12 @@
+use crate::components::{self, Component, COMPONENTS};
 use anyhow::Result;
+use autometrics_am::config::AmConfig;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
 use clap::{Parser, Subcommand};
 use indicatif::MultiProgress;
 
+pub mod install;
+pub mod list;
 pub mod prune;
 
 #[derive(Parser)]
@@ -13,12 +18,28 @@ pub struct Arguments {
 
 #[derive(Subcommand)]
 pub enum SubCommands {
-    /// Delete all locally downloaded binaries.
+    /// Delete locally downloaded binaries. Deletes everything, unless one of
+    /// the filters is used.
     Prune(prune::Arguments),
+
+    /// List the installed versions of Prometheus and Pushgateway.
+    List(list::Arguments),
+
+    /// Install Prometheus or Pushgateway, either by downloading it or from a
+    /// local archive.
+    Install(install::Arguments),
 }
 
-pub async fn handle_command(args: Arguments, mp: MultiProgress) -> Result<()> {
+pub async fn handle_command(args: Arguments, config: AmConfig, mp: MultiProgress) -> Result<()> {
     match args.command {
         SubCommands::Prune(args) => prune::handle_command(args, mp).await,
+        SubCommands::Install(args) => install::handle_command(args, config, mp).await,
+        SubCommands::List(args) => list::handle_command(args).await,
     }
 }
+
+/// Parses the identifier of one of the known components.
+fn component_parser() -> impl TypedValueParser<Value = &'static Component> {
+    PossibleValuesParser::new(COMPONENTS.iter().map(|component| component.id))
+        .map(|id| components::find(&id).expect("only known components are accepted"))
+}
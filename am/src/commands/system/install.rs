@@ -0,0 +1,129 @@
+use super::component_parser;
+use crate::commands::start::install::{install, install_from_archive, verify};
+use crate::components::Component;
+use crate::downloader::{ReleaseSource, DEFAULT_DOWNLOAD_RETRIES, DEFAULT_RELEASE_URL_TEMPLATE};
+use anyhow::{bail, Context, Result};
+use autometrics_am::config::AmConfig;
+use clap::Parser;
+use directories::ProjectDirs;
+use indicatif::MultiProgress;
+use std::fs;
+use std::path::PathBuf;
+use tracing::{info, warn};
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Arguments {
+    /// The component to install.
+    #[clap(value_parser = component_parser())]
+    component: &'static Component,
+
+    /// The version to install. Defaults to the version that `am start` uses.
+    ///
+    /// When installing from an archive, the version is read from the name of
+    /// the archive instead.
+    #[clap(long)]
+    version: Option<String>,
+
+    /// Install from a local release archive instead of downloading it, for
+    /// example `prometheus-2.47.2.linux-amd64.tar.gz`. This does not use the
+    /// network.
+    #[clap(long, requires = "checksums")]
+    from_archive: Option<PathBuf>,
+
+    /// The `sha256sums.txt` file of the release, used to verify the archive
+    /// passed to `--from-archive`.
+    #[clap(long, requires = "from_archive")]
+    checksums: Option<PathBuf>,
+
+    /// The URL template used to download the release. See `am start --help`.
+    #[clap(long, env)]
+    release_url_template: Option<String>,
+
+    /// The amount of times a failed download is retried. [default: 3]
+    #[clap(long, env)]
+    download_retries: Option<u32>,
+
+    /// Replace the installed version, if it is already installed.
+    #[clap(short, long)]
+    force: bool,
+}
+
+pub async fn handle_command(args: Arguments, config: AmConfig, mp: MultiProgress) -> Result<()> {
+    let component = args.component;
+
+    let version = match &args.from_archive {
+        Some(archive) => {
+            let package = archive
+                .file_name()
+                .and_then(|name| name.to_str())
+                .context("Invalid archive file name")?;
+            let version = component.version_from_package(package)?;
+
+            if let Some(requested) = &args.version {
+                if requested.trim_start_matches('v') != version {
+                    bail!("{package} contains version {version}, but version {requested} was requested");
+                }
+            }
+
+            version
+        }
+        None => args
+            .version
+            .or_else(|| component.configured_version(&config))
+            .unwrap_or_else(|| component.default_version.to_string()),
+    };
+    let version = version.trim_start_matches('v');
+
+    let project_dirs =
+        ProjectDirs::from("", "autometrics", "am").context("Unable to determine home directory")?;
+    let local_data = project_dirs.data_local_dir().to_owned();
+    let path = component.install_dir(&local_data, version);
+
+    if path.exists() {
+        match verify(&path) {
+            Ok(()) if !args.force => {
+                info!(
+                    "{} {version} is already installed in {}",
+                    component.name,
+                    path.display()
+                );
+                return Ok(());
+            }
+            Ok(()) => {}
+            Err(err) => warn!("Existing installation is invalid ({err:#}), reinstalling"),
+        }
+
+        fs::remove_dir_all(&path)
+            .with_context(|| format!("Unable to remove {}", path.display()))?;
+    }
+
+    fs::create_dir_all(&local_data)
+        .with_context(|| format!("Unable to create data directory: {:?}", local_data))?;
+
+    match (&args.from_archive, &args.checksums) {
+        (Some(archive), Some(checksums)) => {
+            install_from_archive(component, &path, archive, checksums, &mp).await?
+        }
+        _ => {
+            let template = args
+                .release_url_template
+                .or(config.release_url_template)
+                .unwrap_or_else(|| DEFAULT_RELEASE_URL_TEMPLATE.to_string());
+            let retries = args
+                .download_retries
+                .or(config.download_retries)
+                .unwrap_or(DEFAULT_DOWNLOAD_RETRIES);
+            let source = ReleaseSource::new(template)?.with_retries(retries);
+
+            install(component, &path, version, &source, &mp).await?
+        }
+    }
+
+    info!(
+        "Installed {} {version} into {}",
+        component.name,
+        path.display()
+    );
+    Ok(())
+}
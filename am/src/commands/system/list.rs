@@ -0,0 +1,64 @@
+use crate::commands::start::install::installations;
+use anyhow::{Context, Result};
+use clap::Parser;
+use directories::ProjectDirs;
+use indicatif::HumanBytes;
+use std::time::{Duration, SystemTime};
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Arguments {}
+
+pub async fn handle_command(_: Arguments) -> Result<()> {
+    let project_dirs =
+        ProjectDirs::from("", "autometrics", "am").context("Unable to determine home directory")?;
+    let local_data = project_dirs.data_local_dir().to_owned();
+
+    let installations = installations(&local_data)?;
+    if installations.is_empty() {
+        println!("Nothing is installed in {}", local_data.display());
+        return Ok(());
+    }
+
+    println!(
+        "{:<12} {:<10} {:>10}  LAST USED",
+        "COMPONENT", "VERSION", "SIZE"
+    );
+    for installation in installations {
+        let last_used = installation
+            .last_used
+            .map(format_last_used)
+            .unwrap_or_else(|| "unknown".to_string());
+
+        println!(
+            "{:<12} {:<10} {:>10}  {}",
+            installation.component.id,
+            installation.version,
+            HumanBytes(installation.size).to_string(),
+            last_used
+        );
+    }
+
+    Ok(())
+}
+
+/// Format `time` relative to now, rounded to a unit that is still readable,
+/// for example `3days ago`.
+pub(crate) fn format_last_used(time: SystemTime) -> String {
+    let Ok(elapsed) = time.elapsed() else {
+        return "just now".to_string();
+    };
+
+    let secs = elapsed.as_secs();
+    let rounded = match secs {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => secs - secs % 60,
+        3600..=86399 => secs - secs % 3600,
+        _ => secs - secs % 86400,
+    };
+
+    format!(
+        "{} ago",
+        humantime::format_duration(Duration::from_secs(rounded))
+    )
+}
@@ -1,9 +1,15 @@
+use super::component_parser;
+use crate::commands::start::install::{installations, Installation};
+use crate::commands::workspace::WORKSPACES_DIR;
+use crate::components::Component;
 use crate::interactive;
 use anyhow::{bail, Context, Result};
 use clap::Parser;
 use directories::ProjectDirs;
-use indicatif::MultiProgress;
-use std::io;
+use indicatif::{HumanBytes, MultiProgress};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use std::{fs, io};
 use tracing::{debug, info};
 
 #[derive(Parser)]
@@ -12,32 +18,218 @@ pub struct Arguments {
     /// Force the cleanup without asking for confirmation.
     #[clap(short, long, default_value = "false")]
     force: bool,
+
+    /// Keep the latest N installed versions of every component.
+    #[clap(long, value_name = "N")]
+    keep_latest: Option<usize>,
+
+    /// Only delete installations of this component.
+    #[clap(long, value_parser = component_parser())]
+    component: Option<&'static Component>,
+
+    /// Only delete installations that have not been used for this long, for
+    /// example `30d`.
+    #[clap(long, value_parser = humantime::parse_duration)]
+    older_than: Option<Duration>,
+
+    /// Print what would be deleted, without deleting anything.
+    #[clap(long)]
+    dry_run: bool,
 }
 
-pub async fn handle_command(args: Arguments, _: MultiProgress) -> Result<()> {
-    // If the users hasn't specified the `force` argument, then ask the user if
-    // they want to continue.
-    if !args.force && !interactive::confirm("Prune all am program files?")? {
-        bail!("Pruning cancelled");
+impl Arguments {
+    fn has_filters(&self) -> bool {
+        self.keep_latest.is_some() || self.component.is_some() || self.older_than.is_some()
     }
+}
 
+pub async fn handle_command(args: Arguments, _: MultiProgress) -> Result<()> {
     // Get local directory
     let project_dirs =
         ProjectDirs::from("", "autometrics", "am").context("Unable to determine home directory")?;
     let local_data = project_dirs.data_local_dir().to_owned();
 
-    debug!("Deleting all content from {:?}", local_data);
+    if !args.has_filters() {
+        return prune_all(&args, &local_data);
+    }
+
+    let installations = installations(&local_data)?;
+    let selected = select_for_pruning(&installations, &args, SystemTime::now());
 
-    // For now just greedily delete everything in the local data directory for am
-    if let Err(err) = remove_dir_all::remove_dir_contents(&local_data) {
-        // If the root directory does not exist, we can ignore the error (NOTE:
-        // I don't know if it is possible to get this error in any other
-        // situations)
-        if err.kind() != io::ErrorKind::NotFound {
-            return Err(err.into());
+    if selected.is_empty() {
+        info!("Nothing to prune");
+        return Ok(());
+    }
+
+    if args.dry_run {
+        for installation in selected {
+            println!(
+                "Would delete {} {} ({})",
+                installation.component.id,
+                installation.version,
+                HumanBytes(installation.size)
+            );
         }
+        return Ok(());
+    }
+
+    let prompt = format!("Prune {} installation(s)?", selected.len());
+    if !args.force && !interactive::confirm(prompt)? {
+        bail!("Pruning cancelled");
+    }
+
+    for installation in selected {
+        debug!("Deleting {:?}", installation.path);
+        fs::remove_dir_all(&installation.path)
+            .with_context(|| format!("Unable to delete {}", installation.path.display()))?;
+        info!(
+            "Deleted {} {} ({})",
+            installation.component.id,
+            installation.version,
+            HumanBytes(installation.size)
+        );
     }
 
     info!("Pruning complete");
     Ok(())
 }
+
+/// Delete everything in the local data directory, except for the workspaces.
+/// Those contain data instead of program files and are deleted using
+/// `am workspace delete`.
+fn prune_all(args: &Arguments, local_data: &Path) -> Result<()> {
+    let entries = match fs::read_dir(local_data) {
+        Ok(entries) => entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .filter(|path| {
+                path.as_ref()
+                    .map(|path| !path.ends_with(WORKSPACES_DIR))
+                    .unwrap_or(true)
+            })
+            .collect::<io::Result<Vec<_>>>()?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => vec![],
+        Err(err) => return Err(err.into()),
+    };
+
+    if args.dry_run {
+        for path in entries {
+            println!("Would delete {}", path.display());
+        }
+        return Ok(());
+    }
+
+    // If the users hasn't specified the `force` argument, then ask the user if
+    // they want to continue.
+    if !args.force && !interactive::confirm("Prune all am program files?")? {
+        bail!("Pruning cancelled");
+    }
+
+    debug!("Deleting all content from {:?}", local_data);
+
+    for path in entries {
+        let result = if path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else {
+            fs::remove_file(&path)
+        };
+        result.with_context(|| format!("Unable to delete {}", path.display()))?;
+    }
+
+    info!("Pruning complete");
+    Ok(())
+}
+
+/// Select the installations that match all filters in `args`. The
+/// `installations` need to be sorted by version, newest first.
+fn select_for_pruning<'a>(
+    installations: &'a [Installation],
+    args: &Arguments,
+    now: SystemTime,
+) -> Vec<&'a Installation> {
+    installations
+        .iter()
+        .filter(|installation| {
+            args.component
+                .map(|component| component == installation.component)
+                .unwrap_or(true)
+        })
+        .filter(|installation| {
+            let Some(keep_latest) = args.keep_latest else {
+                return true;
+            };
+
+            let newer = installations
+                .iter()
+                .take_while(|other| !std::ptr::eq(*other, *installation))
+                .filter(|other| other.component == installation.component)
+                .count();
+            newer >= keep_latest
+        })
+        .filter(|installation| {
+            let Some(older_than) = args.older_than else {
+                return true;
+            };
+
+            installation
+                .last_used
+                .and_then(|last_used| now.duration_since(last_used).ok())
+                .map(|unused_for| unused_for >= older_than)
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::{PROMETHEUS, PUSHGATEWAY};
+    use std::path::PathBuf;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn installation(component: &'static Component, version: &str, days_ago: u64) -> Installation {
+        Installation {
+            component,
+            version: version.to_string(),
+            path: PathBuf::from(format!("{}-{version}", component.id)),
+            size: 0,
+            last_used: Some(SystemTime::UNIX_EPOCH + DAY * (100 - days_ago) as u32),
+        }
+    }
+
+    #[test]
+    fn filters_are_combined() {
+        let installations = vec![
+            installation(&PROMETHEUS, "2.47.2", 1),
+            installation(&PROMETHEUS, "2.45.0", 40),
+            installation(&PROMETHEUS, "2.44.0", 10),
+            installation(&PUSHGATEWAY, "1.6.2", 60),
+        ];
+        let now = SystemTime::UNIX_EPOCH + DAY * 100;
+
+        let selected = |args: &[&str]| {
+            let args = Arguments::try_parse_from(["prune"].iter().chain(args)).unwrap();
+            select_for_pruning(&installations, &args, now)
+                .into_iter()
+                .map(|i| format!("{}-{}", i.component.id, i.version))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            selected(&["--keep-latest", "1"]),
+            vec!["prometheus-2.45.0", "prometheus-2.44.0"]
+        );
+        assert_eq!(
+            selected(&["--older-than", "30d"]),
+            vec!["prometheus-2.45.0", "pushgateway-1.6.2"]
+        );
+        assert_eq!(
+            selected(&["--component", "prometheus", "--older-than", "30d"]),
+            vec!["prometheus-2.45.0"]
+        );
+        assert_eq!(
+            selected(&["--keep-latest", "2", "--component", "pushgateway"]),
+            Vec::<String>::new()
+        );
+    }
+}
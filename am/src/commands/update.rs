@@ -1,5 +1,5 @@
 use crate::commands::start::CLIENT;
-use crate::downloader::download_github_release;
+use crate::downloader::{download_release, ReleaseSource};
 use anyhow::{anyhow, bail, Context, Result};
 use clap::Parser;
 use directories::ProjectDirs;
@@ -8,7 +8,7 @@ use itertools::Itertools;
 use octocrab::models::repos::{Asset, Release};
 use self_replace::self_replace;
 use semver_rs::Version;
-use std::fs::{File, OpenOptions};
+use std::fs::OpenOptions;
 use std::time::{Duration, SystemTime};
 use std::{env, fs};
 use tracing::{debug, error, info, trace, warn};
@@ -63,12 +63,15 @@ pub(crate) async fn handle_command(args: Arguments, mp: MultiProgress) -> Result
     let temp_exe = executable
         .parent()
         .ok_or_else(|| anyhow!("Parent directory not found"))?
-        .join("am_update.part");
-
-    let file = File::create(&temp_exe)?;
-
-    let calculated_checksum = download_github_release(
-        &file,
+        // Interrupted downloads are resumed, so the version is part of the
+        // name to never resume the download of a different release.
+        .join(format!("am_update-{new_tag}.part"));
+
+    // am itself is always updated from GitHub, since the release is looked up
+    // using the GitHub API.
+    let calculated_checksum = download_release(
+        &temp_exe,
+        &ReleaseSource::default(),
         AUTOMETRICS_GITHUB_ORG,
         AUTOMETRICS_AM_REPO,
         new_tag.strip_prefix('v').unwrap_or(&new_tag),
@@ -0,0 +1,172 @@
+use crate::commands::start::install::{dir_size, LAST_USED_FILE};
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub mod delete;
+pub mod export;
+pub mod list;
+
+/// The directory inside of the local data directory that contains the
+/// workspaces.
+pub(crate) const WORKSPACES_DIR: &str = "workspaces";
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Arguments {
+    #[command(subcommand)]
+    pub command: SubCommands,
+}
+
+#[derive(Subcommand)]
+pub enum SubCommands {
+    /// List the workspaces, which are created by `am start --workspace`.
+    List(list::Arguments),
+
+    /// Delete a workspace, including all of its data.
+    Delete(delete::Arguments),
+
+    /// Export a workspace to a `.tar.gz` archive.
+    Export(export::Arguments),
+}
+
+pub fn handle_command(args: Arguments) -> Result<()> {
+    match args.command {
+        SubCommands::List(args) => list::handle_command(args),
+        SubCommands::Delete(args) => delete::handle_command(args),
+        SubCommands::Export(args) => export::handle_command(args),
+    }
+}
+
+/// A workspace, which keeps the data of Prometheus and the other components
+/// across runs of `am start`.
+#[derive(Debug, Clone)]
+pub(crate) struct Workspace {
+    pub(crate) name: String,
+    pub(crate) path: PathBuf,
+    /// The size of the workspace on disk, in bytes.
+    pub(crate) size: u64,
+    /// When `am start` last used this workspace.
+    pub(crate) last_used: Option<SystemTime>,
+}
+
+impl Workspace {
+    /// Whether Prometheus is currently using this workspace. Prometheus holds
+    /// a lock file in its data directory while it is running, which is
+    /// removed when it shuts down. It is left behind if Prometheus crashes.
+    pub(crate) fn is_in_use(&self) -> bool {
+        self.path
+            .join("prometheus")
+            .join("data")
+            .join("lock")
+            .exists()
+    }
+}
+
+/// The directory of the workspace `name`, which does not need to exist yet.
+pub(crate) fn workspace_dir(local_data: &Path, name: &str) -> PathBuf {
+    local_data.join(WORKSPACES_DIR).join(name)
+}
+
+/// Find all workspaces in `local_data`, sorted by name.
+pub(crate) fn workspaces(local_data: &Path) -> Result<Vec<Workspace>> {
+    let dir = local_data.join(WORKSPACES_DIR);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err).with_context(|| format!("Unable to read {}", dir.display())),
+    };
+
+    let mut workspaces = vec![];
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+
+        let path = entry.path();
+        let last_used = fs::metadata(path.join(LAST_USED_FILE))
+            .or_else(|_| entry.metadata())
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        workspaces.push(Workspace {
+            name,
+            size: dir_size(&path)?,
+            path,
+            last_used,
+        });
+    }
+
+    workspaces.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(workspaces)
+}
+
+/// Find the existing workspace `name`.
+pub(crate) fn find_workspace(local_data: &Path, name: &str) -> Result<Workspace> {
+    match workspaces(local_data)?
+        .into_iter()
+        .find(|workspace| workspace.name == name)
+    {
+        Some(workspace) => Ok(workspace),
+        None => {
+            bail!("Workspace {name} does not exist, use `am workspace list` to see all workspaces")
+        }
+    }
+}
+
+/// Parses the name of a workspace. Names are used as directory names, so
+/// only letters, digits, `-`, `_` and `.` are allowed.
+pub(crate) fn workspace_name_parser(input: &str) -> Result<String> {
+    let valid_chars = input
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+
+    if input.is_empty() || input.starts_with('.') || !valid_chars {
+        bail!("invalid workspace name {input:?}, only letters, digits, `-`, `_` and `.` are allowed and it cannot start with `.`");
+    }
+
+    Ok(input.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workspaces_are_listed_by_name() {
+        let local_data = tempfile::tempdir().unwrap();
+        assert!(workspaces(local_data.path()).unwrap().is_empty());
+
+        for name in ["staging", "payments"] {
+            let dir = workspace_dir(local_data.path(), name).join("prometheus");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("queries.active"), "0123456789").unwrap();
+        }
+        fs::create_dir_all(local_data.path().join("prometheus-2.47.2")).unwrap();
+
+        let workspaces = workspaces(local_data.path()).unwrap();
+        let names: Vec<&str> = workspaces.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, vec!["payments", "staging"]);
+        assert_eq!(workspaces[0].size, 10);
+        assert!(!workspaces[0].is_in_use());
+
+        assert!(find_workspace(local_data.path(), "orders").is_err());
+    }
+
+    #[test]
+    fn workspace_names_are_validated() {
+        assert_eq!(workspace_name_parser("payments-v2").unwrap(), "payments-v2");
+        assert!(workspace_name_parser("").is_err());
+        assert!(workspace_name_parser("..").is_err());
+        assert!(workspace_name_parser("../payments").is_err());
+        assert!(workspace_name_parser("pay ments").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+use super::{find_workspace, workspace_name_parser};
+use crate::interactive;
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use directories::ProjectDirs;
+use indicatif::HumanBytes;
+use std::fs;
+use tracing::{debug, info};
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Arguments {
+    /// The name of the workspace to delete.
+    #[clap(value_parser = workspace_name_parser)]
+    name: String,
+
+    /// Delete the workspace without asking for confirmation, even if it
+    /// appears to be in use.
+    #[clap(short, long)]
+    force: bool,
+}
+
+pub fn handle_command(args: Arguments) -> Result<()> {
+    let project_dirs =
+        ProjectDirs::from("", "autometrics", "am").context("Unable to determine home directory")?;
+    let local_data = project_dirs.data_local_dir().to_owned();
+
+    let workspace = find_workspace(&local_data, &args.name)?;
+
+    if workspace.is_in_use() && !args.force {
+        bail!(
+            "Workspace {} is in use by a running am instance. Stop it first, or use --force if it is not running anymore",
+            workspace.name
+        );
+    }
+
+    let prompt = format!(
+        "Delete workspace {} ({})?",
+        workspace.name,
+        HumanBytes(workspace.size)
+    );
+    if !args.force && !interactive::confirm(prompt)? {
+        bail!("Deleting workspace cancelled");
+    }
+
+    debug!("Deleting {:?}", workspace.path);
+    fs::remove_dir_all(&workspace.path)
+        .with_context(|| format!("Unable to delete {}", workspace.path.display()))?;
+
+    info!("Deleted workspace {}", workspace.name);
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use super::{find_workspace, workspace_name_parser, Workspace};
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use directories::ProjectDirs;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use indicatif::HumanBytes;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Arguments {
+    /// The name of the workspace to export.
+    #[clap(value_parser = workspace_name_parser)]
+    name: String,
+
+    /// The file to write the archive to. Defaults to `<NAME>.tar.gz` in the
+    /// current directory.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// Export the workspace even if it appears to be in use. The data that
+    /// Prometheus has not written to disk yet is not included.
+    #[clap(short, long)]
+    force: bool,
+}
+
+pub fn handle_command(args: Arguments) -> Result<()> {
+    let project_dirs =
+        ProjectDirs::from("", "autometrics", "am").context("Unable to determine home directory")?;
+    let local_data = project_dirs.data_local_dir().to_owned();
+
+    let workspace = find_workspace(&local_data, &args.name)?;
+
+    if workspace.is_in_use() && !args.force {
+        bail!(
+            "Workspace {} is in use by a running am instance. Stop it first, or use --force to export it anyway",
+            workspace.name
+        );
+    }
+
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("{}.tar.gz", workspace.name)));
+
+    export_workspace(&workspace, &output)?;
+
+    let size = fs::metadata(&output)?.len();
+    info!(
+        "Exported workspace {} to {} ({})",
+        workspace.name,
+        output.display(),
+        HumanBytes(size)
+    );
+
+    Ok(())
+}
+
+/// Write the contents of `workspace` into a `.tar.gz` archive at `output`.
+/// All files are stored in a single directory named after the workspace.
+fn export_workspace(workspace: &Workspace, output: &Path) -> Result<()> {
+    let file =
+        File::create(output).with_context(|| format!("Unable to create {}", output.display()))?;
+
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    archive
+        .append_dir_all(&workspace.name, &workspace.path)
+        .with_context(|| format!("Unable to archive {}", workspace.path.display()))?;
+    archive.into_inner()?.finish()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+
+    #[test]
+    fn workspace_is_exported_into_a_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("payments");
+        fs::create_dir_all(path.join("prometheus")).unwrap();
+        fs::write(path.join("prometheus").join("queries.active"), "").unwrap();
+
+        let workspace = Workspace {
+            name: "payments".to_string(),
+            path,
+            size: 0,
+            last_used: None,
+        };
+        let output = dir.path().join("payments.tar.gz");
+        export_workspace(&workspace, &output).unwrap();
+
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(&output).unwrap()));
+        let paths: Vec<PathBuf> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().into_owned())
+            .collect();
+        assert!(paths.contains(&PathBuf::from("payments/prometheus/queries.active")));
+    }
+}
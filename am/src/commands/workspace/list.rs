@@ -0,0 +1,42 @@
+use crate::commands::system::list::format_last_used;
+use anyhow::{Context, Result};
+use clap::Parser;
+use directories::ProjectDirs;
+use indicatif::HumanBytes;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Arguments {}
+
+pub fn handle_command(_: Arguments) -> Result<()> {
+    let project_dirs =
+        ProjectDirs::from("", "autometrics", "am").context("Unable to determine home directory")?;
+    let local_data = project_dirs.data_local_dir().to_owned();
+
+    let workspaces = super::workspaces(&local_data)?;
+    if workspaces.is_empty() {
+        println!("No workspaces found, use `am start --workspace <NAME>` to create one");
+        return Ok(());
+    }
+
+    println!("{:<24} {:>10}  LAST USED", "WORKSPACE", "SIZE");
+    for workspace in workspaces {
+        let last_used = if workspace.is_in_use() {
+            "in use".to_string()
+        } else {
+            workspace
+                .last_used
+                .map(format_last_used)
+                .unwrap_or_else(|| "unknown".to_string())
+        };
+
+        println!(
+            "{:<24} {:>10}  {}",
+            workspace.name,
+            HumanBytes(workspace.size).to_string(),
+            last_used
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,295 @@
+use crate::commands::start::ports::{
+    DEFAULT_ALERTMANAGER_PORT, DEFAULT_PROMETHEUS_PORT, DEFAULT_PUSHGATEWAY_PORT,
+};
+use anyhow::{bail, Result};
+use autometrics_am::config::AmConfig;
+use std::path::{Path, PathBuf};
+
+/// A tool that am downloads from its GitHub releases, installs into the local
+/// data directory and runs as a child process.
+///
+/// Components are declared as statics, such as [`PROMETHEUS`], and registered
+/// in [`COMPONENTS`], which is all that is needed to install, list and prune
+/// them.
+#[derive(Debug)]
+pub(crate) struct Component {
+    /// The identifier of the component. This is used for the directory it is
+    /// installed in, the path it is proxied on and in the CLI and the am API.
+    pub(crate) id: &'static str,
+
+    /// The name of the component as it is shown to the user.
+    pub(crate) name: &'static str,
+
+    /// The GitHub organization and repository that publish the releases.
+    pub(crate) github_org: &'static str,
+    pub(crate) github_repo: &'static str,
+
+    /// The name of the release archive, without the `.tar.gz` extension. The
+    /// `{version}`, `{os}` and `{arch}` placeholders are replaced when
+    /// downloading it. The archive needs to contain a single directory with
+    /// the same name.
+    pub(crate) archive: &'static str,
+
+    /// The name of the binary inside of the archive, without the `.exe`
+    /// extension on Windows.
+    pub(crate) binary: &'static str,
+
+    pub(crate) default_version: &'static str,
+    pub(crate) default_port: u16,
+
+    /// The version configured for the component in the am.toml file, such as
+    /// `prometheus-version`.
+    pub(crate) config_version: fn(&AmConfig) -> &Option<String>,
+
+    /// Arguments that are always passed to the binary, in addition to the
+    /// listen address and external URL.
+    pub(crate) default_args: &'static [&'static str],
+
+    /// Only listen on the loopback interface instead of on every interface,
+    /// for components whose API must not be reachable from other machines.
+    /// am itself reaches the components on localhost.
+    pub(crate) loopback_only: bool,
+
+    /// The path, relative to the external URL, that responds successfully once
+    /// the component is up and running.
+    pub(crate) health_check_path: &'static str,
+}
+
+pub(crate) static PROMETHEUS: Component = Component {
+    id: "prometheus",
+    name: "Prometheus",
+    github_org: "prometheus",
+    github_repo: "prometheus",
+    archive: "prometheus-{version}.{os}-{arch}",
+    binary: "prometheus",
+    default_version: "v2.47.2",
+    default_port: DEFAULT_PROMETHEUS_PORT,
+    config_version: |config| &config.prometheus_version,
+    default_args: &[
+        "--web.enable-lifecycle",
+        "--web.enable-remote-write-receiver",
+    ],
+    // The admin API is enabled to create snapshots, which allows deleting
+    // data as well.
+    loopback_only: true,
+    health_check_path: "/-/healthy",
+};
+
+pub(crate) static PUSHGATEWAY: Component = Component {
+    id: "pushgateway",
+    name: "Pushgateway",
+    github_org: "prometheus",
+    github_repo: "pushgateway",
+    archive: "pushgateway-{version}.{os}-{arch}",
+    binary: "pushgateway",
+    default_version: "v1.6.2",
+    default_port: DEFAULT_PUSHGATEWAY_PORT,
+    config_version: |config| &config.pushgateway_version,
+    default_args: &[],
+    loopback_only: false,
+    health_check_path: "/-/healthy",
+};
+
+pub(crate) static ALERTMANAGER: Component = Component {
+    id: "alertmanager",
+    name: "Alertmanager",
+    github_org: "prometheus",
+    github_repo: "alertmanager",
+    archive: "alertmanager-{version}.{os}-{arch}",
+    binary: "alertmanager",
+    default_version: "v0.26.0",
+    default_port: DEFAULT_ALERTMANAGER_PORT,
+    config_version: |config| &config.alertmanager_version,
+    // An empty address disables clustering, which would otherwise listen on
+    // port 9094.
+    default_args: &["--cluster.listen-address="],
+    loopback_only: false,
+    health_check_path: "/-/healthy",
+};
+
+/// Components are identified by their `id`, which is unique among the
+/// registered components.
+impl PartialEq for Component {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Component {}
+
+/// All components that am knows how to install.
+pub(crate) static COMPONENTS: &[&Component] = &[&PROMETHEUS, &PUSHGATEWAY, &ALERTMANAGER];
+
+/// Find the component with the identifier `id`.
+pub(crate) fn find(id: &str) -> Option<&'static Component> {
+    COMPONENTS
+        .iter()
+        .find(|component| component.id == id)
+        .copied()
+}
+
+impl Component {
+    /// The name of the archive for the current platform, without the `.tar.gz`
+    /// extension. For example: `prometheus-2.47.2.linux-amd64`.
+    pub(crate) fn package_base(&self, version: &str) -> Result<String> {
+        let (os, arch) = determine_os_and_arch()?;
+
+        Ok(self
+            .archive
+            .replace("{version}", version.trim_start_matches('v'))
+            .replace("{os}", os)
+            .replace("{arch}", arch))
+    }
+
+    /// The file name of the archive for the current platform.
+    pub(crate) fn package(&self, version: &str) -> Result<String> {
+        Ok(format!("{}.tar.gz", self.package_base(version)?))
+    }
+
+    /// Determine the version from the name of a release archive, such as
+    /// `prometheus-2.47.2.linux-amd64.tar.gz`. Returns an error if the archive
+    /// is not meant for the current platform.
+    pub(crate) fn version_from_package(&self, package: &str) -> Result<String> {
+        let (os, arch) = determine_os_and_arch()?;
+        let archive = self.archive.replace("{os}", os).replace("{arch}", arch);
+
+        let version = archive
+            .split_once("{version}")
+            .and_then(|(prefix, suffix)| {
+                package
+                    .strip_prefix(prefix)?
+                    .strip_suffix(".tar.gz")?
+                    .strip_suffix(suffix)
+            });
+
+        match version {
+            Some(version) if !version.is_empty() => Ok(version.to_string()),
+            _ => bail!(
+                "{package} is not a {} release archive for {os}-{arch}",
+                self.id
+            ),
+        }
+    }
+
+    /// The version that is configured for this component in `config`, if any.
+    pub(crate) fn configured_version(&self, config: &AmConfig) -> Option<String> {
+        (self.config_version)(config).clone()
+    }
+
+    /// The file name of the binary on the current platform.
+    pub(crate) fn binary_name(&self) -> String {
+        if cfg!(target_os = "windows") {
+            format!("{}.exe", self.binary)
+        } else {
+            self.binary.to_string()
+        }
+    }
+
+    /// The directory that `version` is installed in.
+    pub(crate) fn install_dir(&self, local_data: &Path, version: &str) -> PathBuf {
+        local_data.join(format!("{}-{}", self.id, version.trim_start_matches('v')))
+    }
+
+    /// The arguments to run the component with. `external_url` is the address
+    /// of the am web server, which proxies the component on `/{id}`.
+    pub(crate) fn args(&self, port: u16, external_url: &str) -> Vec<String> {
+        let host = if self.loopback_only { "127.0.0.1" } else { "" };
+        let mut args = vec![
+            format!("--web.listen-address={host}:{port}"),
+            format!("--web.external-url=http://{external_url}/{}", self.id),
+        ];
+        args.extend(self.default_args.iter().map(ToString::to_string));
+        args
+    }
+
+    /// The URL of the health check of the component listening on `port`.
+    pub(crate) fn health_check_url(&self, port: u16) -> String {
+        format!(
+            "http://localhost:{port}/{}{}",
+            self.id, self.health_check_path
+        )
+    }
+}
+
+/// Translates the OS and arch provided by Rust to the convention used by
+/// Prometheus.
+pub(crate) fn determine_os_and_arch() -> Result<(&'static str, &'static str)> {
+    use std::env::consts::{ARCH, OS};
+
+    let os = match OS {
+        "linux" => "linux",
+        "macos" => "darwin",
+        "windows" => "windows",
+        "freebsd" => "freebsd",
+        "netbsd" => "netbsd",
+        "openbsd" => "openbsd",
+        "dragonfly" => "dragonfly",
+        _ => bail!(format!("Unsupported OS: {}", ARCH)),
+    };
+
+    let arch = match ARCH {
+        "x86" => "386",
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "s390x" => "s390x",
+        "powerpc64" => "powerpc64", // NOTE: Do we use this one, or the le one?
+        // "mips" => "mips", // NOTE: Not sure which mips to pick in this situation
+        // "arm" => "arm", // NOTE: Not sure which arm to pick in this situation
+        _ => bail!(format!("Unsupported architecture: {}", ARCH)),
+    };
+
+    Ok((os, arch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_is_read_from_package_name() {
+        let package = PROMETHEUS.package("v2.47.2").unwrap();
+
+        assert_eq!(PROMETHEUS.version_from_package(&package).unwrap(), "2.47.2");
+        assert!(PUSHGATEWAY.version_from_package(&package).is_err());
+        assert!(PROMETHEUS
+            .version_from_package("prometheus-2.47.2.plan9-mips.tar.gz")
+            .is_err());
+    }
+
+    #[test]
+    fn components_are_found_by_id() {
+        assert_eq!(find("pushgateway"), Some(&PUSHGATEWAY));
+        assert_eq!(find("grafana"), None);
+
+        assert_eq!(
+            PUSHGATEWAY.args(9091, "localhost:6789"),
+            vec![
+                "--web.listen-address=:9091",
+                "--web.external-url=http://localhost:6789/pushgateway"
+            ]
+        );
+        assert_eq!(
+            PROMETHEUS.args(9090, "localhost:6789")[0],
+            "--web.listen-address=127.0.0.1:9090"
+        );
+    }
+
+    #[test]
+    fn configured_version_is_read_for_every_component() {
+        let config = AmConfig {
+            prometheus_version: Some("v2.45.0".to_string()),
+            alertmanager_version: Some("v0.25.0".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            PROMETHEUS.configured_version(&config).as_deref(),
+            Some("v2.45.0")
+        );
+        assert_eq!(PUSHGATEWAY.configured_version(&config), None);
+        assert_eq!(
+            ALERTMANAGER.configured_version(&config).as_deref(),
+            Some("v0.25.0")
+        );
+    }
+}
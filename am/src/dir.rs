@@ -11,16 +11,51 @@ pub struct AutoCleanupDir {
 
 impl AutoCleanupDir {
     pub(crate) fn new(process: &str, ephemeral: bool) -> Result<AutoCleanupDir> {
-        let start_dir = if ephemeral {
-            env::temp_dir()
+        let location = if ephemeral {
+            DataLocation::Ephemeral
         } else {
-            env::current_dir()?
+            DataLocation::CurrentDir
         };
 
-        let path = start_dir.join(".autometrics").join(process);
+        location.work_dir(process)
+    }
+}
+
+/// Where the child processes store their data, such as the TSDB of
+/// Prometheus. Every process gets its own directory inside of it.
+#[derive(Debug, Clone)]
+pub(crate) enum DataLocation {
+    /// `.autometrics` in the current directory.
+    CurrentDir,
+    /// `.autometrics` in the temp directory, which is removed on exit.
+    Ephemeral,
+    /// A named workspace in the am data directory, so the data is kept no
+    /// matter which directory am is started from.
+    Workspace(PathBuf),
+}
+
+impl DataLocation {
+    /// The directory that `process` stores its data in, which might not exist
+    /// yet.
+    pub(crate) fn dir(&self, process: &str) -> Result<PathBuf> {
+        let base_dir = match self {
+            DataLocation::CurrentDir => env::current_dir()?.join(".autometrics"),
+            DataLocation::Ephemeral => env::temp_dir().join(".autometrics"),
+            DataLocation::Workspace(workspace_dir) => workspace_dir.clone(),
+        };
+
+        Ok(base_dir.join(process))
+    }
+
+    /// Create the directory that `process` stores its data in.
+    pub(crate) fn work_dir(&self, process: &str) -> Result<AutoCleanupDir> {
+        let path = self.dir(process)?;
         fs::create_dir_all(&path)?;
 
-        Ok(AutoCleanupDir { path, ephemeral })
+        Ok(AutoCleanupDir {
+            path,
+            ephemeral: matches!(self, DataLocation::Ephemeral),
+        })
     }
 }
 
@@ -1,87 +1,338 @@
 use crate::commands::start::CLIENT;
 use anyhow::{anyhow, bail, Result};
 use flate2::read::GzDecoder;
+use futures_util::future;
+use http::header::{CONTENT_RANGE, RANGE};
+use http::StatusCode;
 use indicatif::{MultiProgress, ProgressBar, ProgressState, ProgressStyle};
 use sha2::{Digest, Sha256};
 use std::fmt;
-use std::fs::File;
-use std::io::{BufWriter, Write};
-use std::path::Path;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::{Component, Path};
 use std::time::Duration;
-use tracing::{debug, error};
+use thiserror::Error;
+use tokio::select;
+use tracing::{debug, error, warn};
+
+/// The URL template used to download releases from GitHub.
+pub const DEFAULT_RELEASE_URL_TEMPLATE: &str =
+    "https://github.com/{org}/{repo}/releases/download/v{version}/{package}";
+
+/// The amount of times a failed download is retried by default.
+pub const DEFAULT_DOWNLOAD_RETRIES: u32 = 3;
+
+/// The time to wait before the first retry. It is doubled for every retry.
+pub const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The location that releases are downloaded from. This is a URL template
+/// containing the `{org}`, `{repo}`, `{version}` and `{package}` placeholders,
+/// which allows downloading releases from a mirror instead of GitHub.
+#[derive(Debug, Clone)]
+pub struct ReleaseSource {
+    template: String,
+    retries: u32,
+}
+
+impl ReleaseSource {
+    pub fn new(template: impl Into<String>) -> Result<Self> {
+        let template = template.into();
+
+        if !template.contains("{package}") {
+            bail!("release URL template `{template}` does not contain the {{package}} placeholder");
+        }
+
+        Ok(Self {
+            template,
+            retries: DEFAULT_DOWNLOAD_RETRIES,
+        })
+    }
+
+    /// Set the amount of times a failed download is retried.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// The URL of `package` of the specified release.
+    pub fn url(&self, org: &str, repo: &str, version: &str, package: &str) -> String {
+        self.template
+            .replace("{org}", org)
+            .replace("{repo}", repo)
+            .replace("{version}", version)
+            .replace("{package}", package)
+    }
+}
+
+impl Default for ReleaseSource {
+    fn default() -> Self {
+        Self {
+            template: DEFAULT_RELEASE_URL_TEMPLATE.to_string(),
+            retries: DEFAULT_DOWNLOAD_RETRIES,
+        }
+    }
+}
 
 /// downloads `package` into `destination`, returning the sha256sum hex-digest of the downloaded file
-pub async fn download_github_release(
-    destination: &File,
+///
+/// If `destination` already exists, it is assumed to be a previous, partial
+/// download of the same package, which is resumed. Pressing Ctrl-C cancels
+/// the download, but keeps `destination` around so it can be resumed later.
+pub async fn download_release(
+    destination: &Path,
+    source: &ReleaseSource,
     org: &str,
     repo: &str,
     version: &str,
     package: &str,
     multi_progress: &MultiProgress,
 ) -> Result<String> {
-    let mut hasher = Sha256::new();
-    let mut response = CLIENT
-        .get(format!(
-            "https://github.com/{org}/{repo}/releases/download/v{version}/{package}"
-        ))
-        .send()
-        .await?
-        .error_for_status()?;
-
-    let total_size = response
-        .content_length()
-        .ok_or_else(|| anyhow!("didn't receive content length"))?;
-    let mut downloaded = 0;
+    let url = source.url(org, repo, version, package);
 
-    let pb = multi_progress.add(ProgressBar::new(total_size));
+    let host = reqwest::Url::parse(&url)
+        .ok()
+        .and_then(|url| url.host_str().map(ToString::to_string))
+        .unwrap_or_default();
 
-    // https://github.com/console-rs/indicatif/blob/HEAD/examples/download.rs#L12
-    pb.set_style(
-        ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] {msg} [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})")?
-            .with_key("eta", |state: &ProgressState, w: &mut dyn fmt::Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
-            .progress_chars("=> ")
-    );
+    let options = DownloadOptions {
+        retries: source.retries,
+        initial_backoff: INITIAL_BACKOFF,
+    };
 
-    pb.set_message(format!(
-        "Downloading {package} from github.com/{org}/{repo}"
-    ));
+    let ctrl_c = async {
+        // If we are unable to listen for Ctrl-C, the download simply can't be
+        // cancelled.
+        if tokio::signal::ctrl_c().await.is_err() {
+            future::pending::<()>().await;
+        }
+    };
 
-    let mut buffer = BufWriter::new(destination);
+    download(
+        &url,
+        destination,
+        format!("Downloading {package} from {host}"),
+        &options,
+        multi_progress,
+        ctrl_c,
+    )
+    .await
+}
 
-    while let Some(ref chunk) = response.chunk().await? {
-        buffer.write_all(chunk)?;
-        hasher.update(chunk);
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadOptions {
+    /// The amount of times a failed request is retried.
+    pub retries: u32,
+    /// The time to wait before the first retry. It is doubled for every retry.
+    pub initial_backoff: Duration,
+}
 
-        let new_size = (downloaded + chunk.len() as u64).min(total_size);
-        downloaded = new_size;
+/// Download `url` into `destination`, returning the sha256sum hex-digest of
+/// the downloaded file.
+///
+/// Data that is already in `destination` is kept, and only the remainder is
+/// requested using a `Range` header. This is used both to retry failed
+/// requests, and to resume downloads that were interrupted earlier. The
+/// download stops as soon as `cancel` completes.
+pub async fn download(
+    url: &str,
+    destination: &Path,
+    message: String,
+    options: &DownloadOptions,
+    multi_progress: &MultiProgress,
+    cancel: impl Future<Output = ()>,
+) -> Result<String> {
+    let pb = multi_progress.add(ProgressBar::new_spinner());
+    pb.set_message(message);
 
-        pb.set_position(downloaded);
-    }
+    let result = select! {
+        result = download_with_retries(url, destination, options, &pb) => result,
+        _ = cancel => Err(anyhow!("download of {url} was cancelled, it will be resumed next time")),
+    };
 
     pb.finish_and_clear();
     multi_progress.remove(&pb);
+    result?;
+
+    Ok(file_checksum(destination)?)
+}
+
+async fn download_with_retries(
+    url: &str,
+    destination: &Path,
+    options: &DownloadOptions,
+    pb: &ProgressBar,
+) -> Result<()> {
+    let mut attempt = 0;
 
-    let checksum = hex::encode(hasher.finalize());
-    Ok(checksum)
+    loop {
+        match download_attempt(url, destination, pb).await {
+            Ok(()) => return Ok(()),
+            Err(err) if err.is_retryable() && attempt < options.retries => {
+                let backoff = options.initial_backoff * 2u32.pow(attempt);
+                attempt += 1;
+
+                warn!(
+                    "Download of {url} failed ({err}), retrying in {} ({attempt}/{})",
+                    humantime::format_duration(backoff),
+                    options.retries
+                );
+                tokio::time::sleep(backoff).await;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+async fn download_attempt(
+    url: &str,
+    destination: &Path,
+    pb: &ProgressBar,
+) -> Result<(), DownloadError> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(destination)?;
+    let mut offset = file.metadata()?.len();
+
+    let mut response = request(url, offset).await?;
+
+    if offset > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // The server responds with the total size of the file, which tells us
+        // whether the previous download was already complete.
+        if total_size(&response) == Some(offset) {
+            debug!("{} is already downloaded", destination.display());
+            return Ok(());
+        }
+
+        offset = 0;
+        response = request(url, offset).await?;
+    }
+
+    let mut response = response.error_for_status()?;
+
+    // The server might not support range requests, in which case we start
+    // over.
+    if response.status() != StatusCode::PARTIAL_CONTENT && offset > 0 {
+        debug!("Server does not support resuming downloads, starting over");
+        offset = 0;
+    }
+
+    if offset == 0 {
+        file.set_len(0)?;
+    } else {
+        debug!("Resuming download of {url} at byte {offset}");
+    }
+
+    match response.content_length() {
+        Some(length) => {
+            // https://github.com/console-rs/indicatif/blob/HEAD/examples/download.rs#L12
+            pb.set_style(
+                ProgressStyle::with_template("{spinner:.green} [{elapsed_precise}] {msg} [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+                    .expect("valid template")
+                    .with_key("eta", |state: &ProgressState, w: &mut dyn fmt::Write| write!(w, "{:.1}s", state.eta().as_secs_f64()).unwrap())
+                    .progress_chars("=> ")
+            );
+            pb.set_length(offset + length);
+        }
+        None => {
+            // Without a content length, we are unable to show the progress.
+            pb.set_style(
+                ProgressStyle::with_template(
+                    "{spinner:.green} [{elapsed_precise}] {msg} {bytes} ({bytes_per_sec})",
+                )
+                .expect("valid template"),
+            );
+            pb.enable_steady_tick(Duration::from_millis(120));
+        }
+    }
+    pb.set_position(offset);
+
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk)?;
+        pb.inc(chunk.len() as u64);
+    }
+
+    Ok(())
+}
+
+async fn request(url: &str, offset: u64) -> Result<reqwest::Response, reqwest::Error> {
+    let mut request = CLIENT.get(url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={offset}-"));
+    }
+
+    request.send().await
+}
+
+/// The total size from a `Content-Range: bytes */<size>` header.
+fn total_size(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes */")?
+        .parse()
+        .ok()
+}
+
+#[derive(Debug, Error)]
+enum DownloadError {
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl DownloadError {
+    /// Whether the request might succeed if it is retried. Failing to write
+    /// the file, or client errors such as a 404, are not retried.
+    fn is_retryable(&self) -> bool {
+        match self {
+            DownloadError::Request(err) => match err.status() {
+                Some(status) => {
+                    status.is_server_error()
+                        || status == StatusCode::REQUEST_TIMEOUT
+                        || status == StatusCode::TOO_MANY_REQUESTS
+                }
+                None => true,
+            },
+            DownloadError::Io(_) => false,
+        }
+    }
 }
 
-pub async fn verify_checksum(
-    sha256sum: &str,
+/// Calculate the sha256sum hex-digest of the file at `path`.
+pub fn file_checksum(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Download the `sha256sums.txt` file of the specified release.
+pub async fn download_checksums(
+    source: &ReleaseSource,
     org: &str,
     repo: &str,
     version: &str,
-    package: &str,
-) -> Result<()> {
+) -> Result<String> {
     let checksums = CLIENT
-        .get(format!(
-            "https://github.com/{org}/{repo}/releases/download/v{version}/sha256sums.txt"
-        ))
+        .get(source.url(org, repo, version, "sha256sums.txt"))
         .send()
         .await?
         .error_for_status()?
         .text()
         .await?;
 
+    Ok(checksums)
+}
+
+/// Verify `sha256sum` against the checksum of `package` in `checksums`, which
+/// uses the format of `sha256sum`.
+pub fn verify_checksum(sha256sum: &str, checksums: &str, package: &str) -> Result<()> {
     // Go through all the lines in the checksum file and look for the one that
     // we need for our current service/version/os/arch.
     let expected_checksum = checksums
@@ -125,6 +376,12 @@ pub async fn unpack(
 
         debug!("Unpacking {}", path.display());
 
+        // Archives can be shared between users, so make sure that none of
+        // the entries end up outside of the destination.
+        if path.components().any(|c| matches!(c, Component::ParentDir)) {
+            bail!("{package} contains an invalid path: {}", path.display());
+        }
+
         // Remove the prefix and join it with the base directory.
         let path = path.strip_prefix(prefix)?.to_owned();
         let path = destination_path.join(path);
@@ -136,3 +393,250 @@ pub async fn unpack(
     multi_progress.remove(&pb);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{Bytes, StreamBody};
+    use axum::extract::State;
+    use axum::http::{HeaderMap, HeaderValue};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::get;
+    use axum::Router;
+    use futures_util::{stream, StreamExt};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    const BODY: &[u8] = b"prometheus-2.47.2.linux-amd64/prometheus and a lot more bytes";
+
+    /// The `Range` headers of all requests that were received.
+    type Requests = Arc<Mutex<Vec<Option<String>>>>;
+
+    /// Start a stand-in for a release server, which responds to requests for
+    /// `/archive` using `handler`.
+    async fn serve<H, T>(handler: H, requests: Requests) -> String
+    where
+        H: axum::handler::Handler<T, Requests>,
+        T: 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/archive", get(handler))
+            .with_state(requests);
+
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        format!("http://{address}/archive")
+    }
+
+    fn record(requests: &Requests, headers: &HeaderMap) -> usize {
+        let range = headers
+            .get(RANGE)
+            .map(|range| range.to_str().unwrap().to_string());
+
+        let mut requests = requests.lock().unwrap();
+        requests.push(range);
+        requests.len()
+    }
+
+    fn options() -> DownloadOptions {
+        DownloadOptions {
+            retries: 2,
+            initial_backoff: Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn interrupted_download_is_resumed() {
+        async fn handler(requests: State<Requests>, headers: HeaderMap) -> Response {
+            if record(&requests, &headers) == 1 {
+                // Announce the full body, but drop the connection halfway.
+                let first = stream::once(async { Ok(Bytes::from_static(&BODY[..20])) });
+                let reset = stream::once(async {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+                });
+
+                let mut response = StreamBody::new(first.chain(reset)).into_response();
+                response
+                    .headers_mut()
+                    .insert(http::header::CONTENT_LENGTH, HeaderValue::from(BODY.len()));
+                return response;
+            }
+
+            let start: usize = headers
+                .get(RANGE)
+                .and_then(|range| {
+                    range
+                        .to_str()
+                        .ok()?
+                        .strip_prefix("bytes=")?
+                        .strip_suffix('-')
+                })
+                .and_then(|start| start.parse().ok())
+                .unwrap_or(0);
+            (StatusCode::PARTIAL_CONTENT, &BODY[start..]).into_response()
+        }
+
+        let requests = Requests::default();
+        let url = serve(handler, requests.clone()).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("archive.tar.gz.part");
+
+        let checksum = download(
+            &url,
+            &destination,
+            "Downloading archive".to_string(),
+            &options(),
+            &MultiProgress::new(),
+            future::pending(),
+        )
+        .await
+        .expect("expected download to be resumed");
+
+        assert_eq!(std::fs::read(&destination).unwrap(), BODY);
+        assert_eq!(checksum, hex::encode(Sha256::digest(BODY)));
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![None, Some("bytes=20-".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried_without_content_length() {
+        async fn handler(requests: State<Requests>, headers: HeaderMap) -> Response {
+            if record(&requests, &headers) == 1 {
+                return StatusCode::SERVICE_UNAVAILABLE.into_response();
+            }
+
+            // A streaming body is sent using chunked encoding, so without a
+            // content length.
+            let chunks: Vec<Result<Bytes, io::Error>> = BODY
+                .chunks(16)
+                .map(|chunk| Ok(Bytes::from_static(chunk)))
+                .collect();
+            StreamBody::new(stream::iter(chunks)).into_response()
+        }
+
+        let requests = Requests::default();
+        let url = serve(handler, requests.clone()).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("archive.tar.gz.part");
+
+        download(
+            &url,
+            &destination,
+            "Downloading archive".to_string(),
+            &options(),
+            &MultiProgress::new(),
+            future::pending(),
+        )
+        .await
+        .expect("expected download to be retried");
+
+        assert_eq!(std::fs::read(&destination).unwrap(), BODY);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn client_errors_are_not_retried() {
+        async fn handler(requests: State<Requests>, headers: HeaderMap) -> StatusCode {
+            record(&requests, &headers);
+            StatusCode::NOT_FOUND
+        }
+
+        let requests = Requests::default();
+        let url = serve(handler, requests.clone()).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        download(
+            &url,
+            &dir.path().join("archive.tar.gz.part"),
+            "Downloading archive".to_string(),
+            &options(),
+            &MultiProgress::new(),
+            future::pending(),
+        )
+        .await
+        .expect_err("expected download to fail");
+
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn cancelled_download_keeps_partial_file() {
+        async fn handler(requests: State<Requests>, headers: HeaderMap) -> Response {
+            record(&requests, &headers);
+
+            // Send the first chunk, and then nothing at all.
+            let first = stream::iter([Ok::<_, io::Error>(Bytes::from_static(&BODY[..20]))]);
+            StreamBody::new(first.chain(stream::pending())).into_response()
+        }
+
+        let requests = Requests::default();
+        let url = serve(handler, requests.clone()).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("archive.tar.gz.part");
+
+        download(
+            &url,
+            &destination,
+            "Downloading archive".to_string(),
+            &options(),
+            &MultiProgress::new(),
+            tokio::time::sleep(Duration::from_millis(200)),
+        )
+        .await
+        .expect_err("expected download to be cancelled");
+
+        assert_eq!(std::fs::read(&destination).unwrap(), &BODY[..20]);
+    }
+
+    #[test]
+    fn release_url_from_template() {
+        let source = ReleaseSource::default();
+        assert_eq!(
+            source.url("prometheus", "pushgateway", "1.6.2", "sha256sums.txt"),
+            "https://github.com/prometheus/pushgateway/releases/download/v1.6.2/sha256sums.txt"
+        );
+
+        let source =
+            ReleaseSource::new("https://artifactory.example.com/github/{repo}/{version}/{package}")
+                .unwrap();
+        assert_eq!(
+            source.url("prometheus", "prometheus", "2.47.2", "prometheus.tar.gz"),
+            "https://artifactory.example.com/github/prometheus/2.47.2/prometheus.tar.gz"
+        );
+
+        assert!(ReleaseSource::new("https://mirror.example.com/").is_err());
+    }
+
+    #[test]
+    fn checksum_is_verified() {
+        let checksums = "\
+0c58c8ab6ef6e2ae9ff6f4de1d4cdb1c4a6ce0be2a4a9ea9d6bcf1d1e59cca26  prometheus-2.47.2.darwin-amd64.tar.gz
+1b7a2e86b9a04e1b8e3ac4b5e4c8a5a0a4ed7f2a1c0b6e6a3f7d5c2b1a0f9e8d  prometheus-2.47.2.linux-amd64.tar.gz
+";
+
+        verify_checksum(
+            "1b7a2e86b9a04e1b8e3ac4b5e4c8a5a0a4ed7f2a1c0b6e6a3f7d5c2b1a0f9e8d",
+            checksums,
+            "prometheus-2.47.2.linux-amd64.tar.gz",
+        )
+        .expect("expected checksum to match");
+
+        verify_checksum("0000", checksums, "prometheus-2.47.2.linux-amd64.tar.gz")
+            .expect_err("expected checksum to not match");
+
+        verify_checksum("0000", checksums, "prometheus-2.47.2.windows-amd64.tar.gz")
+            .expect_err("expected missing package to be an error");
+    }
+}
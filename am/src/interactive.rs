@@ -2,8 +2,20 @@ use dialoguer::theme::SimpleTheme;
 use dialoguer::{Confirm, Input};
 use indicatif::MultiProgress;
 use std::io::{stderr, IoSlice, Result, Write};
+use std::sync::{Arc, Mutex};
 use tracing_subscriber::fmt::MakeWriter;
 
+/// While this is set, the output of the [`IndicatifWriter`] is written to this
+/// sink instead of to stderr. This is used while the TUI is shown, which would
+/// otherwise be overwritten by the log messages.
+static CAPTURED_OUTPUT: Mutex<Option<Arc<dyn OutputSink>>> = Mutex::new(None);
+
+/// Receives the log output while it is captured using [`capture_output`].
+pub(crate) trait OutputSink: Send + Sync {
+    /// Store a single line of output, with its color codes removed.
+    fn write_line(&self, line: String);
+}
+
 pub fn user_input(prompt: impl Into<String>) -> dialoguer::Result<String> {
     Input::with_theme(&SimpleTheme)
         .with_prompt(prompt)
@@ -57,8 +69,69 @@ impl IndicatifWriter {
     }
 }
 
+/// Capture the log output in `sink` until the returned guard is dropped.
+pub(crate) fn capture_output(sink: Arc<dyn OutputSink>) -> OutputCapture {
+    *CAPTURED_OUTPUT.lock().unwrap() = Some(sink);
+    OutputCapture { _private: () }
+}
+
+/// Whether the log output is currently being captured, in which case nothing
+/// should be written to the terminal directly either.
+pub(crate) fn is_output_captured() -> bool {
+    CAPTURED_OUTPUT.lock().unwrap().is_some()
+}
+
+/// Writes the log output to stderr again once dropped.
+pub(crate) struct OutputCapture {
+    _private: (),
+}
+
+impl Drop for OutputCapture {
+    fn drop(&mut self) {
+        *CAPTURED_OUTPUT.lock().unwrap() = None;
+    }
+}
+
+/// Write `buf` to the capture sink, if the output is being captured. Log
+/// messages can contain color codes, which are removed.
+fn try_capture(buf: &[u8]) -> bool {
+    let Some(sink) = CAPTURED_OUTPUT.lock().unwrap().clone() else {
+        return false;
+    };
+
+    for line in String::from_utf8_lossy(buf).lines() {
+        sink.write_line(strip_ansi_codes(line));
+    }
+
+    true
+}
+
+fn strip_ansi_codes(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip the escape sequence up until and including its final byte.
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
 impl Write for IndicatifWriter {
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if try_capture(buf) {
+            return Ok(buf.len());
+        }
+
         self.multi_progress.suspend(|| stderr().write(buf))
     }
 
@@ -67,15 +140,30 @@ impl Write for IndicatifWriter {
     }
 
     fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+        if is_output_captured() {
+            let buf: Vec<u8> = bufs.iter().flat_map(|buf| buf.iter().copied()).collect();
+            try_capture(&buf);
+            return Ok(buf.len());
+        }
+
         self.multi_progress
             .suspend(|| stderr().write_vectored(bufs))
     }
 
     fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        if try_capture(buf) {
+            return Ok(());
+        }
+
         self.multi_progress.suspend(|| stderr().write_all(buf))
     }
 
     fn write_fmt(&mut self, fmt: std::fmt::Arguments<'_>) -> Result<()> {
+        if is_output_captured() {
+            try_capture(fmt.to_string().as_bytes());
+            return Ok(());
+        }
+
         self.multi_progress.suspend(|| stderr().write_fmt(fmt))
     }
 }
@@ -16,12 +16,16 @@ use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::{EnvFilter, Layer, Registry};
 
 mod commands;
+mod components;
 mod dir;
 mod downloader;
 mod interactive;
 mod server;
 mod terminal;
 
+/// The config file that is used if none was specified using `--config-file`.
+const DEFAULT_CONFIG_FILE: &str = "./am.toml";
+
 #[tokio::main]
 async fn main() {
     let app = Application::parse();
@@ -123,7 +127,7 @@ fn init_logging(app: &Application, writer: IndicatifWriter) -> Result<()> {
 async fn load_config(config_file: Option<PathBuf>) -> Result<AmConfig> {
     let (path, is_default) = match config_file {
         Some(path) => (path, false),
-        None => (PathBuf::from("./am.toml"), true),
+        None => (PathBuf::from(DEFAULT_CONFIG_FILE), true),
     };
 
     debug!(?path, "Loading config");
@@ -2,7 +2,7 @@ use anyhow::{Context, Result};
 use autometrics::prometheus_exporter;
 use axum::body::Body;
 use axum::response::Redirect;
-use axum::routing::{any, get};
+use axum::routing::{any, delete, get, post};
 use axum::{Router, Server};
 use http::header::CONNECTION;
 use std::collections::HashMap;
@@ -12,23 +12,87 @@ use tokio::sync::watch::Sender;
 use tracing::debug;
 use url::Url;
 
+use crate::commands::start::alerts::SharedAlertHistory;
+use crate::commands::start::logs::LogBuffers;
+use crate::commands::start::reload::SharedConfigManager;
+use crate::commands::start::snapshot::SharedSnapshotSource;
+use crate::commands::start::targets::SharedTargetMonitor;
 use crate::server::util::proxy_handler;
 
+mod alertmanager;
+mod alerts;
+mod endpoints;
 mod explorer;
 mod functions;
+mod logs;
 mod prometheus;
 mod pushgateway;
+mod snapshots;
+mod targets;
 mod util;
 
+/// Options that determine which routes the web server of am exposes.
+pub(crate) struct WebServerOptions {
+    pub(crate) listen_address: SocketAddr,
+    pub(crate) enable_prometheus: bool,
+    pub(crate) enable_pushgateway: bool,
+    pub(crate) enable_alertmanager: bool,
+
+    /// The ports the local Prometheus, Pushgateway and Alertmanager instances
+    /// listen on.
+    pub(crate) prometheus_port: u16,
+    pub(crate) pushgateway_port: u16,
+    pub(crate) alertmanager_port: u16,
+
+    pub(crate) prometheus_proxy_url: Option<Url>,
+    pub(crate) static_assets_url: Url,
+
+    /// Allows managing the endpoints of the local Prometheus instance through
+    /// the `/api/endpoints` API.
+    pub(crate) config_manager: Option<SharedConfigManager>,
+
+    /// Exposes the output of the child processes through the `/api/logs` API.
+    pub(crate) log_buffers: Option<LogBuffers>,
+
+    /// Receives the alerts of Alertmanager on `/api/alerts/webhook` and
+    /// exposes them through the `/api/alerts` API.
+    pub(crate) alert_history: Option<SharedAlertHistory>,
+
+    /// Creates snapshots of the data of the local Prometheus instance through
+    /// the `/api/snapshots` API.
+    pub(crate) snapshot_source: Option<SharedSnapshotSource>,
+
+    /// Exposes the health of the scrape targets of the local Prometheus
+    /// instance through the `/api/targets` API.
+    pub(crate) target_monitor: Option<SharedTargetMonitor>,
+}
+
 pub(crate) async fn start_web_server(
-    listen_address: &SocketAddr,
-    enable_prometheus: bool,
-    enable_pushgateway: bool,
-    prometheus_proxy_url: Option<Url>,
-    static_assets_url: Url,
+    options: WebServerOptions,
     tx: Sender<Option<SocketAddr>>,
     tx_url: Sender<HashMap<&'static str, String>>,
 ) -> Result<()> {
+    let WebServerOptions {
+        listen_address,
+        enable_prometheus,
+        enable_pushgateway,
+        enable_alertmanager,
+        prometheus_port,
+        pushgateway_port,
+        alertmanager_port,
+        prometheus_proxy_url,
+        static_assets_url,
+        config_manager,
+        log_buffers,
+        alert_history,
+        snapshot_source,
+        target_monitor,
+    } = options;
+
+    let prometheus_url = Url::parse(&format!("http://localhost:{prometheus_port}"))?;
+    let pushgateway_url = Url::parse(&format!("http://localhost:{pushgateway_port}"))?;
+    let alertmanager_url = Url::parse(&format!("http://localhost:{alertmanager_port}"))?;
+
     let is_proxying_prometheus = prometheus_proxy_url.is_some();
     let should_enable_prometheus = enable_prometheus && !is_proxying_prometheus;
 
@@ -68,11 +132,70 @@ pub(crate) async fn start_web_server(
             get(|| async { prometheus_exporter::encode_http_response() }),
         );
 
+    // Allow managing the scrape endpoints of the local Prometheus instance
+    if let Some(config_manager) = config_manager {
+        app = app
+            .route(
+                "/api/endpoints",
+                get(endpoints::list_endpoints)
+                    .post(endpoints::add_endpoint)
+                    .with_state(config_manager.clone()),
+            )
+            .route(
+                "/api/endpoints/:job_name",
+                delete(endpoints::remove_endpoint).with_state(config_manager),
+            );
+    }
+
+    if let Some(log_buffers) = log_buffers {
+        app = app.route(
+            "/api/logs/:process",
+            get(logs::handler).with_state(log_buffers),
+        );
+    }
+
+    if let Some(alert_history) = alert_history {
+        app = app
+            .route(
+                "/api/alerts",
+                get(alerts::list_alerts).with_state(alert_history.clone()),
+            )
+            .route(
+                "/api/alerts/webhook",
+                post(alerts::webhook).with_state(alert_history),
+            );
+    }
+
+    if let Some(snapshot_source) = snapshot_source {
+        app = app
+            .route(
+                "/api/snapshots",
+                post(snapshots::create_snapshot).with_state(snapshot_source.clone()),
+            )
+            .route(
+                "/api/snapshots/:name",
+                get(snapshots::download_snapshot).with_state(snapshot_source),
+            );
+    }
+
+    if let Some(target_monitor) = target_monitor {
+        app = app.route(
+            "/api/targets",
+            get(targets::list_targets).with_state(target_monitor),
+        );
+    }
+
     // Proxy `/prometheus` to the upstream (local) prometheus instance
     if should_enable_prometheus {
         app = app
-            .route("/prometheus/*path", any(prometheus::handler))
-            .route("/prometheus", any(prometheus::handler));
+            .route(
+                "/prometheus/*path",
+                any(prometheus::handler).with_state(prometheus_url.clone()),
+            )
+            .route(
+                "/prometheus",
+                any(prometheus::handler).with_state(prometheus_url),
+            );
     }
 
     // NOTE - this will override local prometheus routes if specified
@@ -114,12 +237,33 @@ pub(crate) async fn start_web_server(
 
     if enable_pushgateway {
         app = app
-            .route("/metrics", any(pushgateway::metrics_proxy_handler))
-            .route("/pushgateway/*path", any(pushgateway::handler))
-            .route("/pushgateway", any(pushgateway::handler));
+            .route(
+                "/metrics",
+                any(pushgateway::metrics_proxy_handler).with_state(pushgateway_url.clone()),
+            )
+            .route(
+                "/pushgateway/*path",
+                any(pushgateway::handler).with_state(pushgateway_url.clone()),
+            )
+            .route(
+                "/pushgateway",
+                any(pushgateway::handler).with_state(pushgateway_url),
+            );
     }
 
-    let server = Server::try_bind(listen_address)
+    if enable_alertmanager {
+        app = app
+            .route(
+                "/alertmanager/*path",
+                any(alertmanager::handler).with_state(alertmanager_url.clone()),
+            )
+            .route(
+                "/alertmanager",
+                any(alertmanager::handler).with_state(alertmanager_url),
+            );
+    }
+
+    let server = Server::try_bind(&listen_address)
         .with_context(|| format!("failed to bind to {}", listen_address))?
         .serve(app.into_make_service());
 
@@ -130,7 +274,10 @@ pub(crate) async fn start_web_server(
     let mut urls = HashMap::from([("Explorer", format!("http://{}", server.local_addr()))]);
 
     if should_enable_prometheus {
-        urls.insert("Prometheus", "http://127.0.0.1:9090/prometheus".to_string());
+        urls.insert(
+            "Prometheus",
+            format!("http://127.0.0.1:{prometheus_port}/prometheus"),
+        );
     }
 
     if is_proxying_prometheus {
@@ -143,7 +290,14 @@ pub(crate) async fn start_web_server(
     if enable_pushgateway {
         urls.insert(
             "Pushgateway",
-            "http://127.0.0.1:9091/pushgateway".to_string(),
+            format!("http://127.0.0.1:{pushgateway_port}/pushgateway"),
+        );
+    }
+
+    if enable_alertmanager {
+        urls.insert(
+            "Alertmanager",
+            format!("http://127.0.0.1:{alertmanager_port}/alertmanager"),
         );
     }
 
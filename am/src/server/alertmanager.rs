@@ -0,0 +1,15 @@
+use crate::server::util::proxy_handler;
+use autometrics::autometrics;
+use axum::body::Body;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use url::Url;
+
+/// Proxy the request to the local Alertmanager instance at `upstream_base`.
+#[autometrics]
+pub(crate) async fn handler(
+    upstream_base: State<Url>,
+    req: http::Request<Body>,
+) -> impl IntoResponse {
+    proxy_handler(req, upstream_base.0).await
+}
@@ -0,0 +1,91 @@
+use crate::commands::start::alerts::SharedAlertHistory;
+use crate::terminal;
+use autometrics::autometrics;
+use autometrics_am::alertmanager::WebhookPayload;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::Json;
+use http::StatusCode;
+use tracing::warn;
+
+/// Receive alert notifications from Alertmanager. Every alert is printed to
+/// the terminal and added to the history.
+#[autometrics]
+pub(crate) async fn webhook(
+    alert_history: State<SharedAlertHistory>,
+    payload: Json<WebhookPayload>,
+) -> impl IntoResponse {
+    for alert in payload.0.alerts {
+        if let Err(err) = terminal::print_alert(&alert) {
+            warn!(?err, "Unable to print alert");
+        }
+
+        alert_history.push(alert);
+    }
+
+    StatusCode::OK
+}
+
+/// List the alert notifications that were received from Alertmanager, oldest
+/// first.
+#[autometrics]
+pub(crate) async fn list_alerts(alert_history: State<SharedAlertHistory>) -> impl IntoResponse {
+    Json(alert_history.alerts())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::start::alerts::AlertHistory;
+    use autometrics_am::alertmanager::AlertStatus;
+    use std::sync::Arc;
+
+    const PAYLOAD: &str = r#"{
+        "version": "4",
+        "groupKey": "{}:{alertname=\"HighErrorRate\"}",
+        "truncatedAlerts": 0,
+        "status": "resolved",
+        "receiver": "am",
+        "groupLabels": { "alertname": "HighErrorRate" },
+        "commonLabels": { "alertname": "HighErrorRate", "objective_name": "api" },
+        "commonAnnotations": {},
+        "externalURL": "http://localhost:6789/alertmanager",
+        "alerts": [
+            {
+                "status": "firing",
+                "labels": { "alertname": "HighErrorRate", "objective_name": "api", "severity": "page" },
+                "annotations": { "summary": "High error rate for the `api` objective" },
+                "startsAt": "2023-11-02T10:15:30.000Z",
+                "endsAt": "0001-01-01T00:00:00Z",
+                "generatorURL": "http://localhost:6789/prometheus/graph",
+                "fingerprint": "5a1b2c3d4e5f6a7b"
+            },
+            {
+                "status": "resolved",
+                "labels": { "alertname": "HighLatency", "objective_name": "api" },
+                "annotations": {},
+                "startsAt": "2023-11-02T10:00:00.000Z",
+                "endsAt": "2023-11-02T10:12:00.000Z",
+                "generatorURL": "http://localhost:6789/prometheus/graph",
+                "fingerprint": "8c9d0e1f2a3b4c5d"
+            }
+        ]
+    }"#;
+
+    #[tokio::test]
+    async fn received_alerts_are_kept_in_history() {
+        let alert_history = Arc::new(AlertHistory::new(1));
+        let payload: WebhookPayload = serde_json::from_str(PAYLOAD).unwrap();
+
+        let response = webhook(State(alert_history.clone()), Json(payload))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Only the most recent alert is kept, since the capacity is 1.
+        let alerts = alert_history.alerts();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].alert.name(), "HighLatency");
+        assert_eq!(alerts[0].alert.status, AlertStatus::Resolved);
+    }
+}
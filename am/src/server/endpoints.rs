@@ -0,0 +1,309 @@
+use crate::commands::start::reload::SharedConfigManager;
+use crate::commands::start::Endpoint;
+use autometrics::autometrics;
+use axum::extract::{Path, State};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::info;
+use url::Url;
+
+/// Where an endpoint in the Prometheus configuration originates from.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum EndpointSource {
+    /// Provided through the CLI or the am.toml file.
+    Config,
+    /// Added through the `/api/endpoints` API.
+    Runtime,
+    /// Added by am itself, such as the Pushgateway.
+    Internal,
+    /// Discovered from the running Docker containers.
+    Discovered,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct EndpointInfo {
+    job_name: String,
+    url: Url,
+    honor_labels: bool,
+    scrape_interval: Option<String>,
+    source: EndpointSource,
+}
+
+impl EndpointInfo {
+    fn new(endpoint: &Endpoint, source: EndpointSource) -> Self {
+        Self {
+            job_name: endpoint.job_name.clone(),
+            url: endpoint.url.clone(),
+            honor_labels: endpoint.honor_labels,
+            scrape_interval: endpoint
+                .scrape_interval
+                .map(|interval| humantime::format_duration(interval).to_string()),
+            source,
+        }
+    }
+}
+
+/// List all the endpoints that are currently scraped by Prometheus.
+#[autometrics]
+pub(crate) async fn list_endpoints(
+    config_manager: State<SharedConfigManager>,
+) -> impl IntoResponse {
+    let config_manager = config_manager.lock().await;
+    let settings = config_manager.settings();
+
+    let endpoints: Vec<EndpointInfo> = settings
+        .endpoints
+        .iter()
+        .map(|endpoint| EndpointInfo::new(endpoint, EndpointSource::Config))
+        .chain(
+            settings
+                .runtime_endpoints
+                .iter()
+                .map(|endpoint| EndpointInfo::new(endpoint, EndpointSource::Runtime)),
+        )
+        .chain(
+            settings
+                .internal_endpoints
+                .iter()
+                .map(|endpoint| EndpointInfo::new(endpoint, EndpointSource::Internal)),
+        )
+        .chain(
+            settings
+                .discovered_endpoints
+                .iter()
+                .map(|endpoint| EndpointInfo::new(endpoint, EndpointSource::Discovered)),
+        )
+        .collect();
+
+    Json(endpoints)
+}
+
+/// Add a new endpoint to the Prometheus configuration. The request body uses
+/// the same format as an `[[endpoint]]` entry in the am.toml file, except that
+/// the `job-name` is required.
+#[autometrics]
+pub(crate) async fn add_endpoint(
+    config_manager: State<SharedConfigManager>,
+    endpoint: Json<autometrics_am::config::Endpoint>,
+) -> Result<impl IntoResponse, EndpointError> {
+    if endpoint.job_name.is_none() {
+        return Err(EndpointError::MissingJobName);
+    }
+
+    let endpoint: Endpoint = endpoint
+        .0
+        .try_into()
+        .map_err(|err| EndpointError::Invalid(format!("{err:#}")))?;
+
+    let mut config_manager = config_manager.lock().await;
+
+    if config_manager.settings().has_job(&endpoint.job_name) {
+        return Err(EndpointError::DuplicateJobName(endpoint.job_name));
+    }
+
+    let endpoint_info = EndpointInfo::new(&endpoint, EndpointSource::Runtime);
+
+    config_manager
+        .update(|settings| settings.runtime_endpoints.push(endpoint))
+        .await
+        .map_err(|err| EndpointError::ReloadFailed(format!("{err:?}")))?;
+
+    info!(
+        "Now sampling {} (job {})",
+        endpoint_info.url, endpoint_info.job_name
+    );
+
+    Ok((StatusCode::CREATED, Json(endpoint_info)))
+}
+
+/// Remove an endpoint that was previously added through the API.
+#[autometrics]
+pub(crate) async fn remove_endpoint(
+    config_manager: State<SharedConfigManager>,
+    job_name: Path<String>,
+) -> Result<impl IntoResponse, EndpointError> {
+    let job_name = job_name.0;
+    let mut config_manager = config_manager.lock().await;
+    let settings = config_manager.settings();
+
+    if !settings
+        .runtime_endpoints
+        .iter()
+        .any(|endpoint| endpoint.job_name == job_name)
+    {
+        return if settings
+            .all_endpoints()
+            .any(|endpoint| endpoint.job_name == job_name)
+        {
+            Err(EndpointError::NotRemovable(job_name))
+        } else {
+            Err(EndpointError::NotFound(job_name))
+        };
+    }
+
+    config_manager
+        .update(|settings| {
+            settings
+                .runtime_endpoints
+                .retain(|endpoint| endpoint.job_name != job_name)
+        })
+        .await
+        .map_err(|err| EndpointError::ReloadFailed(format!("{err:?}")))?;
+
+    info!("Stopped sampling job {job_name}");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize, Serialize, Debug, Error)]
+#[serde(tag = "error", content = "details", rename_all = "snake_case")]
+pub(crate) enum EndpointError {
+    #[error("a `job-name` is required when adding an endpoint")]
+    MissingJobName,
+
+    #[error("invalid endpoint: {0}")]
+    Invalid(String),
+
+    #[error("an endpoint with job name `{0}` already exists")]
+    DuplicateJobName(String),
+
+    #[error("no endpoint with job name `{0}` exists")]
+    NotFound(String),
+
+    #[error("endpoint `{0}` was not added through the API and cannot be removed")]
+    NotRemovable(String),
+
+    #[error("unable to reload Prometheus: {0}")]
+    ReloadFailed(String),
+}
+
+impl IntoResponse for EndpointError {
+    fn into_response(self) -> Response {
+        let status_code = match self {
+            EndpointError::MissingJobName | EndpointError::Invalid(_) => StatusCode::BAD_REQUEST,
+            EndpointError::DuplicateJobName(_) | EndpointError::NotRemovable(_) => {
+                StatusCode::CONFLICT
+            }
+            EndpointError::NotFound(_) => StatusCode::NOT_FOUND,
+            EndpointError::ReloadFailed(_) => StatusCode::BAD_GATEWAY,
+        };
+
+        (status_code, Json(self)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::start::reload::{PrometheusConfigManager, ScrapeSettings};
+    use axum::routing::post;
+    use axum::{Router, Server};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+
+    /// Create a config manager which reloads against a stand-in for
+    /// Prometheus' lifecycle API, which accepts every configuration.
+    fn config_manager(dir: &std::path::Path) -> SharedConfigManager {
+        let app = Router::new().route("/-/reload", post(|| async { StatusCode::OK }));
+        let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(app.into_make_service());
+        let reload_url = Url::parse(&format!("http://{}/-/reload", server.local_addr())).unwrap();
+        tokio::spawn(server);
+
+        let settings = ScrapeSettings {
+            endpoints: vec![Endpoint::new(
+                Url::parse("http://localhost:3000/metrics").unwrap(),
+                "am_0".to_string(),
+                false,
+                None,
+            )],
+            runtime_endpoints: vec![],
+            internal_endpoints: vec![],
+            discovered_endpoints: vec![],
+            file_sd_jobs: vec![],
+            scrape_interval: Duration::from_secs(5),
+            enable_rules: false,
+            rule_files: vec![],
+            alertmanager_port: None,
+        };
+
+        let manager = PrometheusConfigManager::new(settings, dir.to_path_buf(), reload_url);
+        Arc::new(Mutex::new(manager))
+    }
+
+    fn new_endpoint(job_name: Option<&str>) -> Json<autometrics_am::config::Endpoint> {
+        Json(autometrics_am::config::Endpoint {
+            url: Url::parse("http://localhost:3030/metrics").unwrap(),
+            additional_urls: vec![],
+            job_name: job_name.map(ToString::to_string),
+            honor_labels: None,
+            prometheus_scrape_interval: None,
+            scrape_options: Default::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn add_and_remove_endpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = config_manager(dir.path());
+
+        add_endpoint(State(manager.clone()), new_endpoint(Some("service")))
+            .await
+            .expect("expected endpoint to be added");
+
+        let config = std::fs::read_to_string(dir.path().join("prometheus.yml")).unwrap();
+        assert!(config.contains("job_name: service"));
+
+        let err = add_endpoint(State(manager.clone()), new_endpoint(Some("service")))
+            .await
+            .err()
+            .expect("expected duplicate job name to be rejected");
+        assert!(matches!(err, EndpointError::DuplicateJobName(_)));
+
+        let err = remove_endpoint(State(manager.clone()), Path("am_0".to_string()))
+            .await
+            .err()
+            .expect("expected config endpoint to not be removable");
+        assert!(matches!(err, EndpointError::NotRemovable(_)));
+
+        remove_endpoint(State(manager.clone()), Path("service".to_string()))
+            .await
+            .expect("expected endpoint to be removed");
+
+        assert!(manager.lock().await.settings().runtime_endpoints.is_empty());
+    }
+
+    #[tokio::test]
+    async fn add_endpoint_requires_job_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = config_manager(dir.path());
+
+        let err = add_endpoint(State(manager), new_endpoint(None))
+            .await
+            .err()
+            .expect("expected missing job name to be rejected");
+        assert!(matches!(err, EndpointError::MissingJobName));
+    }
+
+    #[tokio::test]
+    async fn add_endpoint_reports_invalid_endpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = config_manager(dir.path());
+
+        let mut endpoint = new_endpoint(Some("service"));
+        endpoint.additional_urls = vec![Url::parse("https://localhost:3031/metrics").unwrap()];
+
+        let err = add_endpoint(State(manager), endpoint)
+            .await
+            .err()
+            .expect("expected mixed schemes to be rejected");
+        match err {
+            EndpointError::Invalid(reason) => assert!(reason.contains("same scheme and path")),
+            err => panic!("unexpected error: {err:?}"),
+        }
+    }
+}
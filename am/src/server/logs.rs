@@ -0,0 +1,120 @@
+use crate::commands::start::logs::{LogBuffers, LogLine};
+use autometrics::autometrics;
+use axum::extract::{Path, Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use futures_util::stream::{self, Stream, StreamExt};
+use http::header::ACCEPT;
+use http::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+#[derive(Deserialize, Debug, Default)]
+pub(crate) struct LogsQuery {
+    /// Keep the connection open and stream new lines as server-sent events.
+    #[serde(default)]
+    follow: bool,
+}
+
+/// Get the most recent output of a child process. The output is streamed as
+/// server-sent events if `?follow=true` is passed or if the client accepts
+/// `text/event-stream`, otherwise the buffered lines are returned as JSON.
+#[autometrics]
+pub(crate) async fn handler(
+    log_buffers: State<LogBuffers>,
+    process: Path<String>,
+    query: Query<LogsQuery>,
+    headers: HeaderMap,
+) -> Result<Response, LogsError> {
+    let buffer = log_buffers
+        .get(process.as_str())
+        .ok_or_else(|| LogsError::UnknownProcess(process.0.clone()))?;
+
+    let accepts_event_stream = headers
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .map(|accept| accept.contains("text/event-stream"))
+        .unwrap_or_default();
+
+    if !query.follow && !accepts_event_stream {
+        return Ok(Json(buffer.lines()).into_response());
+    }
+
+    let (backlog, rx) = buffer.subscribe();
+    let events = stream::iter(backlog)
+        .chain(follow(rx))
+        .map(|line| Event::default().id(line.seq.to_string()).json_data(line));
+
+    Ok(Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+/// Turn the receiver into a stream of all new lines. Lines that were missed
+/// because the client was not able to keep up are skipped.
+fn follow(rx: broadcast::Receiver<LogLine>) -> impl Stream<Item = LogLine> {
+    stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(line) => return Some((line, rx)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+#[derive(Deserialize, Serialize, Debug, Error)]
+#[serde(tag = "error", content = "details", rename_all = "snake_case")]
+pub(crate) enum LogsError {
+    #[error("no logs are available for process `{0}`")]
+    UnknownProcess(String),
+}
+
+impl IntoResponse for LogsError {
+    fn into_response(self) -> Response {
+        let status_code = match self {
+            LogsError::UnknownProcess(_) => StatusCode::NOT_FOUND,
+        };
+
+        (status_code, Json(self)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::start::logs::{LogBuffer, LogStream};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn unknown_process_is_not_found() {
+        let buffer = Arc::new(LogBuffer::new(10));
+        buffer.push(LogStream::Stdout, "Server is ready".to_string());
+        let log_buffers: LogBuffers = HashMap::from([("prometheus", buffer)]);
+
+        let response = handler(
+            State(log_buffers.clone()),
+            Path("prometheus".to_string()),
+            Query(LogsQuery::default()),
+            HeaderMap::new(),
+        )
+        .await
+        .expect("expected logs to be returned");
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let err = handler(
+            State(log_buffers),
+            Path("alertmanager".to_string()),
+            Query(LogsQuery::default()),
+            HeaderMap::new(),
+        )
+        .await
+        .expect_err("expected unknown process to be rejected");
+        assert!(matches!(err, LogsError::UnknownProcess(_)));
+    }
+}
@@ -1,13 +1,28 @@
 use crate::server::util::proxy_handler;
 use autometrics::autometrics;
 use axum::body::Body;
-use axum::response::IntoResponse;
+use axum::extract::State;
+use axum::response::{IntoResponse, Response};
+use http::StatusCode;
+use percent_encoding::percent_decode_str;
 use url::Url;
 
+/// Proxy the request to the local Prometheus instance at `upstream_base`.
+///
+/// Requests to the admin API are rejected. It is enabled so that am can create
+/// snapshots, which it does by calling Prometheus directly, but it also allows
+/// anyone to delete data.
 #[autometrics]
-pub(crate) async fn handler(req: http::Request<Body>) -> impl IntoResponse {
-    let upstream_base = url::Url::parse("http://localhost:9090").unwrap();
-    proxy_handler(req, upstream_base).await
+pub(crate) async fn handler(upstream_base: State<Url>, req: http::Request<Body>) -> Response {
+    if is_admin_api(req.uri().path()) {
+        return (
+            StatusCode::FORBIDDEN,
+            "The Prometheus admin API is not available through am",
+        )
+            .into_response();
+    }
+
+    proxy_handler(req, upstream_base.0).await.into_response()
 }
 
 pub(crate) async fn handler_with_url(
@@ -16,3 +31,46 @@ pub(crate) async fn handler_with_url(
 ) -> impl IntoResponse {
     proxy_handler(req, upstream_base.clone()).await
 }
+
+/// Whether `path` refers to the admin API of Prometheus, in the same way that
+/// Prometheus resolves it: percent-decoded, with the dot segments and empty
+/// segments removed and ignoring case.
+fn is_admin_api(path: &str) -> bool {
+    let path = percent_decode_str(path).decode_utf8_lossy();
+    let mut segments = vec![];
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment.to_ascii_lowercase()),
+        }
+    }
+
+    segments
+        .iter()
+        .map(String::as_str)
+        .take(4)
+        .eq(["prometheus", "api", "v1", "admin"])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admin_api_is_detected() {
+        assert!(is_admin_api("/prometheus/api/v1/admin/tsdb/delete_series"));
+        assert!(is_admin_api(
+            "/prometheus//api/v1/Admin/tsdb/clean_tombstones"
+        ));
+        assert!(is_admin_api(
+            "/prometheus/api/v1/query/../%61dmin/tsdb/snapshot"
+        ));
+
+        assert!(!is_admin_api("/prometheus/api/v1/query"));
+        assert!(!is_admin_api("/prometheus/graph"));
+    }
+}
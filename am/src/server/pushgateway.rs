@@ -1,17 +1,24 @@
 use crate::server::util::proxy_handler;
 use autometrics::autometrics;
 use axum::body::Body;
+use axum::extract::State;
 use axum::response::IntoResponse;
 use url::Url;
 
+/// Proxy the request to the local Pushgateway instance at `upstream_base`.
 #[autometrics]
-pub(crate) async fn handler(req: http::Request<Body>) -> impl IntoResponse {
-    let upstream_base = Url::parse("http://localhost:9091").unwrap();
-    proxy_handler(req, upstream_base).await
+pub(crate) async fn handler(
+    upstream_base: State<Url>,
+    req: http::Request<Body>,
+) -> impl IntoResponse {
+    proxy_handler(req, upstream_base.0).await
 }
 
 #[autometrics]
-pub(crate) async fn metrics_proxy_handler(req: http::Request<Body>) -> impl IntoResponse {
-    let upstream_base = Url::parse("http://localhost:9091/pushgateway/metrics").unwrap();
+pub(crate) async fn metrics_proxy_handler(
+    upstream_base: State<Url>,
+    req: http::Request<Body>,
+) -> impl IntoResponse {
+    let upstream_base = upstream_base.join("/pushgateway/metrics").unwrap();
     proxy_handler(req, upstream_base).await
 }
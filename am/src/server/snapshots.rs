@@ -0,0 +1,142 @@
+use crate::commands::start::snapshot::SharedSnapshotSource;
+use autometrics::autometrics;
+use axum::body::{Bytes, StreamBody};
+use axum::extract::{Path, State};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use futures_util::stream;
+use http::header::{CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE};
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+use tracing::info;
+
+/// The size of the chunks in which an archive is sent.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Create a snapshot of the data of Prometheus. The archive containing the
+/// snapshot, config and rules can be downloaded from
+/// `/api/snapshots/:name` afterwards.
+#[autometrics]
+pub(crate) async fn create_snapshot(
+    snapshot_source: State<SharedSnapshotSource>,
+) -> Result<impl IntoResponse, SnapshotError> {
+    let snapshot = snapshot_source
+        .create()
+        .await
+        .map_err(|err| SnapshotError::CreateFailed(format!("{err:#}")))?;
+
+    info!("Created snapshot {}", snapshot.name);
+
+    Ok((StatusCode::CREATED, Json(snapshot)))
+}
+
+/// Download the archive of a snapshot that was created earlier.
+#[autometrics]
+pub(crate) async fn download_snapshot(
+    snapshot_source: State<SharedSnapshotSource>,
+    name: Path<String>,
+) -> Result<Response, SnapshotError> {
+    let path = snapshot_source
+        .archive_path(&name)
+        .ok_or_else(|| SnapshotError::NotFound(name.0.clone()))?;
+
+    let file = File::open(&path)
+        .await
+        .map_err(|_| SnapshotError::NotFound(name.0.clone()))?;
+    let size = file
+        .metadata()
+        .await
+        .map_err(|_| SnapshotError::NotFound(name.0.clone()))?
+        .len();
+
+    // Read the archive in chunks, so it does not have to fit in memory. The
+    // stream ends after the first error.
+    let chunks = stream::unfold(Some(file), |file| async move {
+        let mut file = file?;
+        let mut buffer = vec![0; CHUNK_SIZE];
+        match file.read(&mut buffer).await {
+            Ok(0) => None,
+            Ok(read) => {
+                buffer.truncate(read);
+                Some((Ok(Bytes::from(buffer)), Some(file)))
+            }
+            Err(err) => Some((Err(err), None)),
+        }
+    });
+
+    Ok((
+        [
+            (CONTENT_TYPE, "application/gzip".to_string()),
+            (CONTENT_LENGTH, size.to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!(r#"attachment; filename="am-snapshot-{}.tar.gz""#, name.0),
+            ),
+        ],
+        StreamBody::new(chunks),
+    )
+        .into_response())
+}
+
+#[derive(Deserialize, Serialize, Debug, Error)]
+#[serde(tag = "error", content = "details", rename_all = "snake_case")]
+pub(crate) enum SnapshotError {
+    #[error("unable to create snapshot: {0}")]
+    CreateFailed(String),
+
+    #[error("no snapshot with name `{0}` exists")]
+    NotFound(String),
+}
+
+impl IntoResponse for SnapshotError {
+    fn into_response(self) -> Response {
+        let status_code = match self {
+            SnapshotError::CreateFailed(_) => StatusCode::BAD_GATEWAY,
+            SnapshotError::NotFound(_) => StatusCode::NOT_FOUND,
+        };
+
+        (status_code, Json(self)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::start::snapshot::tests::config_manager;
+    use crate::commands::start::snapshot::SnapshotSource;
+    use std::sync::Arc;
+    use url::Url;
+
+    #[tokio::test]
+    async fn archive_is_downloaded() {
+        let runtime_dir = tempfile::tempdir().unwrap();
+        let source = Arc::new(SnapshotSource {
+            prometheus_url: Url::parse("http://localhost:9090/prometheus").unwrap(),
+            tsdb_dir: runtime_dir.path().join("data"),
+            runtime_dir: runtime_dir.path().to_path_buf(),
+            config_manager: config_manager(),
+        });
+
+        let name = "20231115T101010Z-2be650b6d019eb54";
+        let archive = source.archive_path(name).unwrap();
+        std::fs::create_dir_all(archive.parent().unwrap()).unwrap();
+        std::fs::write(&archive, vec![7; CHUNK_SIZE + 10]).unwrap();
+
+        let response = download_snapshot(State(source.clone()), Path(name.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_LENGTH], "65546");
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body.len(), CHUNK_SIZE + 10);
+
+        let err = download_snapshot(State(source), Path("20231115T000000Z-0".to_string()))
+            .await
+            .expect_err("snapshot does not exist");
+        assert!(matches!(err, SnapshotError::NotFound(_)));
+    }
+}
@@ -0,0 +1,12 @@
+use crate::commands::start::targets::SharedTargetMonitor;
+use autometrics::autometrics;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum::Json;
+
+/// List the scrape targets of the local Prometheus instance and their health,
+/// including the error of the last scrape of the targets that are down.
+#[autometrics]
+pub(crate) async fn list_targets(target_monitor: State<SharedTargetMonitor>) -> impl IntoResponse {
+    Json(target_monitor.summary())
+}
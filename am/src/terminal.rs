@@ -1,4 +1,9 @@
+use crate::commands::start::supervisor::RestartCounts;
+use crate::commands::start::targets::Transition;
+use crate::interactive;
 use anyhow::Result;
+use autometrics_am::alertmanager::{Alert, AlertStatus};
+use autometrics_am::prometheus::TargetHealth;
 use itertools::Itertools;
 use std::collections::HashMap;
 use std::io::Write;
@@ -7,29 +12,44 @@ use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 use tokio::sync::watch::Receiver;
 use tracing::info;
 
-pub(crate) fn wait_and_print_urls(mut rx: Receiver<HashMap<&'static str, String>>) {
+/// Print the URLs once they are available. If `restarts_rx` is provided, the
+/// URLs will be printed again whenever one of the processes is restarted,
+/// including the amount of times it has been restarted.
+pub(crate) fn wait_and_print_urls(
+    mut rx: Receiver<HashMap<&'static str, String>>,
+    restarts_rx: Option<Receiver<RestartCounts>>,
+) {
     tokio::spawn(async move {
         // wait a second until all other log messages (invoked in belows `select!`) are printed
         // Prometheus and Pushgateway usually dont take longer than a second to start so this should be good
         tokio::time::sleep(Duration::from_secs(1)).await;
 
-        match rx.wait_for(|map| !map.is_empty()).await {
-            Ok(map) => {
-                let _ = print_urls(&map);
-            }
+        let map = match rx.wait_for(|map| !map.is_empty()).await {
+            Ok(map) => map.clone(),
             Err(err) => {
                 info!(?err, "failed to wait for urls");
+                return;
+            }
+        };
+
+        let Some(mut restarts_rx) = restarts_rx else {
+            let _ = print_urls(&map, &RestartCounts::new());
+            return;
+        };
+
+        loop {
+            let restarts = restarts_rx.borrow_and_update().clone();
+            let _ = print_urls(&map, &restarts);
+
+            if restarts_rx.changed().await.is_err() {
+                break;
             }
         }
     });
 }
 
-pub(crate) fn print_urls(map: &HashMap<&str, String>) -> Result<()> {
-    let length = map
-        .iter()
-        .map(|(name, _)| name.len() + 5)
-        .max()
-        .unwrap_or(0);
+pub(crate) fn print_urls(map: &HashMap<&str, String>, restarts: &RestartCounts) -> Result<()> {
+    let length = map.keys().map(|name| name.len() + 5).max().unwrap_or(0);
 
     let mut stdout = StandardStream::stdout(ColorChoice::Always);
 
@@ -51,9 +71,105 @@ pub(crate) fn print_urls(map: &HashMap<&str, String>) -> Result<()> {
         write!(stdout, "  {:width$}", name, width = length)?;
 
         stdout.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bold(false))?;
-        writeln!(stdout, "  {}", url)?;
+        write!(stdout, "  {}", url)?;
+
+        match restarts.get(name) {
+            Some(&count) if count > 0 => {
+                stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)))?;
+                let times = if count == 1 { "time" } else { "times" };
+                writeln!(stdout, "  (restarted {count} {times})")?;
+            }
+            _ => writeln!(stdout)?,
+        }
+    }
+
+    writeln!(stdout)?;
+    Ok(())
+}
+
+/// Print an alert notification received from Alertmanager, including its
+/// labels and summary.
+pub(crate) fn print_alert(alert: &Alert) -> Result<()> {
+    // The dashboard shows the firing alerts itself.
+    if interactive::is_output_captured() {
+        return Ok(());
+    }
+
+    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+
+    let (status, color) = match alert.status {
+        AlertStatus::Firing => ("FIRING", Color::Red),
+        AlertStatus::Resolved => ("RESOLVED", Color::Green),
+    };
+
+    stdout.set_color(ColorSpec::new().set_fg(Some(color)).set_bold(true))?;
+    write!(stdout, "\n  {status} ")?;
+
+    stdout.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bold(true))?;
+    write!(stdout, "{}", alert.name())?;
+
+    let labels = alert
+        .labels
+        .iter()
+        .filter(|(name, _)| *name != "alertname")
+        .map(|(name, value)| format!("{name}={value}"))
+        .join(" ");
+    stdout.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bold(false))?;
+    writeln!(stdout, "  {labels}")?;
+
+    let summary = alert
+        .annotations
+        .get("summary")
+        .or_else(|| alert.annotations.get("description"));
+    if let Some(summary) = summary {
+        writeln!(stdout, "  {summary}")?;
+    }
+
+    let since = match alert.status {
+        AlertStatus::Firing => format!("since {}", alert.starts_at),
+        AlertStatus::Resolved => format!("from {} until {}", alert.starts_at, alert.ends_at),
+    };
+    stdout.set_color(ColorSpec::new().set_dimmed(true))?;
+    writeln!(stdout, "  {since}")?;
+
+    stdout.reset()?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+/// Print a scrape target that went down, including the error of the last
+/// scrape, or that came back up.
+pub(crate) fn print_target_transition(transition: &Transition) -> Result<()> {
+    // The dashboard shows the health of the targets itself.
+    if interactive::is_output_captured() {
+        return Ok(());
+    }
+
+    let mut stdout = StandardStream::stdout(ColorChoice::Auto);
+    let target = &transition.target;
+
+    let (health, color) = match target.health {
+        TargetHealth::Up => ("UP", Color::Green),
+        TargetHealth::Down => ("DOWN", Color::Red),
+        TargetHealth::Unknown => ("UNKNOWN", Color::Yellow),
+    };
+
+    stdout.set_color(ColorSpec::new().set_fg(Some(color)).set_bold(true))?;
+    write!(stdout, "\n  {health} ")?;
+
+    stdout.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bold(true))?;
+    write!(stdout, "{}", target.job)?;
+
+    stdout.set_color(ColorSpec::new().set_fg(Some(Color::White)).set_bold(false))?;
+    writeln!(stdout, "  {}", target.scrape_url)?;
+
+    match (&target.last_error, transition.previous) {
+        (Some(error), _) => writeln!(stdout, "  {error}")?,
+        (None, Some(TargetHealth::Down)) => writeln!(stdout, "  Scraped successfully again")?,
+        (None, _) => {}
     }
 
+    stdout.reset()?;
     writeln!(stdout)?;
     Ok(())
 }
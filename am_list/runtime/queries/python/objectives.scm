@@ -0,0 +1,10 @@
+;; Objectives are created with `Objective("name", success_rate=..., latency=...)`,
+;; either directly in the `objective=` argument of the decorator or as a separate
+;; variable. The arguments are read from the @objective.args capture, since they
+;; can be given positionally or as keyword arguments.
+((call
+   function: [(identifier) @objective.type
+              (attribute
+                attribute: (identifier) @objective.type)]
+   arguments: (argument_list) @objective.args)
+ (#eq? @objective.type "Objective"))
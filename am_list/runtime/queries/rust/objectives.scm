@@ -0,0 +1,15 @@
+;; Objectives are created with `Objective::new("name")`, and configured by
+;; chaining calls to `.success_rate(...)` and `.latency(...)`. The chained calls
+;; wrap the constructor call, so they are found by walking up the tree from the
+;; @objective.name capture instead.
+((call_expression
+   function: (scoped_identifier
+               path: [(identifier) @objective.type
+                      (scoped_identifier
+                        name: (identifier) @objective.type)]
+               name: (identifier) @objective.constructor)
+   arguments: (arguments
+                .
+                (string_literal) @objective.name))
+ (#eq? @objective.type "Objective")
+ (#eq? @objective.constructor "new"))
@@ -18,6 +18,8 @@ use thiserror::Error;
 use tree_sitter::{LanguageError, QueryError};
 
 const FUNC_NAME_CAPTURE: &str = "func.name";
+const OBJECTIVE_NAME_CAPTURE: &str = "objective.name";
+const OBJECTIVE_ARGS_CAPTURE: &str = "objective.args";
 
 /// The identifier of a function in the form of an "expected" autometrics label.
 ///
@@ -137,6 +139,47 @@ impl Display for FunctionInfo {
     }
 }
 
+/// An autometrics objective declared in the source code, such as
+/// `Objective::new("api").success_rate(ObjectivePercentile::P99_9)` in Rust.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ObjectiveInfo {
+    /// The name of the objective, as it appears in the `objective_name` label.
+    pub name: String,
+    /// The percentage of calls that need to succeed, such as `99.9`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub success_rate: Option<String>,
+    /// The latency target of the objective.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub latency: Option<ObjectiveLatency>,
+    /// The location of the declaration of the objective.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub location: Option<Location>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ObjectiveLatency {
+    /// The latency threshold in seconds, such as `0.25`, as it appears in the
+    /// `objective_latency_threshold` label.
+    pub threshold: String,
+    /// The percentage of calls that need to be faster than the threshold, such
+    /// as `99`.
+    pub percentile: String,
+}
+
+/// Convert the name of an `ObjectivePercentile` variant, such as `P99_9`, into
+/// the percentile it represents.
+pub(crate) fn objective_percentile(variant: &str) -> Option<String> {
+    let percentile = variant.strip_prefix('P')?.replace('_', ".");
+    percentile.parse::<f64>().is_ok().then_some(percentile)
+}
+
+/// Convert the name of an `ObjectiveLatency` variant, such as `Ms250`, into the
+/// threshold in seconds.
+pub(crate) fn objective_latency_threshold(variant: &str) -> Option<String> {
+    let millis: u32 = variant.strip_prefix("Ms")?.parse().ok()?;
+    Some((f64::from(millis) / 1000.0).to_string())
+}
+
 /// Trait to implement to claim "Language support" for am_list.
 ///
 /// This means we can both list all autometricized functions in a project, and
@@ -207,6 +250,14 @@ pub trait ListAmFunctions {
     }
 }
 
+/// Trait to implement to support listing the objectives declared in a project.
+pub trait ListAmObjectives {
+    /// List all the objectives declared under the given project.
+    fn list_objectives(&mut self, project_root: &Path) -> Result<Vec<ObjectiveInfo>>;
+    /// List all the objectives declared in the given source code.
+    fn list_objectives_in_single_file(&mut self, source_code: &str) -> Result<Vec<ObjectiveInfo>>;
+}
+
 /// Instrument a file, adding autometrics annotations as necessary.
 ///
 /// Each language is responsible to reuse its queries/create additonal queries to add the
@@ -372,6 +423,53 @@ pub fn list_single_project_functions(
     Ok(res)
 }
 
+pub fn list_all_project_objectives(
+    root: &Path,
+) -> Result<BTreeMap<PathBuf, (Language, Vec<ObjectiveInfo>)>> {
+    let projects = find_project_roots(root)?;
+    let mut res: BTreeMap<PathBuf, (Language, Vec<ObjectiveInfo>)> = BTreeMap::new();
+    let mut errors = Vec::new();
+
+    for (path, language) in projects {
+        info!(
+            "Listing objectives in {} (Language: {})",
+            path.display(),
+            language
+        );
+        match list_single_project_objectives(&path, language) {
+            Ok(objectives) => res
+                .entry(path)
+                .or_insert_with(|| (language, Vec::new()))
+                .1
+                .extend(objectives),
+            Err(err) => errors.push((path, err)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(AmlError::Projects(errors));
+    }
+
+    Ok(res)
+}
+
+pub fn list_single_project_objectives(
+    root: &Path,
+    language: Language,
+) -> Result<Vec<ObjectiveInfo>> {
+    let mut implementor: Box<dyn ListAmObjectives> = match language {
+        Language::Rust => Box::new(crate::rust::Impl {}),
+        Language::Python => Box::new(crate::python::Impl {}),
+        Language::Go | Language::Typescript => {
+            info!("Listing objectives is not supported for {language} projects yet");
+            return Ok(Vec::new());
+        }
+    };
+    let mut res = implementor.list_objectives(root)?;
+    res.sort();
+    Ok(res)
+}
+
 pub fn instrument_all_project_files(
     root: &Path,
     exclude_patterns: &ignore::gitignore::Gitignore,
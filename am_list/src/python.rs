@@ -1,8 +1,10 @@
 mod queries;
 
-use crate::{FunctionInfo, InstrumentFile, ListAmFunctions, Result};
+use crate::{
+    FunctionInfo, InstrumentFile, ListAmFunctions, ListAmObjectives, ObjectiveInfo, Result,
+};
 use log::debug;
-use queries::{AllFunctionsQuery, AmImportQuery, AmQuery};
+use queries::{AllFunctionsQuery, AmImportQuery, AmQuery, ObjectiveQuery};
 use rayon::prelude::*;
 use std::{
     collections::HashSet,
@@ -169,6 +171,36 @@ impl ListAmFunctions for Impl {
     }
 }
 
+impl ListAmObjectives for Impl {
+    fn list_objectives(&mut self, project_root: &Path) -> Result<Vec<ObjectiveInfo>> {
+        let query = ObjectiveQuery::try_new()?;
+        let project_files = Self::list_files(project_root, None);
+
+        let mut result: Vec<ObjectiveInfo> = project_files
+            .par_iter()
+            .filter_map(|path| {
+                let source = read_to_string(path).ok()?;
+                let file_name = PathBuf::from(path)
+                    .strip_prefix(project_root)
+                    .expect("path comes from a project_root WalkDir")
+                    .to_str()
+                    .expect("file_name is a valid path as it is part of `path`")
+                    .to_string();
+                query.list_objectives(&file_name, &source).ok()
+            })
+            .flatten()
+            .collect();
+
+        result.sort();
+        Ok(result)
+    }
+
+    fn list_objectives_in_single_file(&mut self, source_code: &str) -> Result<Vec<ObjectiveInfo>> {
+        let query = ObjectiveQuery::try_new()?;
+        query.list_objectives("<single file>", source_code)
+    }
+}
+
 impl InstrumentFile for Impl {
     fn instrument_source_code(&mut self, source: &str) -> Result<String> {
         const DEF_LEN: usize = "def ".len();
@@ -1,5 +1,9 @@
-use crate::{AmlError, FunctionInfo, Location, Result, FUNC_NAME_CAPTURE};
-use tree_sitter::{Parser, Query};
+use crate::{
+    objective_latency_threshold, objective_percentile, AmlError, FunctionInfo, Location,
+    ObjectiveInfo, ObjectiveLatency, Result, FUNC_NAME_CAPTURE, OBJECTIVE_ARGS_CAPTURE,
+};
+use log::warn;
+use tree_sitter::{Node, Parser, Query};
 use tree_sitter_python::language;
 
 const IMPORT_ALIAS_CAPTURE: &str = "import.alias";
@@ -217,3 +221,129 @@ impl AllFunctionsQuery {
             .collect::<std::result::Result<Vec<_>, _>>()
     }
 }
+
+/// Query wrapper for "all objectives declared in source"
+#[derive(Debug)]
+pub(super) struct ObjectiveQuery {
+    query: Query,
+    /// Index of the capture for the argument list of the `Objective` call.
+    objective_args_idx: u32,
+}
+
+impl ObjectiveQuery {
+    /// Failible constructor.
+    ///
+    /// The constructor only fails if the given tree-sitter query does not have the
+    /// necessary named captures.
+    pub fn try_new() -> Result<Self> {
+        let query = Query::new(
+            language(),
+            include_str!("../../runtime/queries/python/objectives.scm"),
+        )?;
+        let objective_args_idx = query
+            .capture_index_for_name(OBJECTIVE_ARGS_CAPTURE)
+            .ok_or_else(|| AmlError::MissingNamedCapture(OBJECTIVE_ARGS_CAPTURE.to_string()))?;
+        Ok(Self {
+            query,
+            objective_args_idx,
+        })
+    }
+
+    pub fn list_objectives(&self, file_name: &str, source: &str) -> Result<Vec<ObjectiveInfo>> {
+        let mut parser = new_parser()?;
+        let parsed_source = parser.parse(source, None).ok_or(AmlError::Parsing)?;
+
+        let mut cursor = tree_sitter::QueryCursor::new();
+        let objectives = cursor
+            .matches(&self.query, parsed_source.root_node(), source.as_bytes())
+            .filter_map(|m| {
+                let args = m.nodes_for_capture_index(self.objective_args_idx).next()?;
+                objective_from_arguments(args, file_name, source)
+            })
+            .collect();
+
+        Ok(objectives)
+    }
+}
+
+/// Read the objective from the arguments of `Objective(name, success_rate, latency)`,
+/// which can be given either positionally or as keyword arguments.
+fn objective_from_arguments(args: Node, file_name: &str, source: &str) -> Option<ObjectiveInfo> {
+    let mut name = None;
+    let mut success_rate = None;
+    let mut latency = None;
+
+    let mut walker = args.walk();
+    let mut position = 0;
+    for argument in args.named_children(&mut walker) {
+        let (keyword, value) = if argument.kind() == "keyword_argument" {
+            let keyword = argument
+                .child_by_field_name("name")?
+                .utf8_text(source.as_bytes())
+                .ok()?;
+            (keyword, argument.child_by_field_name("value")?)
+        } else {
+            let keyword = match position {
+                0 => "name",
+                1 => "success_rate",
+                2 => "latency",
+                _ => continue,
+            };
+            position += 1;
+            (keyword, argument)
+        };
+
+        match keyword {
+            "name" => name = Some(value),
+            "success_rate" => success_rate = Some(value),
+            "latency" => latency = Some(value),
+            _ => {}
+        }
+    }
+
+    let name_node = name.filter(|node| node.kind() == "string")?;
+    let name = name_node
+        .utf8_text(source.as_bytes())
+        .ok()?
+        .trim_matches(|c| c == '"' || c == '\'');
+
+    let objective = ObjectiveInfo {
+        name: name.to_string(),
+        success_rate: success_rate
+            .and_then(|node| objective_percentile(&last_attribute(node, source)?)),
+        latency: latency.and_then(|node| {
+            let mut walker = node.walk();
+            let values: Vec<String> = node
+                .named_children(&mut walker)
+                .filter_map(|value| last_attribute(value, source))
+                .collect();
+            match values.as_slice() {
+                [threshold, percentile] => Some(ObjectiveLatency {
+                    threshold: objective_latency_threshold(threshold)?,
+                    percentile: objective_percentile(percentile)?,
+                }),
+                _ => None,
+            }
+        }),
+        location: Some(Location::from((
+            file_name,
+            name_node.start_position(),
+            name_node.end_position(),
+        ))),
+    };
+
+    if objective.success_rate.is_none() && objective.latency.is_none() {
+        warn!(
+            "Objective {} in {file_name} has no success rate or latency target",
+            objective.name
+        );
+    }
+
+    Some(objective)
+}
+
+/// The last part of a dotted name, such as `P99_9` in `ObjectivePercentile.P99_9`.
+fn last_attribute(node: Node, source: &str) -> Option<String> {
+    let text = node.utf8_text(source.as_bytes()).ok()?;
+    Some(text.rsplit('.').next().unwrap_or(text).to_string())
+}
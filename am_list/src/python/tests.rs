@@ -4,7 +4,7 @@
 //! language to then merge the sets so that functions that get detected by both
 //! queries have their information merged.
 
-use crate::{Location, Position, Range};
+use crate::{Location, ObjectiveLatency, Position, Range};
 
 use super::*;
 use pretty_assertions::assert_eq;
@@ -263,3 +263,58 @@ fn instrument_multiple() {
     let actual = implementation.instrument_source_code(source).unwrap();
     assert_eq!(&actual, expected);
 }
+
+#[test]
+fn detect_objectives() {
+    let source = r#"
+        from autometrics import autometrics
+        from autometrics.objectives import Objective, ObjectiveLatency, ObjectivePercentile
+
+        API_SLO = Objective(
+            "api",
+            success_rate=ObjectivePercentile.P99_9,
+            latency=(ObjectiveLatency.Ms250, ObjectivePercentile.P99),
+        )
+
+        @autometrics(objective=API_SLO)
+        def list_users():
+            return []
+
+        @autometrics(objective=Objective('batch', ObjectivePercentile.P95))
+        def run_batch():
+            return None
+        "#;
+
+    let list = ObjectiveQuery::try_new()
+        .unwrap()
+        .list_objectives(FILE_NAME, source)
+        .unwrap();
+
+    let api = ObjectiveInfo {
+        name: "api".to_string(),
+        success_rate: Some("99.9".to_string()),
+        latency: Some(ObjectiveLatency {
+            threshold: "0.25".to_string(),
+            percentile: "99".to_string(),
+        }),
+        location: Some(Location {
+            file: FILE_NAME.to_string(),
+            range: Range {
+                start: Position {
+                    line: 5,
+                    column: 12,
+                },
+                end: Position {
+                    line: 5,
+                    column: 17,
+                },
+            },
+        }),
+    };
+
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0], api);
+    assert_eq!(list[1].name, "batch");
+    assert_eq!(list[1].success_rate, Some("95".to_string()));
+    assert_eq!(list[1].latency, None);
+}
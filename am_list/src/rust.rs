@@ -1,7 +1,9 @@
 mod queries;
 
-use self::queries::{AllFunctionsQuery, AmQuery};
-use crate::{FunctionInfo, InstrumentFile, ListAmFunctions, Result};
+use self::queries::{AllFunctionsQuery, AmQuery, ObjectiveQuery};
+use crate::{
+    FunctionInfo, InstrumentFile, ListAmFunctions, ListAmObjectives, ObjectiveInfo, Result,
+};
 use log::debug;
 use rayon::prelude::*;
 use std::{
@@ -206,6 +208,36 @@ impl ListAmFunctions for Impl {
     }
 }
 
+impl ListAmObjectives for Impl {
+    fn list_objectives(&mut self, project_root: &Path) -> Result<Vec<ObjectiveInfo>> {
+        let query = ObjectiveQuery::try_new()?;
+        let source_mod_pairs = Self::list_files_and_modules(project_root, None);
+
+        let mut result: Vec<ObjectiveInfo> = source_mod_pairs
+            .par_iter()
+            .filter_map(|(path, _module)| {
+                let source = read_to_string(path).ok()?;
+                let file_name = PathBuf::from(path)
+                    .strip_prefix(project_root)
+                    .expect("path comes from a project_root WalkDir")
+                    .to_str()
+                    .expect("file_name is a valid path as it is part of `path`")
+                    .to_string();
+                query.list_objectives(&file_name, &source).ok()
+            })
+            .flatten()
+            .collect();
+
+        result.sort();
+        Ok(result)
+    }
+
+    fn list_objectives_in_single_file(&mut self, source_code: &str) -> Result<Vec<ObjectiveInfo>> {
+        let query = ObjectiveQuery::try_new()?;
+        query.list_objectives("<single file>", source_code)
+    }
+}
+
 impl InstrumentFile for Impl {
     fn instrument_source_code(&mut self, source: &str) -> Result<String> {
         let mut locations = self.list_all_functions_in_single_file(source)?;
@@ -1,4 +1,7 @@
-use crate::{AmlError, FunctionInfo, Location, Result, FUNC_NAME_CAPTURE};
+use crate::{
+    objective_latency_threshold, objective_percentile, AmlError, FunctionInfo, Location,
+    ObjectiveInfo, ObjectiveLatency, Result, FUNC_NAME_CAPTURE, OBJECTIVE_NAME_CAPTURE,
+};
 use log::{trace, warn};
 use tree_sitter::{Node, Parser, Query};
 use tree_sitter_rust::language;
@@ -649,3 +652,125 @@ impl AllFunctionsQuery {
             .collect()
     }
 }
+
+/// Query wrapper for "all objectives declared in source"
+#[derive(Debug)]
+pub(super) struct ObjectiveQuery {
+    query: Query,
+    /// Index of the capture for the string literal containing the objective name.
+    objective_name_idx: u32,
+}
+
+impl ObjectiveQuery {
+    /// Failible constructor.
+    ///
+    /// The constructor only fails if the given tree-sitter query does not have the
+    /// necessary named captures.
+    pub fn try_new() -> Result<Self> {
+        let query = Query::new(
+            language(),
+            include_str!("../../runtime/queries/rust/objectives.scm"),
+        )?;
+
+        let objective_name_idx = query
+            .capture_index_for_name(OBJECTIVE_NAME_CAPTURE)
+            .ok_or_else(|| AmlError::MissingNamedCapture(OBJECTIVE_NAME_CAPTURE.into()))?;
+
+        Ok(Self {
+            query,
+            objective_name_idx,
+        })
+    }
+
+    pub fn list_objectives(&self, file_name: &str, source: &str) -> Result<Vec<ObjectiveInfo>> {
+        let mut parser = new_parser()?;
+        let parsed_source = parser.parse(source, None).ok_or(AmlError::Parsing)?;
+
+        let mut cursor = tree_sitter::QueryCursor::new();
+        let objectives = cursor
+            .matches(&self.query, parsed_source.root_node(), source.as_bytes())
+            .filter_map(|m| {
+                let name_node = m.nodes_for_capture_index(self.objective_name_idx).next()?;
+                let name = name_node
+                    .utf8_text(source.as_bytes())
+                    .ok()?
+                    .trim_matches('"');
+
+                let mut objective = ObjectiveInfo {
+                    name: name.to_string(),
+                    location: Some(Location::from((
+                        file_name,
+                        name_node.start_position(),
+                        name_node.end_position(),
+                    ))),
+                    ..Default::default()
+                };
+
+                // The `Objective::new` call is the value of the field expression
+                // of every chained method call.
+                let mut call = name_node.parent()?.parent()?;
+                while let Some((method, arguments)) = chained_method_call(call, source) {
+                    match (method.as_str(), arguments.as_slice()) {
+                        ("success_rate", [percentile]) => {
+                            objective.success_rate = objective_percentile(percentile);
+                        }
+                        ("latency", [threshold, percentile]) => {
+                            objective.latency = objective_latency_threshold(threshold)
+                                .zip(objective_percentile(percentile))
+                                .map(|(threshold, percentile)| ObjectiveLatency {
+                                    threshold,
+                                    percentile,
+                                });
+                        }
+                        _ => trace!("Ignoring call to {method} on objective {name}"),
+                    }
+
+                    call = call.parent()?.parent()?;
+                }
+
+                if objective.success_rate.is_none() && objective.latency.is_none() {
+                    warn!("Objective {name} in {file_name} has no success rate or latency target");
+                }
+
+                Some(objective)
+            })
+            .collect();
+
+        Ok(objectives)
+    }
+}
+
+/// If `node` is the receiver of a method call, such as `receiver` in
+/// `receiver.method(A::B, C)`, return the name of the method and the last path
+/// segment of every argument (`["B", "C"]`).
+fn chained_method_call(node: Node, source: &str) -> Option<(String, Vec<String>)> {
+    let field_expression = node.parent()?;
+    if field_expression.kind() != "field_expression"
+        || field_expression.child_by_field_name("value")?.id() != node.id()
+    {
+        return None;
+    }
+
+    let call = field_expression.parent()?;
+    if call.kind() != "call_expression" {
+        return None;
+    }
+
+    let method = field_expression
+        .child_by_field_name("field")?
+        .utf8_text(source.as_bytes())
+        .ok()?
+        .to_string();
+
+    let arguments_node = call.child_by_field_name("arguments")?;
+    let mut walker = arguments_node.walk();
+    let arguments = arguments_node
+        .named_children(&mut walker)
+        .filter_map(|argument| {
+            let text = argument.utf8_text(source.as_bytes()).ok()?;
+            Some(text.rsplit("::").next().unwrap_or(text).trim().to_string())
+        })
+        .collect();
+
+    Some((method, arguments))
+}
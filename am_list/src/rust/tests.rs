@@ -4,7 +4,7 @@
 //! language to then merge the sets so that functions that get detected by both
 //! queries have their information merged.
 
-use crate::{Location, Position, Range};
+use crate::{Location, ObjectiveLatency, Position, Range};
 
 use super::*;
 use pretty_assertions::assert_eq;
@@ -561,3 +561,53 @@ fn instrument_multiple() {
     let actual = implementation.instrument_source_code(source).unwrap();
     assert_eq!(&actual, expected);
 }
+
+#[test]
+fn detect_objectives() {
+    let source = r#"
+        use autometrics::objectives::{Objective, ObjectiveLatency, ObjectivePercentile};
+
+        const API_SLO: Objective = Objective::new("api")
+            .success_rate(ObjectivePercentile::P99_9)
+            .latency(ObjectiveLatency::Ms250, ObjectivePercentile::P99);
+
+        const BATCH_SLO: Objective =
+            autometrics::objectives::Objective::new("batch").success_rate(ObjectivePercentile::P95);
+
+        #[autometrics(objective = API_SLO)]
+        fn handler() {}
+        "#;
+
+    let list = ObjectiveQuery::try_new()
+        .unwrap()
+        .list_objectives(FILE_NAME, source)
+        .unwrap();
+
+    let api = ObjectiveInfo {
+        name: "api".to_string(),
+        success_rate: Some("99.9".to_string()),
+        latency: Some(ObjectiveLatency {
+            threshold: "0.25".to_string(),
+            percentile: "99".to_string(),
+        }),
+        location: Some(Location {
+            file: FILE_NAME.to_string(),
+            range: Range {
+                start: Position {
+                    line: 3,
+                    column: 50,
+                },
+                end: Position {
+                    line: 3,
+                    column: 55,
+                },
+            },
+        }),
+    };
+
+    assert_eq!(list.len(), 2);
+    assert_eq!(list[0], api);
+    assert_eq!(list[1].name, "batch");
+    assert_eq!(list[1].success_rate, Some("95".to_string()));
+    assert_eq!(list[1].latency, None);
+}
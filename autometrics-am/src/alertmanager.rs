@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The configuration of Alertmanager. Only the parts that am uses are
+/// included.
+#[derive(Debug, Serialize)]
+pub struct Config {
+    pub route: Route,
+    pub receivers: Vec<Receiver>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Route {
+    /// The receiver that all alerts are sent to, unless a more specific route
+    /// matches.
+    pub receiver: String,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub group_by: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Receiver {
+    pub name: String,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub webhook_configs: Vec<WebhookConfig>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookConfig {
+    pub url: String,
+
+    /// Also notify the webhook once an alert is resolved.
+    pub send_resolved: bool,
+}
+
+/// The payload Alertmanager sends to webhook receivers. Only the parts that am
+/// uses are included.
+///
+/// See: https://prometheus.io/docs/alerting/latest/configuration/#webhook_config
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    pub receiver: String,
+    pub status: AlertStatus,
+    pub alerts: Vec<Alert>,
+
+    #[serde(default)]
+    pub group_labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub common_labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    Firing,
+    Resolved,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Alert {
+    pub status: AlertStatus,
+
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+
+    pub starts_at: String,
+    /// Set to `0001-01-01T00:00:00Z` for alerts that are still firing.
+    pub ends_at: String,
+
+    #[serde(rename = "generatorURL", default)]
+    pub generator_url: String,
+    #[serde(default)]
+    pub fingerprint: String,
+}
+
+impl Alert {
+    /// The name of the alerting rule that triggered this alert.
+    pub fn name(&self) -> &str {
+        self.labels
+            .get("alertname")
+            .map(String::as_str)
+            .unwrap_or("unnamed alert")
+    }
+}
@@ -1,6 +1,9 @@
 use crate::parser::endpoint_parser;
+use crate::prometheus::Scheme;
 use serde::de::Error;
 use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 use url::Url;
@@ -15,12 +18,77 @@ pub struct AmConfig {
     #[serde(rename = "endpoint")]
     pub endpoints: Option<Vec<Endpoint>>,
 
+    /// Jobs whose targets are read from files, using Prometheus' file-based
+    /// service discovery.
+    pub file_sd: Option<Vec<FileSdJob>>,
+
+    /// Additional Prometheus rule files, with recording or alerting rules,
+    /// which are loaded alongside the autometrics rules. Relative paths are
+    /// resolved against the directory containing the am.toml file.
+    pub rule_files: Option<Vec<PathBuf>>,
+
+    /// Use this rules file instead of the autometrics rules that are included
+    /// in am, such as the one generated by `am rules generate`. Relative paths
+    /// are resolved against the directory containing the am.toml file.
+    pub rules_file: Option<PathBuf>,
+
+    /// The Prometheus version to use. It will be downloaded if am has not
+    /// downloaded it already.
+    pub prometheus_version: Option<String>,
+
+    /// Use this Prometheus binary instead of downloading Prometheus. Relative
+    /// paths are resolved against the directory containing the am.toml file.
+    pub prometheus_binary: Option<PathBuf>,
+
     /// Startup the pushgateway.
     pub pushgateway_enabled: Option<bool>,
 
+    /// The Pushgateway version to use.
+    pub pushgateway_version: Option<String>,
+
+    /// Use this Pushgateway binary instead of downloading Pushgateway.
+    pub pushgateway_binary: Option<PathBuf>,
+
+    /// The URL template used to download Prometheus and Pushgateway, for
+    /// example to use a mirror instead of GitHub. Supports the `{org}`,
+    /// `{repo}`, `{version}` and `{package}` placeholders.
+    pub release_url_template: Option<String>,
+
+    /// The amount of times a failed download of Prometheus or Pushgateway is
+    /// retried.
+    pub download_retries: Option<u32>,
+
+    /// Scrape Docker containers with the `autometrics.port` label.
+    pub docker_discovery: Option<bool>,
+
     /// The default scrape interval for all Prometheus endpoints.
     #[serde(default, with = "humantime_serde::option")]
     pub prometheus_scrape_interval: Option<Duration>,
+
+    /// The port Prometheus will listen on. Use 0 to select a free port.
+    pub prometheus_port: Option<u16>,
+
+    /// How long Prometheus keeps its data, for example `30d`.
+    pub prometheus_retention_time: Option<String>,
+
+    /// The maximum amount of disk space Prometheus uses for its data, for
+    /// example `10GB`.
+    pub prometheus_retention_size: Option<String>,
+
+    /// The port Pushgateway will listen on. Use 0 to select a free port.
+    pub pushgateway_port: Option<u16>,
+
+    /// Startup Alertmanager, which receives the alerts of Prometheus.
+    pub alertmanager_enabled: Option<bool>,
+
+    /// The Alertmanager version to use.
+    pub alertmanager_version: Option<String>,
+
+    /// Use this Alertmanager binary instead of downloading Alertmanager.
+    pub alertmanager_binary: Option<PathBuf>,
+
+    /// The port Alertmanager will listen on. Use 0 to select a free port.
+    pub alertmanager_port: Option<u16>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -31,6 +99,16 @@ pub struct Endpoint {
     #[serde(deserialize_with = "parse_maybe_shorthand")]
     pub url: Url,
 
+    /// Additional URLs that are scraped as part of the same job, for example
+    /// replicas of the same service. These must use the same scheme and path
+    /// as `url`.
+    #[serde(
+        default,
+        deserialize_with = "parse_maybe_shorthand_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub additional_urls: Vec<Url>,
+
     /// The job name as it appears in Prometheus. This value will be added to
     /// the scraped metrics as a label.
     pub job_name: Option<String>,
@@ -40,6 +118,111 @@ pub struct Endpoint {
     /// The scrape interval for this endpoint.
     #[serde(default, with = "humantime_serde::option")]
     pub prometheus_scrape_interval: Option<Duration>,
+
+    /// Additional options which are passed on to the Prometheus scrape config
+    /// of this endpoint.
+    #[serde(flatten)]
+    pub scrape_options: ScrapeOptions,
+}
+
+/// Prometheus scrape options that can be set per endpoint. These map directly
+/// onto the options of the same name in the Prometheus `scrape_config`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ScrapeOptions {
+    /// The timeout for scraping this endpoint. Must not be larger than the
+    /// scrape interval.
+    #[serde(
+        default,
+        with = "humantime_serde::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub scrape_timeout: Option<Duration>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub basic_auth: Option<BasicAuth>,
+
+    /// A token which is sent in the `Authorization` header as a bearer token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_config: Option<TlsConfig>,
+
+    /// Query parameters that are added to the URL when scraping.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, Vec<String>>,
+
+    /// Labels that are added to all metrics scraped from this endpoint.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+
+    /// Relabeling rules that are applied to the scraped metrics before they
+    /// are stored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metric_relabel_configs: Vec<RelabelConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct BasicAuth {
+    pub username: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+
+    /// Read the password from this file instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_file: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct TlsConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_file: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cert_file: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_name: Option<String>,
+
+    /// Disable validation of the server certificate, which is useful for local
+    /// services using self-signed certificates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insecure_skip_verify: Option<bool>,
+}
+
+/// A Prometheus relabel config. See
+/// <https://prometheus.io/docs/prometheus/latest/configuration/configuration/#relabel_config>
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct RelabelConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub source_labels: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub separator: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_label: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modulus: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+
+    /// For example `replace`, `keep`, `drop` or `labeldrop`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
 }
 
 fn parse_maybe_shorthand<'de, D: Deserializer<'de>>(input: D) -> Result<Url, D::Error> {
@@ -47,21 +230,79 @@ fn parse_maybe_shorthand<'de, D: Deserializer<'de>>(input: D) -> Result<Url, D::
     endpoint_parser(&input_str).map_err(Error::custom)
 }
 
+fn parse_maybe_shorthand_list<'de, D: Deserializer<'de>>(input: D) -> Result<Vec<Url>, D::Error> {
+    let inputs: Vec<String> = Deserialize::deserialize(input)?;
+    inputs
+        .iter()
+        .map(|input| endpoint_parser(input).map_err(Error::custom))
+        .collect()
+}
+
+/// A job whose targets are read from JSON or YAML files, in the format of
+/// Prometheus' `file_sd_configs`. Prometheus watches these files, so any
+/// changes to the targets are picked up without restarting am.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct FileSdJob {
+    /// The job name as it appears in Prometheus.
+    pub job_name: String,
+
+    /// The files containing the targets. Relative paths are resolved against
+    /// the directory containing the am.toml file. The last path segment may
+    /// contain a glob, such as `targets/*.json`.
+    pub files: Vec<PathBuf>,
+
+    /// How often Prometheus re-reads the files, in addition to watching them.
+    #[serde(
+        default,
+        with = "humantime_serde::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub refresh_interval: Option<Duration>,
+
+    /// The path that is scraped on every target. Defaults to `/metrics`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_path: Option<String>,
+
+    /// The scheme that is used to scrape the targets. Defaults to `http`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheme: Option<Scheme>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub honor_labels: Option<bool>,
+
+    /// The scrape interval for this job.
+    #[serde(
+        default,
+        with = "humantime_serde::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub prometheus_scrape_interval: Option<Duration>,
+
+    #[serde(flatten)]
+    pub scrape_options: ScrapeOptions,
+}
+
 /// If the user specified an endpoint using args, then use those.
 /// Otherwise, use the endpoint configured in the config file. And
 /// fallback to an empty list if neither are configured.
 pub fn endpoints_from_first_input(args: Vec<Url>, config: Option<Vec<Endpoint>>) -> Vec<Endpoint> {
-    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    // The counter is local to this call so that calling this function again
+    // with the same input (for example when the config file is reloaded)
+    // results in the same job names.
+    let counter = AtomicUsize::new(0);
 
     if !args.is_empty() {
         args.into_iter()
             .map(|url| {
-                let num = COUNTER.fetch_add(1, Ordering::SeqCst);
+                let num = counter.fetch_add(1, Ordering::SeqCst);
                 Endpoint {
                     url,
+                    additional_urls: vec![],
                     job_name: Some(format!("am_{num}")),
                     honor_labels: Some(false),
                     prometheus_scrape_interval: None,
+                    scrape_options: ScrapeOptions::default(),
                 }
             })
             .collect()
@@ -70,14 +311,16 @@ pub fn endpoints_from_first_input(args: Vec<Url>, config: Option<Vec<Endpoint>>)
             .into_iter()
             .map(|endpoint| {
                 let job_name = endpoint.job_name.unwrap_or_else(|| {
-                    format!("am_{num}", num = COUNTER.fetch_add(1, Ordering::SeqCst))
+                    format!("am_{num}", num = counter.fetch_add(1, Ordering::SeqCst))
                 });
 
                 Endpoint {
                     url: endpoint.url,
+                    additional_urls: endpoint.additional_urls,
                     job_name: Some(job_name),
                     honor_labels: endpoint.honor_labels,
                     prometheus_scrape_interval: endpoint.prometheus_scrape_interval,
+                    scrape_options: endpoint.scrape_options,
                 }
             })
             .collect()
@@ -1,3 +1,4 @@
+pub mod alertmanager;
 pub mod config;
 pub mod parser;
 pub mod prometheus;
@@ -1,4 +1,6 @@
-use serde::Serialize;
+use crate::config;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::time::Duration;
 
 #[derive(Debug, Serialize)]
@@ -7,6 +9,20 @@ pub struct Config {
     pub scrape_configs: Vec<ScrapeConfig>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub rule_files: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alerting: Option<AlertingConfig>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AlertingConfig {
+    pub alertmanagers: Vec<AlertmanagerConfig>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AlertmanagerConfig {
+    pub static_configs: Vec<StaticScrapeConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_prefix: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -19,9 +35,15 @@ pub struct GlobalConfig {
 #[derive(Debug, Serialize)]
 pub struct ScrapeConfig {
     pub job_name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub static_configs: Vec<StaticScrapeConfig>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub file_sd_configs: Vec<FileSdConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub metrics_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub scheme: Option<Scheme>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub honor_labels: Option<bool>,
 
     #[serde(
@@ -30,16 +52,284 @@ pub struct ScrapeConfig {
         skip_serializing_if = "Option::is_none"
     )]
     pub scrape_interval: Option<Duration>,
+
+    #[serde(
+        default,
+        with = "humantime_serde::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub scrape_timeout: Option<Duration>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub basic_auth: Option<BasicAuth>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization: Option<Authorization>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_config: Option<TlsConfig>,
+
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, Vec<String>>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub relabel_configs: Vec<RelabelConfig>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub metric_relabel_configs: Vec<RelabelConfig>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct StaticScrapeConfig {
     pub targets: Vec<String>,
+
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileSdConfig {
+    pub files: Vec<String>,
+
+    #[serde(
+        default,
+        with = "humantime_serde::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub refresh_interval: Option<Duration>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BasicAuth {
+    pub username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password_file: Option<String>,
+}
+
+impl From<config::BasicAuth> for BasicAuth {
+    fn from(basic_auth: config::BasicAuth) -> Self {
+        Self {
+            username: basic_auth.username,
+            password: basic_auth.password,
+            password_file: basic_auth.password_file,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Authorization {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub credentials: String,
+}
+
+impl Authorization {
+    pub fn bearer(token: String) -> Self {
+        Self {
+            kind: "Bearer".to_string(),
+            credentials: token,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
+pub struct TlsConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cert_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub insecure_skip_verify: Option<bool>,
+}
+
+impl From<config::TlsConfig> for TlsConfig {
+    fn from(tls_config: config::TlsConfig) -> Self {
+        Self {
+            ca_file: tls_config.ca_file,
+            cert_file: tls_config.cert_file,
+            key_file: tls_config.key_file,
+            server_name: tls_config.server_name,
+            insecure_skip_verify: tls_config.insecure_skip_verify,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RelabelConfig {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub source_labels: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub separator: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modulus: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+}
+
+impl RelabelConfig {
+    /// A relabel config which sets `label` to `value` on every target.
+    pub fn set_label(label: String, value: String) -> Self {
+        Self {
+            target_label: Some(label),
+            replacement: Some(value),
+            action: Some("replace".to_string()),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<config::RelabelConfig> for RelabelConfig {
+    fn from(relabel_config: config::RelabelConfig) -> Self {
+        Self {
+            source_labels: relabel_config.source_labels,
+            separator: relabel_config.separator,
+            target_label: relabel_config.target_label,
+            regex: relabel_config.regex,
+            modulus: relabel_config.modulus,
+            replacement: relabel_config.replacement,
+            action: relabel_config.action,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Scheme {
     Http,
     Https,
 }
+
+/// A Prometheus rules file, containing groups of recording and alerting rules.
+#[derive(Debug, Serialize)]
+pub struct RuleFile {
+    pub groups: Vec<RuleGroup>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuleGroup {
+    pub name: String,
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Rule {
+    Recording {
+        record: String,
+        expr: String,
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        labels: BTreeMap<String, String>,
+    },
+    Alerting {
+        alert: String,
+        expr: String,
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        labels: BTreeMap<String, String>,
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        annotations: BTreeMap<String, String>,
+    },
+}
+
+/// The response of the query APIs of Prometheus, `/api/v1/query` and
+/// `/api/v1/query_range`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum QueryResponse {
+    Success {
+        data: QueryData,
+    },
+    Error {
+        #[serde(rename = "errorType")]
+        error_type: String,
+        error: String,
+    },
+}
+
+/// The result of a query, which depends on the type of the expression.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "resultType", content = "result", rename_all = "lowercase")]
+pub enum QueryData {
+    Vector(Vec<InstantSeries>),
+    Matrix(Vec<RangeSeries>),
+    Scalar(Sample),
+    String(Sample),
+}
+
+/// A series of an instant vector, with a single sample.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstantSeries {
+    pub metric: BTreeMap<String, String>,
+    pub value: Sample,
+}
+
+/// A series of a range vector, with all samples in the range.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RangeSeries {
+    pub metric: BTreeMap<String, String>,
+    pub values: Vec<Sample>,
+}
+
+/// A sample, consisting of a unix timestamp in seconds and the value. The
+/// value is encoded as a string, since JSON cannot represent `NaN` and
+/// infinity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sample(pub f64, pub String);
+
+impl Sample {
+    /// The value of the sample, which is `NaN` if it is not a number.
+    pub fn value(&self) -> f64 {
+        self.1.parse().unwrap_or(f64::NAN)
+    }
+}
+
+/// The response of the targets API of Prometheus, `/api/v1/targets`.
+#[derive(Debug, Deserialize)]
+pub struct TargetsResponse {
+    pub data: TargetsData,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetsData {
+    pub active_targets: Vec<ActiveTarget>,
+}
+
+/// A target that Prometheus is scraping.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveTarget {
+    /// The name of the scrape config, which is the job name for the targets
+    /// of am.
+    pub scrape_pool: String,
+    pub scrape_url: String,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    pub health: TargetHealth,
+    /// The error of the last scrape, which is empty if it succeeded.
+    #[serde(default)]
+    pub last_error: String,
+    /// When the target was last scraped, as an RFC 3339 timestamp.
+    #[serde(default)]
+    pub last_scrape: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetHealth {
+    Up,
+    Down,
+    /// The target has not been scraped yet.
+    Unknown,
+}